@@ -1,4 +1,31 @@
-#[derive(Debug)]
+use crate::lexer::Pos;
+
+/// Type is a type specifier of a declaration.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+#[cfg_attr(feature = "serde", serde(tag = "type", content = "value"))]
+pub enum Type {
+    Int,
+    Char,
+    Long,
+    Void,
+}
+
+impl Type {
+    /// size returns a number of bytes a value of the type takes.
+    pub fn size(&self) -> usize {
+        match self {
+            Type::Char => 1,
+            Type::Int => 4,
+            Type::Long => 8,
+            Type::Void => 0,
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+#[cfg_attr(feature = "serde", serde(tag = "type", content = "value"))]
 pub enum BinOp {
     BitwiseXor,
     BitwiseOr,
@@ -20,19 +47,25 @@ pub enum BinOp {
     BitwiseRightShift,
 }
 
-#[derive(Debug)]
+#[derive(Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+#[cfg_attr(feature = "serde", serde(tag = "type", content = "value"))]
 pub enum Const {
     Int(i64),
 }
 
-#[derive(Debug)]
+#[derive(Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+#[cfg_attr(feature = "serde", serde(tag = "type", content = "value"))]
 pub enum UnOp {
     Negation,
     BitwiseComplement,
     LogicalNegation,
 }
 
-#[derive(Debug)]
+#[derive(Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+#[cfg_attr(feature = "serde", serde(tag = "type", content = "value"))]
 pub enum IncOrDec {
     Inc(OperationSide),
     Dec(OperationSide),
@@ -47,13 +80,17 @@ impl IncOrDec {
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+#[cfg_attr(feature = "serde", serde(tag = "type", content = "value"))]
 pub enum OperationSide {
     Prefix,
     Postfix,
 }
 
-#[derive(Debug)]
+#[derive(Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+#[cfg_attr(feature = "serde", serde(tag = "type", content = "value"))]
 pub enum AssignmentOp {
     Plus,
     Sub,
@@ -67,76 +104,192 @@ pub enum AssignmentOp {
     BitXor,
 }
 
-#[derive(Debug)]
+/// Exp is an expression, the last field of each variant is a position of its token:
+/// a name of a variable or a function, a literal, or an operator.
+/// Assignments point at the assigned variable rather than at `=`.
+#[derive(Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+#[cfg_attr(feature = "serde", serde(tag = "type", content = "value"))]
 pub enum Exp {
-    Assign(String, Box<Exp>),
-    Var(String),
-    Const(Const),
-    IncOrDec(String, IncOrDec),
-    UnOp(UnOp, Box<Exp>),
-    BinOp(BinOp, Box<Exp>, Box<Exp>),
-    AssignOp(String, AssignmentOp, Box<Exp>),
-    CondExp(Box<Exp>, Box<Exp>, Box<Exp>),
-    FuncCall(String, Vec<Exp>),
+    Assign(String, Box<Exp>, Pos),
+    Var(String, Pos),
+    Const(Const, Pos),
+    IncOrDec(String, IncOrDec, Pos),
+    UnOp(UnOp, Box<Exp>, Pos),
+    BinOp(BinOp, Box<Exp>, Box<Exp>, Pos),
+    AssignOp(String, AssignmentOp, Box<Exp>, Pos),
+    /// CondExp is `a ? b : c`, the position points at `?`.
+    CondExp(Box<Exp>, Box<Exp>, Box<Exp>, Pos),
+    FuncCall(String, Vec<Exp>, Pos),
+    /// AddressOf is an address of a function, `&f`.
+    AddressOf(String, Pos),
+    /// Comma evaluates both expressions and results in the right one.
+    Comma(Box<Exp>, Box<Exp>, Pos),
 }
 
+impl Exp {
+    /// pos returns a position of the expression's token.
+    pub fn pos(&self) -> &Pos {
+        match self {
+            Exp::Assign(.., pos)
+            | Exp::Var(.., pos)
+            | Exp::Const(.., pos)
+            | Exp::IncOrDec(.., pos)
+            | Exp::UnOp(.., pos)
+            | Exp::BinOp(.., pos)
+            | Exp::AssignOp(.., pos)
+            | Exp::CondExp(.., pos)
+            | Exp::FuncCall(.., pos)
+            | Exp::AddressOf(.., pos)
+            | Exp::Comma(.., pos) => pos,
+        }
+    }
+}
+
+/// Statement is a statement, `pos` is a position of its first token.
+#[derive(Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+#[cfg_attr(feature = "serde", serde(tag = "type", content = "value"))]
 pub enum Statement {
     Return {
         exp: Exp,
+        pos: Pos,
     },
     Exp {
         exp: Option<Exp>,
+        pos: Pos,
     },
     Conditional {
         cond_expr: Exp,
         if_block: Box<Statement>,
         else_block: Option<Box<Statement>>,
+        pos: Pos,
     },
     Compound {
         list: Option<Vec<BlockItem>>,
+        pos: Pos,
     },
     For {
         exp1: Option<Exp>,
         exp2: Exp,
         exp3: Option<Exp>,
         statement: Box<Statement>,
+        pos: Pos,
     },
     ForDecl {
         decl: Declaration,
         exp2: Exp,
         exp3: Option<Exp>,
         statement: Box<Statement>,
+        pos: Pos,
     },
     While {
         exp: Exp,
         statement: Box<Statement>,
+        pos: Pos,
     },
     Do {
         statement: Box<Statement>,
         exp: Exp,
+        pos: Pos,
+    },
+    /// Switch jumps to a case which value is equal to the expression.
+    Switch {
+        exp: Exp,
+        cases: Vec<SwitchCase>,
+        pos: Pos,
+    },
+    Break {
+        pos: Pos,
     },
-    Break,
-    Continue,
+    Continue {
+        pos: Pos,
+    },
+}
+
+impl Statement {
+    /// pos returns a position of the statement's first token.
+    pub fn pos(&self) -> &Pos {
+        match self {
+            Statement::Return { pos, .. }
+            | Statement::Exp { pos, .. }
+            | Statement::Conditional { pos, .. }
+            | Statement::Compound { pos, .. }
+            | Statement::For { pos, .. }
+            | Statement::ForDecl { pos, .. }
+            | Statement::While { pos, .. }
+            | Statement::Do { pos, .. }
+            | Statement::Switch { pos, .. }
+            | Statement::Break { pos }
+            | Statement::Continue { pos } => pos,
+        }
+    }
+}
+
+/// SwitchCase is a `case` label with statements up to the next label,
+/// `value` of the `default` label is None.
+#[derive(Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct SwitchCase {
+    pub value: Option<i64>,
+    pub body: Vec<BlockItem>,
 }
 
+/// Declaration introduces a variable, `pos` is a position of its name.
+#[derive(Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+#[cfg_attr(feature = "serde", serde(tag = "type", content = "value"))]
 pub enum Declaration {
-    Declare { name: String, exp: Option<Exp> },
+    Declare {
+        ty: Type,
+        name: String,
+        exp: Option<Exp>,
+        pos: Pos,
+    },
+    /// FuncPointer is a pointer to a function which takes
+    /// `parameters` number of `int` parameters, `int (*fp)(int, int)`.
+    FuncPointer {
+        name: String,
+        parameters: usize,
+        exp: Option<Exp>,
+        pos: Pos,
+    },
 }
 
+#[derive(Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+#[cfg_attr(feature = "serde", serde(tag = "type", content = "value"))]
 pub enum BlockItem {
     Statement(Statement),
     Declaration(Declaration),
 }
 
+/// Parameter is a parameter of a function, `pos` is a position of its name.
+#[derive(Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct Parameter {
+    pub ty: Type,
+    pub name: String,
+    pub pos: Pos,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct FuncDecl {
+    pub return_type: Type,
     pub name: String,
-    pub parameters: Vec<String>,
+    pub parameters: Vec<Parameter>,
     pub blocks: Option<Vec<BlockItem>>,
 }
 
+#[derive(Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+#[cfg_attr(feature = "serde", serde(tag = "type", content = "value"))]
 pub enum TopLevel {
     Function(FuncDecl),
     Declaration(Declaration),
 }
 
+#[derive(Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct Program(pub Vec<TopLevel>);
@@ -0,0 +1,423 @@
+use super::{
+    AssignmentOp, BinOp, BlockItem, Const, Declaration, Exp, FuncDecl, IncOrDec, Program,
+    Statement, TopLevel, Type, UnOp,
+};
+use std::fmt::{self, Display, Formatter};
+
+const INDENT: &str = "    ";
+
+impl Display for Program {
+    /// fmt renders the program back in C,
+    /// every body of a statement is put in braces.
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        for (i, item) in self.0.iter().enumerate() {
+            if i > 0 {
+                writeln!(f)?;
+            }
+
+            match item {
+                TopLevel::Function(func) => write!(f, "{}", func)?,
+                TopLevel::Declaration(decl) => writeln!(f, "{};", decl)?,
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl Display for FuncDecl {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        let params = self
+            .parameters
+            .iter()
+            .map(|p| format!("{} {}", p.ty, p.name))
+            .collect::<Vec<_>>()
+            .join(", ");
+        write!(f, "{} {}({})", self.return_type, self.name, params)?;
+
+        match &self.blocks {
+            Some(blocks) => {
+                writeln!(f, " {{")?;
+                write_blocks(f, blocks, 1)?;
+                writeln!(f, "}}")
+            }
+            None => writeln!(f, ";"),
+        }
+    }
+}
+
+impl Display for Statement {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write_statement(f, self, 0)
+    }
+}
+
+fn write_blocks(f: &mut Formatter, blocks: &[BlockItem], level: usize) -> fmt::Result {
+    for block in blocks {
+        match block {
+            BlockItem::Declaration(decl) => writeln!(f, "{}{};", INDENT.repeat(level), decl)?,
+            BlockItem::Statement(st) => write_statement(f, st, level)?,
+        }
+    }
+
+    Ok(())
+}
+
+/// write_body writes a statement of a loop or a condition without surrounding braces.
+fn write_body(f: &mut Formatter, st: &Statement, level: usize) -> fmt::Result {
+    match st {
+        Statement::Compound { list, .. } => {
+            write_blocks(f, list.as_deref().unwrap_or_default(), level)
+        }
+        st => write_statement(f, st, level),
+    }
+}
+
+fn write_statement(f: &mut Formatter, st: &Statement, level: usize) -> fmt::Result {
+    let indent = INDENT.repeat(level);
+    match st {
+        Statement::Return { exp, .. } => writeln!(f, "{}return {};", indent, exp),
+        Statement::Exp { exp: Some(exp), .. } => writeln!(f, "{}{};", indent, exp),
+        Statement::Exp { exp: None, .. } => writeln!(f, "{};", indent),
+        Statement::Conditional {
+            cond_expr,
+            if_block,
+            else_block,
+            ..
+        } => {
+            writeln!(f, "{}if ({}) {{", indent, cond_expr)?;
+            write_body(f, if_block, level + 1)?;
+            if let Some(else_block) = else_block {
+                writeln!(f, "{}}} else {{", indent)?;
+                write_body(f, else_block, level + 1)?;
+            }
+            writeln!(f, "{}}}", indent)
+        }
+        Statement::Compound { list, .. } => {
+            writeln!(f, "{}{{", indent)?;
+            write_blocks(f, list.as_deref().unwrap_or_default(), level + 1)?;
+            writeln!(f, "{}}}", indent)
+        }
+        Statement::For {
+            exp1,
+            exp2,
+            exp3,
+            statement,
+            ..
+        } => {
+            writeln!(
+                f,
+                "{}for ({}; {}; {}) {{",
+                indent,
+                OptExp(exp1),
+                exp2,
+                OptExp(exp3)
+            )?;
+            write_body(f, statement, level + 1)?;
+            writeln!(f, "{}}}", indent)
+        }
+        Statement::ForDecl {
+            decl,
+            exp2,
+            exp3,
+            statement,
+            ..
+        } => {
+            writeln!(f, "{}for ({}; {}; {}) {{", indent, decl, exp2, OptExp(exp3))?;
+            write_body(f, statement, level + 1)?;
+            writeln!(f, "{}}}", indent)
+        }
+        Statement::While { exp, statement, .. } => {
+            writeln!(f, "{}while ({}) {{", indent, exp)?;
+            write_body(f, statement, level + 1)?;
+            writeln!(f, "{}}}", indent)
+        }
+        Statement::Do { statement, exp, .. } => {
+            writeln!(f, "{}do {{", indent)?;
+            write_body(f, statement, level + 1)?;
+            writeln!(f, "{}}} while ({});", indent, exp)
+        }
+        Statement::Switch { exp, cases, .. } => {
+            writeln!(f, "{}switch ({}) {{", indent, exp)?;
+            for case in cases {
+                match case.value {
+                    Some(value) => writeln!(f, "{}case {}:", indent, value)?,
+                    None => writeln!(f, "{}default:", indent)?,
+                }
+                write_blocks(f, &case.body, level + 1)?;
+            }
+            writeln!(f, "{}}}", indent)
+        }
+        Statement::Break { .. } => writeln!(f, "{}break;", indent),
+        Statement::Continue { .. } => writeln!(f, "{}continue;", indent),
+    }
+}
+
+impl Display for Declaration {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        let exp = match self {
+            Declaration::Declare { ty, name, exp, .. } => {
+                write!(f, "{} {}", ty, name)?;
+                exp
+            }
+            Declaration::FuncPointer {
+                name,
+                parameters,
+                exp,
+                ..
+            } => {
+                write!(
+                    f,
+                    "int (*{})({})",
+                    name,
+                    vec!["int"; *parameters].join(", ")
+                )?;
+                exp
+            }
+        };
+
+        match exp {
+            Some(exp) => write!(f, " = {}", AssignmentExp(exp)),
+            None => Ok(()),
+        }
+    }
+}
+
+impl Display for Exp {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self {
+            Exp::Assign(name, exp, _) => write!(f, "{} = {}", name, AssignmentExp(exp)),
+            Exp::Var(name, _) => write!(f, "{}", name),
+            Exp::Const(Const::Int(value), _) => write!(f, "{}", value),
+            Exp::IncOrDec(name, op, _) => match op {
+                IncOrDec::Inc(..) if op.is_postfix() => write!(f, "{}++", name),
+                IncOrDec::Dec(..) if op.is_postfix() => write!(f, "{}--", name),
+                IncOrDec::Inc(..) => write!(f, "++{}", name),
+                IncOrDec::Dec(..) => write!(f, "--{}", name),
+            },
+            // a nested operand is parenthesized so `-(-a)` isn't read back as `--a`
+            Exp::UnOp(op, exp, _) => match **exp {
+                Exp::UnOp(..) | Exp::IncOrDec(..) | Exp::Const(Const::Int(i64::MIN..=-1), _) => {
+                    write!(f, "{}({})", op, exp)
+                }
+                _ => write!(f, "{}{}", op, Operand(exp)),
+            },
+            Exp::BinOp(op, exp1, exp2, _) => {
+                write!(f, "{} {} {}", Operand(exp1), op, Operand(exp2))
+            }
+            Exp::AssignOp(name, op, exp, _) => write!(f, "{} {} {}", name, op, AssignmentExp(exp)),
+            Exp::CondExp(cond, exp1, exp2, _) => write!(
+                f,
+                "{} ? {} : {}",
+                Operand(cond),
+                Operand(exp1),
+                Operand(exp2)
+            ),
+            Exp::FuncCall(name, params, _) => {
+                let params = params
+                    .iter()
+                    .map(|p| AssignmentExp(p).to_string())
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                write!(f, "{}({})", name, params)
+            }
+            Exp::AddressOf(name, _) => write!(f, "&{}", name),
+            Exp::Comma(exp1, exp2, _) => write!(f, "{}, {}", exp1, exp2),
+        }
+    }
+}
+
+/// Operand is a part of an expression,
+/// it's put in parentheses unless it's a single term.
+struct Operand<'a>(&'a Exp);
+
+impl Display for Operand<'_> {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self.0 {
+            Exp::Assign(..)
+            | Exp::BinOp(..)
+            | Exp::AssignOp(..)
+            | Exp::CondExp(..)
+            | Exp::Comma(..) => write!(f, "({})", self.0),
+            exp => write!(f, "{}", exp),
+        }
+    }
+}
+
+/// AssignmentExp is an expression which can't contain the comma operator on the top level,
+/// e.g. an argument of a function call.
+struct AssignmentExp<'a>(&'a Exp);
+
+impl Display for AssignmentExp<'_> {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self.0 {
+            Exp::Comma(..) => write!(f, "({})", self.0),
+            exp => write!(f, "{}", exp),
+        }
+    }
+}
+
+/// OptExp is an optional clause of `for` statement.
+struct OptExp<'a>(&'a Option<Exp>);
+
+impl Display for OptExp<'_> {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self.0 {
+            Some(exp) => write!(f, "{}", exp),
+            None => Ok(()),
+        }
+    }
+}
+
+impl Display for Type {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        let ty = match self {
+            Type::Int => "int",
+            Type::Char => "char",
+            Type::Long => "long",
+            Type::Void => "void",
+        };
+
+        write!(f, "{}", ty)
+    }
+}
+
+impl Display for BinOp {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        let op = match self {
+            BinOp::BitwiseXor => "^",
+            BinOp::BitwiseOr => "|",
+            BinOp::BitwiseAnd => "&",
+            BinOp::Addition => "+",
+            BinOp::Sub => "-",
+            BinOp::Multiplication => "*",
+            BinOp::Division => "/",
+            BinOp::Modulo => "%",
+            BinOp::And => "&&",
+            BinOp::Or => "||",
+            BinOp::Equal => "==",
+            BinOp::NotEqual => "!=",
+            BinOp::LessThan => "<",
+            BinOp::LessThanOrEqual => "<=",
+            BinOp::GreaterThan => ">",
+            BinOp::GreaterThanOrEqual => ">=",
+            BinOp::BitwiseLeftShift => "<<",
+            BinOp::BitwiseRightShift => ">>",
+        };
+
+        write!(f, "{}", op)
+    }
+}
+
+impl Display for UnOp {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        let op = match self {
+            UnOp::Negation => "-",
+            UnOp::BitwiseComplement => "~",
+            UnOp::LogicalNegation => "!",
+        };
+
+        write!(f, "{}", op)
+    }
+}
+
+impl Display for AssignmentOp {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        let op = match self {
+            AssignmentOp::Plus => "+=",
+            AssignmentOp::Sub => "-=",
+            AssignmentOp::Mul => "*=",
+            AssignmentOp::Div => "/=",
+            AssignmentOp::Mod => "%=",
+            AssignmentOp::BitLeftShift => "<<=",
+            AssignmentOp::BitRightShift => ">>=",
+            AssignmentOp::BitAnd => "&=",
+            AssignmentOp::BitOr => "|=",
+            AssignmentOp::BitXor => "^=",
+        };
+
+        write!(f, "{}", op)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{lexer::Lexer, parser};
+
+    #[test]
+    fn program() {
+        let program = r"
+            int g = 2;
+            int inc(int a);
+            int main() {
+                int (*fp)(int) = &inc;
+                int sum = -g;
+                for (int i = 0; i < 10; i++) if (i % 2) sum += i; else continue;
+                while (sum > 100) { sum = sum / 2; }
+                do --sum; while (!sum);
+                switch (sum) { case 1: break; default: ; }
+                { sum = (sum, 3) ? fp(sum * (1 + 2), (g, 1)) : ~sum; }
+                return sum;
+            }
+        ";
+        let expected = "\
+int g = 2;
+
+int inc(int a);
+
+int main() {
+    int (*fp)(int) = &inc;
+    int sum = -g;
+    for (int i = 0; i < 10; i++) {
+        if (i % 2) {
+            sum += i;
+        } else {
+            continue;
+        }
+    }
+    while (sum > 100) {
+        sum = sum / 2;
+    }
+    do {
+        --sum;
+    } while (!sum);
+    switch (sum) {
+    case 1:
+        break;
+    default:
+        ;
+    }
+    {
+        sum = (sum, 3) ? fp(sum * (1 + 2), (g, 1)) : ~sum;
+    }
+    return sum;
+}
+";
+
+        let tokens = Lexer::new().lex_str(program).unwrap();
+        let ast = parser::parse(tokens).unwrap();
+        assert_eq!(ast.to_string(), expected);
+    }
+
+    #[test]
+    fn nested_unary_operators() {
+        let program = "int main() { int a = 1; a = -(-a); a = -(--a); a = !(!a); return ~(-a); }";
+        let expected = "\
+int main() {
+    int a = 1;
+    a = -(-a);
+    a = -(--a);
+    a = !(!a);
+    return ~(-a);
+}
+";
+
+        let tokens = Lexer::new().lex_str(program).unwrap();
+        let ast = parser::parse(tokens).unwrap();
+        assert_eq!(ast.to_string(), expected);
+
+        let tokens = Lexer::new().lex_str(expected).unwrap();
+        let ast = parser::parse(tokens).unwrap();
+        assert_eq!(ast.to_string(), expected);
+    }
+}
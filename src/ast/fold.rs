@@ -0,0 +1,243 @@
+use super::{BinOp, BlockItem, Const, Declaration, Exp, Program, Statement, TopLevel, UnOp};
+
+/// fold_constants replaces operations over constants by their results.
+///
+/// Operations which fail at runtime, like a division by zero,
+/// are left as they are so they still fail when the program runs.
+pub fn fold_constants(program: &mut Program) {
+    for item in &mut program.0 {
+        match item {
+            TopLevel::Declaration(decl) => fold_decl(decl),
+            TopLevel::Function(func) => {
+                if let Some(blocks) = &mut func.blocks {
+                    fold_blocks(blocks);
+                }
+            }
+        }
+    }
+}
+
+fn fold_blocks(blocks: &mut [BlockItem]) {
+    for block in blocks {
+        match block {
+            BlockItem::Declaration(decl) => fold_decl(decl),
+            BlockItem::Statement(st) => fold_statement(st),
+        }
+    }
+}
+
+fn fold_decl(decl: &mut Declaration) {
+    match decl {
+        Declaration::Declare { exp, .. } | Declaration::FuncPointer { exp, .. } => {
+            if let Some(exp) = exp {
+                fold_exp(exp);
+            }
+        }
+    }
+}
+
+fn fold_statement(st: &mut Statement) {
+    match st {
+        Statement::Return { exp, .. } => fold_exp(exp),
+        Statement::Exp { exp, .. } => {
+            if let Some(exp) = exp {
+                fold_exp(exp);
+            }
+        }
+        Statement::Conditional {
+            cond_expr,
+            if_block,
+            else_block,
+            ..
+        } => {
+            fold_exp(cond_expr);
+            fold_statement(if_block);
+            if let Some(else_block) = else_block {
+                fold_statement(else_block);
+            }
+        }
+        Statement::Compound { list, .. } => {
+            if let Some(list) = list {
+                fold_blocks(list);
+            }
+        }
+        Statement::For {
+            exp1,
+            exp2,
+            exp3,
+            statement,
+            ..
+        } => {
+            if let Some(exp1) = exp1 {
+                fold_exp(exp1);
+            }
+            fold_exp(exp2);
+            if let Some(exp3) = exp3 {
+                fold_exp(exp3);
+            }
+            fold_statement(statement);
+        }
+        Statement::ForDecl {
+            decl,
+            exp2,
+            exp3,
+            statement,
+            ..
+        } => {
+            fold_decl(decl);
+            fold_exp(exp2);
+            if let Some(exp3) = exp3 {
+                fold_exp(exp3);
+            }
+            fold_statement(statement);
+        }
+        Statement::While { exp, statement, .. } | Statement::Do { statement, exp, .. } => {
+            fold_exp(exp);
+            fold_statement(statement);
+        }
+        Statement::Switch { exp, cases, .. } => {
+            fold_exp(exp);
+            for case in cases {
+                fold_blocks(&mut case.body);
+            }
+        }
+        Statement::Break { .. } | Statement::Continue { .. } => (),
+    }
+}
+
+fn fold_exp(exp: &mut Exp) {
+    match exp {
+        Exp::Assign(_, exp, _) | Exp::AssignOp(_, _, exp, _) | Exp::UnOp(_, exp, _) => {
+            fold_exp(exp)
+        }
+        Exp::BinOp(_, exp1, exp2, _) | Exp::Comma(exp1, exp2, _) => {
+            fold_exp(exp1);
+            fold_exp(exp2);
+        }
+        Exp::CondExp(cond, exp1, exp2, _) => {
+            fold_exp(cond);
+            fold_exp(exp1);
+            fold_exp(exp2);
+        }
+        Exp::FuncCall(_, params, _) => {
+            for exp in params {
+                fold_exp(exp);
+            }
+        }
+        Exp::Var(..) | Exp::Const(..) | Exp::IncOrDec(..) | Exp::AddressOf(..) => (),
+    }
+
+    let value = match exp {
+        Exp::UnOp(op, exp, _) => constant(exp).map(|a| un_op(op, a)),
+        Exp::BinOp(op, exp1, exp2, _) => match (constant(exp1), constant(exp2)) {
+            (Some(a), Some(b)) => bin_op(op, a, b),
+            _ => None,
+        },
+        _ => None,
+    };
+
+    // the constant keeps the position of the operator it's folded from
+    if let Some(value) = value {
+        *exp = Exp::Const(Const::Int(value as i64), exp.pos().clone());
+    }
+}
+
+/// constant returns a value of the expression if it's a constant of `int` type.
+fn constant(exp: &Exp) -> Option<i32> {
+    match exp {
+        Exp::Const(Const::Int(value), _)
+            if *value >= i32::MIN as i64 && *value <= i32::MAX as i64 =>
+        {
+            Some(*value as i32)
+        }
+        _ => None,
+    }
+}
+
+fn un_op(op: &UnOp, a: i32) -> i32 {
+    match op {
+        UnOp::Negation => a.wrapping_neg(),
+        UnOp::BitwiseComplement => !a,
+        UnOp::LogicalNegation => (a == 0) as i32,
+    }
+}
+
+fn bin_op(op: &BinOp, a: i32, b: i32) -> Option<i32> {
+    let value = match op {
+        BinOp::Addition => a.wrapping_add(b),
+        BinOp::Sub => a.wrapping_sub(b),
+        BinOp::Multiplication => a.wrapping_mul(b),
+        // checked operations fail on a division by zero and on overflow
+        BinOp::Division => a.checked_div(b)?,
+        BinOp::Modulo => a.checked_rem(b)?,
+        BinOp::BitwiseXor => a ^ b,
+        BinOp::BitwiseOr => a | b,
+        BinOp::BitwiseAnd => a & b,
+        BinOp::BitwiseLeftShift if (0..32).contains(&b) => a.wrapping_shl(b as u32),
+        BinOp::BitwiseRightShift if (0..32).contains(&b) => a >> b,
+        BinOp::BitwiseLeftShift | BinOp::BitwiseRightShift => return None,
+        BinOp::And => (a != 0 && b != 0) as i32,
+        BinOp::Or => (a != 0 || b != 0) as i32,
+        BinOp::Equal => (a == b) as i32,
+        BinOp::NotEqual => (a != b) as i32,
+        BinOp::LessThan => (a < b) as i32,
+        BinOp::LessThanOrEqual => (a <= b) as i32,
+        BinOp::GreaterThan => (a > b) as i32,
+        BinOp::GreaterThanOrEqual => (a >= b) as i32,
+    };
+
+    Some(value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{lexer::Lexer, parser};
+
+    fn fold(exp: &str) -> Exp {
+        let tokens = Lexer::new().lex_str(exp).unwrap();
+        let (mut exp, _) = parser::parse_exp(tokens).unwrap();
+        fold_exp(&mut exp);
+        exp
+    }
+
+    #[test]
+    fn fold_operations() {
+        assert!(matches!(fold("2 + 3 * 4"), Exp::Const(Const::Int(14), _)));
+        assert!(matches!(
+            fold("-(1 << 3) | ~0 & 5"),
+            Exp::Const(Const::Int(-3), _)
+        ));
+        assert!(matches!(
+            fold("!(2 > 1) || 3 == 3"),
+            Exp::Const(Const::Int(1), _)
+        ));
+        assert!(matches!(
+            fold("a + 2 * 3"),
+            Exp::BinOp(BinOp::Addition, _, ref b, _) if matches!(**b, Exp::Const(Const::Int(6), _))
+        ));
+    }
+
+    #[test]
+    fn keep_runtime_errors() {
+        assert!(matches!(fold("1 / 0"), Exp::BinOp(BinOp::Division, ..)));
+        assert!(matches!(fold("1 % (2 - 2)"), Exp::BinOp(BinOp::Modulo, ..)));
+        assert!(matches!(
+            fold("1 << 32"),
+            Exp::BinOp(BinOp::BitwiseLeftShift, ..)
+        ));
+    }
+
+    #[test]
+    fn fold_program() {
+        let program = "int g = 2 * 3; int main() { int a = 1 + 1; return a + (4 - 1); }";
+        let tokens = Lexer::new().lex_str(program).unwrap();
+        let mut program = parser::parse(tokens).unwrap();
+        fold_constants(&mut program);
+
+        assert_eq!(
+            program.to_string(),
+            "int g = 6;\n\nint main() {\n    int a = 2;\n    return a + 3;\n}\n"
+        );
+    }
+}
@@ -0,0 +1,34 @@
+use super::Program;
+
+/// to_json serializes the program,
+/// every enum variant is an object with its name in a `type` field
+/// and its fields in a `value` one.
+pub fn to_json(program: &Program) -> String {
+    serde_json::to_string(program).expect("the AST is always serializable")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{lexer::Lexer, parser};
+
+    #[test]
+    fn program() {
+        let tokens = Lexer::new().lex_str("int main() { return 1 + a; }").unwrap();
+        let program = parser::parse(tokens).unwrap();
+
+        let json = to_json(&program);
+        let value: serde_json::Value = serde_json::from_str(&json).unwrap();
+
+        let func = &value[0];
+        assert_eq!(func["type"], "Function");
+        assert_eq!(func["value"]["name"], "main");
+        assert_eq!(func["value"]["return_type"]["type"], "Int");
+
+        let exp = &func["value"]["blocks"][0]["value"]["value"]["exp"];
+        assert_eq!(exp["type"], "BinOp");
+        assert_eq!(exp["value"][0]["type"], "Addition");
+        assert_eq!(exp["value"][2]["type"], "Var");
+        assert_eq!(exp["value"][2]["value"][1]["column"], 25);
+    }
+}
@@ -1,5 +1,12 @@
 mod ast;
+mod display;
+mod fold;
+#[cfg(feature = "serde")]
+mod json;
 pub mod visitor;
 
 pub use ast::*;
-pub use visitor::{Visitor};
+pub use fold::fold_constants;
+#[cfg(feature = "serde")]
+pub use json::to_json;
+pub use visitor::{walk_program, Visitor};
@@ -1,4 +1,4 @@
-use super::{BlockItem, Declaration, Exp, FuncDecl, Statement, TopLevel};
+use super::{BlockItem, Declaration, Exp, FuncDecl, Program, Statement, TopLevel};
 
 pub trait Visitor<'ast> {
     fn visit_global_item(&mut self, item: &'ast TopLevel) {
@@ -26,10 +26,17 @@ pub trait Visitor<'ast> {
     }
 }
 
+/// walk_program runs the visitor over every top level item of the program.
+pub fn walk_program<'ast, V: Visitor<'ast> + ?Sized>(v: &mut V, prog: &'ast Program) {
+    for item in &prog.0 {
+        v.visit_global_item(item);
+    }
+}
+
 pub fn visit_statement<'ast, V: Visitor<'ast> + ?Sized>(v: &mut V, st: &'ast Statement) {
     match st {
-        Statement::Return { exp } => v.visit_expr(exp),
-        Statement::Exp { exp } => {
+        Statement::Return { exp, .. } => v.visit_expr(exp),
+        Statement::Exp { exp, .. } => {
             if let Some(exp) = exp {
                 v.visit_expr(exp)
             }
@@ -38,6 +45,7 @@ pub fn visit_statement<'ast, V: Visitor<'ast> + ?Sized>(v: &mut V, st: &'ast Sta
             cond_expr,
             if_block,
             else_block,
+            ..
         } => {
             v.visit_expr(cond_expr);
             v.visit_statement(if_block);
@@ -45,7 +53,7 @@ pub fn visit_statement<'ast, V: Visitor<'ast> + ?Sized>(v: &mut V, st: &'ast Sta
                 v.visit_statement(else_block);
             }
         }
-        Statement::Compound { list } => match list {
+        Statement::Compound { list, .. } => match list {
             Some(list) => {
                 for block in list {
                     v.visit_block(block);
@@ -53,11 +61,11 @@ pub fn visit_statement<'ast, V: Visitor<'ast> + ?Sized>(v: &mut V, st: &'ast Sta
             }
             None => (),
         },
-        Statement::While { exp, statement } => {
+        Statement::While { exp, statement, .. } => {
             v.visit_expr(exp);
             v.visit_statement(statement);
         }
-        Statement::Do { statement, exp } => {
+        Statement::Do { statement, exp, .. } => {
             v.visit_statement(statement);
             v.visit_expr(exp);
         }
@@ -66,6 +74,7 @@ pub fn visit_statement<'ast, V: Visitor<'ast> + ?Sized>(v: &mut V, st: &'ast Sta
             exp2,
             exp3,
             statement,
+            ..
         } => {
             v.visit_decl(decl);
             v.visit_expr(exp2);
@@ -79,6 +88,7 @@ pub fn visit_statement<'ast, V: Visitor<'ast> + ?Sized>(v: &mut V, st: &'ast Sta
             exp2,
             exp3,
             statement,
+            ..
         } => {
             if let Some(exp1) = exp1 {
                 v.visit_expr(exp1);
@@ -89,30 +99,43 @@ pub fn visit_statement<'ast, V: Visitor<'ast> + ?Sized>(v: &mut V, st: &'ast Sta
             }
             v.visit_statement(statement);
         }
-        Statement::Break => (),
-        Statement::Continue => (),
+        Statement::Switch { exp, cases, .. } => {
+            v.visit_expr(exp);
+            for case in cases {
+                for block in &case.body {
+                    v.visit_block(block);
+                }
+            }
+        }
+        Statement::Break { .. } => (),
+        Statement::Continue { .. } => (),
     }
 }
 
 pub fn visit_expr<'ast, V: Visitor<'ast> + ?Sized>(v: &mut V, exp: &'ast Exp) {
     match exp {
-        Exp::BinOp(_, exp1, exp2) => {
+        Exp::BinOp(_, exp1, exp2, _) => {
             v.visit_expr(exp1);
             v.visit_expr(exp2);
         }
-        Exp::UnOp(_, exp) => v.visit_expr(exp),
-        Exp::Assign(_, exp) => v.visit_expr(exp),
-        Exp::AssignOp(_, _, exp) => v.visit_expr(exp),
-        Exp::CondExp(cond, exp1, exp2) => {
+        Exp::UnOp(_, exp, _) => v.visit_expr(exp),
+        Exp::Assign(_, exp, _) => v.visit_expr(exp),
+        Exp::AssignOp(_, _, exp, _) => v.visit_expr(exp),
+        Exp::CondExp(cond, exp1, exp2, _) => {
             v.visit_expr(cond);
             v.visit_expr(exp1);
             v.visit_expr(exp2);
         }
-        Exp::FuncCall(_, params) => {
+        Exp::FuncCall(_, params, _) => {
             for exp in params {
                 v.visit_expr(exp);
             }
         }
+        Exp::Comma(exp1, exp2, _) => {
+            v.visit_expr(exp1);
+            v.visit_expr(exp2);
+        }
+        Exp::AddressOf(..) => (),
         Exp::IncOrDec(..) => (),
         Exp::Var(..) => (),
         Exp::Const(..) => (),
@@ -128,7 +151,7 @@ pub fn visit_block<'ast, V: Visitor<'ast> + ?Sized>(v: &mut V, block: &'ast Bloc
 
 pub fn visit_decl<'ast, V: Visitor<'ast> + ?Sized>(v: &mut V, decl: &'ast Declaration) {
     match decl {
-        Declaration::Declare { exp, .. } => {
+        Declaration::Declare { exp, .. } | Declaration::FuncPointer { exp, .. } => {
             if let Some(exp) = exp {
                 v.visit_expr(exp)
             }
@@ -150,3 +173,41 @@ pub fn visit_global_item<'ast, V: Visitor<'ast> + ?Sized>(v: &mut V, item: &'ast
         TopLevel::Function(func) => visit_function(v, func),
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{lexer::Lexer, parser};
+
+    struct BinOps(usize);
+
+    impl<'ast> Visitor<'ast> for BinOps {
+        fn visit_expr(&mut self, exp: &'ast Exp) {
+            if let Exp::BinOp(..) = exp {
+                self.0 += 1;
+            }
+
+            visit_expr(self, exp);
+        }
+    }
+
+    #[test]
+    fn count_bin_ops() {
+        let program = r"
+            int g = 1 + 2;
+            int f(int a) { return a * (a - 1); }
+            int main() {
+                int sum = 0;
+                for (int i = 0; i < 10; i++)
+                    sum += f(i) % 3;
+                return sum;
+            }
+        ";
+        let tokens = Lexer::new().lex_str(program).unwrap();
+        let prog = parser::parse(tokens).unwrap();
+
+        let mut counter = BinOps(0);
+        walk_program(&mut counter, &prog);
+        assert_eq!(counter.0, 5);
+    }
+}
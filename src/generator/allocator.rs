@@ -1,44 +1,46 @@
 use super::asm::{Indirect, Offset, Part, Place, Register, RegisterX64, Size, Block, AsmX32};
-use crate::il::lifeinterval;
-use crate::il::tac;
+use crate::il::{cfg, lifeinterval, liveness, tac};
+use std::collections::hash_map::Entry;
 use std::collections::HashMap;
 
+/// REGISTERS are the ones which are handed out to temporaries.
+///
+/// `rax` and `rdx` are left out since they're taken by `idiv`, a result of a call
+/// and a return value, `r11` is a scratch register of indirect calls.
+const REGISTERS: &[RegisterX64] = {
+    use RegisterX64::*;
+    &[RCX, RSI, RDI, R8, R9, R10]
+};
+
 pub struct Allocator {
     m: HashMap<tac::ID, Place>,
     intervals: lifeinterval::LiveIntervals,
     pub stack_size: usize,
-    REGISTERS: &'static [RegisterX64],
 }
 
 impl Allocator {
+    /// new places ids of the function by a linear scan over their live intervals.
+    ///
+    /// When every register is taken the interval which ends the furthest
+    /// is spilled to the stack.
     pub fn new(ir: &tac::File, f: &tac::FuncDef) -> (Self, Block) {
         use RegisterX64::*;
         use Size::*;
 
-        let REGISTERS: &'static [RegisterX64] = {
-            if f.name == "main" {
-                &[RAX, RBX, RCX, RDX]
-            } else {
-                // let params_regs = ["edi", "esi", "edx", "ecx", "r8d", "r9d"];
-                match f.parameters.len() {
-                    6..=std::usize::MAX => &[RDI, RSI, RDX, RCX, R8, R9, RBX],
-                    5 => &[RDI, RSI, RDX, RCX, R8, RBX, RAX],
-                    4 => &[RDI, RSI, RDX, RCX, RAX],
-                    3 => &[RDI, RSI, RDX, RAX],
-                    2 => &[RDI, RSI, RAX],
-                    1 => &[RDI, RAX],
-                    0 => &[RAX],
-                    _ => unreachable!(),
-                }
-            }
-        };
-
-        let intervals = lifeinterval::LiveIntervals::new(&f.instructions);
-        let (mut s, mut stack_start) = Self::recognize_params(&f.parameters);
+        let cfg = cfg::build(f);
+        let intervals =
+            lifeinterval::LiveIntervals::from_liveness(f, &cfg, &liveness::liveness(f, &cfg));
+        let mut s = Self::recognize_params(&f.parameters);
+        let mut stack_start = 0;
 
         let mut params = Block::new();
         for (param, place) in s.iter_mut() {
-            stack_start += 4;
+            // a parameter passed on the stack already lives in memory
+            if matches!(place, Place::Indirect(..)) {
+                continue;
+            }
+
+            stack_start += Size::Doubleword.size();
             let stack = Place::Indirect(Indirect::new(
                 Register::Register(RegisterX64::RBP),
                 stack_start,
@@ -63,13 +65,22 @@ impl Allocator {
             );
         }
 
-        let mut free = REGISTERS.to_vec();
-        let mut allocated: HashMap<RegisterX64, tac::ID> = HashMap::new();
-        let used_registers = free.clone();
         let mut stack_ptr = stack_start;
-        for (index, tac::InstructionLine(i, id)) in f.instructions.iter().enumerate() {
-            if matches!(i, tac::Instruction::Alloc(..)) && f.ctx.is_variable(id.unwrap()) {
-                stack_ptr += 4;
+        let mut temporaries = Vec::new();
+        for tac::InstructionLine(i, id) in &f.instructions {
+            if matches!(id, Some(id) if f.ctx.is_pointer(*id)) {
+                // a function pointer doesn't fit the 32 bit registers
+                // so it always lives on the stack
+                if let Entry::Vacant(entry) = s.entry(id.unwrap()) {
+                    stack_ptr += Quadword.size();
+                    entry.insert(Place::Indirect(Indirect::new(
+                        Register::Register(RBP),
+                        stack_ptr,
+                        Quadword,
+                    )));
+                }
+            } else if matches!(i, tac::Instruction::Alloc(..)) && f.ctx.is_variable(id.unwrap()) {
+                stack_ptr += Doubleword.size();
                 s.insert(
                     id.unwrap(),
                     Place::Indirect(Indirect::new(
@@ -78,41 +89,62 @@ impl Allocator {
                         Doubleword,
                     )),
                 );
-            } else if let Some(id) = id {
-                allocated.retain(|reg, id| {
-                    if index > intervals.get(*id).end {
-                        free.push(reg.clone());
-                        false
+            } else if let Some(id) = id.filter(|id| !s.contains_key(id)) {
+                if !temporaries.contains(&id) {
+                    temporaries.push(id);
+                }
+            }
+        }
+        temporaries.sort_by_key(|id| intervals.get(*id).start);
+
+        let mut active: Vec<(tac::ID, RegisterX64)> = Vec::new();
+        let mut spilled = Vec::new();
+        for id in temporaries {
+            let start = intervals.get(id).start;
+            active.retain(|(active, _)| intervals.get(*active).end >= start);
+
+            let free = REGISTERS
+                .iter()
+                .find(|reg| active.iter().all(|(_, used)| used != *reg));
+            match free {
+                Some(reg) => active.push((id, reg.clone())),
+                None => {
+                    let (furthest, _) = active
+                        .iter()
+                        .enumerate()
+                        .max_by_key(|(_, (active, _))| intervals.get(*active).end)
+                        .unwrap();
+                    if intervals.get(active[furthest].0).end > intervals.get(id).end {
+                        let (victim, reg) = active.remove(furthest);
+                        spilled.push(victim);
+                        active.push((id, reg));
                     } else {
-                        true
+                        spilled.push(id);
                     }
-                });
-
-                if free.is_empty() {
-                    let reg = used_registers.first().unwrap();
-                    let id = allocated.remove(reg).unwrap();
-                    free.push(reg.clone());
-
-                    stack_ptr += 4;
-                    *s.get_mut(&id).unwrap() = Place::Indirect(Indirect::new(
-                        Register::Register(RBP),
-                        stack_ptr,
-                        Doubleword,
-                    ));
                 }
+            }
 
-                let reg = free.pop().unwrap();
-                allocated.insert(reg.clone(), *id);
-                s.entry(*id)
-                    .or_insert(Place::Register(Register::Sub(reg, Part::Doubleword)));
+            if let Some((_, reg)) = active.iter().find(|(active, _)| *active == id) {
+                s.insert(id, Place::Register(Register::Sub(reg.clone(), Part::Doubleword)));
             }
         }
 
+        for id in spilled {
+            stack_ptr += Doubleword.size();
+            s.insert(
+                id,
+                Place::Indirect(Indirect::new(
+                    Register::Register(RBP),
+                    stack_ptr,
+                    Doubleword,
+                )),
+            );
+        }
+
         (Allocator {
             m: s,
             stack_size: stack_ptr,
             intervals,
-            REGISTERS,
         }, params)
     }
 
@@ -134,6 +166,20 @@ impl Allocator {
             .collect()
     }
 
+    /// live_across returns registers which keep values
+    /// that are set before the instruction and used after it.
+    pub fn live_across(&self, index: usize) -> Vec<RegisterX64> {
+        self.intervals
+            .0
+            .iter()
+            .filter(|(_, range)| range.start < index && range.end > index)
+            .filter_map(|(id, _)| match self.m.get(id) {
+                Some(Place::Register(reg)) => Some(reg.base()),
+                _ => None,
+            })
+            .collect()
+    }
+
     pub fn free_at(&self, index: usize) -> Vec<RegisterX64> {
         let live_at = self.intervals.live_at(index);
         let occupied = live_at
@@ -148,7 +194,7 @@ impl Allocator {
             })
             .collect::<Vec<_>>();
 
-        let mut regs = self.REGISTERS.to_vec();
+        let mut regs = REGISTERS.to_vec();
         regs.retain(|reg| {
             occupied.contains(&Register::Register(reg.clone()))
                 || occupied.contains(&Register::Sub(reg.clone(), Part::Doubleword)) == false
@@ -157,11 +203,11 @@ impl Allocator {
     }
 
     pub fn alloc_stack(&mut self) -> usize {
-        self.stack_size += 4;
+        self.stack_size += Size::Doubleword.size();
         self.stack_size
     }
 
-    fn recognize_params(params: &[tac::ID]) -> (HashMap<tac::ID, Place>, usize) {
+    fn recognize_params(params: &[tac::ID]) -> HashMap<tac::ID, Place> {
         use RegisterX64::*;
         let regs = [RDI, RSI, RDX, RCX, R8, R9];
         let mut p = params
@@ -177,8 +223,8 @@ impl Allocator {
             .collect::<HashMap<tac::ID, Place>>();
 
         if params.len() > regs.len() {
-            const PLATFORM_WORD_SIZE: usize = 8;
-            let mut param_offset = PLATFORM_WORD_SIZE * 2;
+            // skip saved rbp and the return address
+            let mut param_offset = Size::Quadword.size() * 2;
             p.extend(
                 params
                     .iter()
@@ -186,21 +232,19 @@ impl Allocator {
                     .take(params.len() - regs.len())
                     .rev()
                     .map(|id| {
-                        let reg = Place::Indirect(Indirect::new(
-                            Register::Register(RBP),
-                            param_offset,
-                            Size::Doubleword,
-                        ));
-                        param_offset += PLATFORM_WORD_SIZE;
+                        let reg = Place::Indirect(Indirect {
+                            reg: Register::Register(RBP),
+                            offset: Offset::Above(param_offset),
+                            size: Size::Doubleword,
+                        });
+                        param_offset += Size::Quadword.size();
 
                         (*id, reg)
                     })
                     .collect::<HashMap<tac::ID, Place>>(),
             );
-
-            (p, param_offset)
-        } else {
-            (p, 0)
         }
+
+        p
     }
 }
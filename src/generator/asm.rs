@@ -1,9 +1,10 @@
-use super::syntax::Syntax;
+use super::syntax::{Indent, Syntax};
 use std::collections::HashMap;
 
 pub struct Assembly {
     pub funcs: HashMap<String, Func>,
     pub data: Block,
+    pub metadata: Block,
 }
 
 impl Assembly {
@@ -11,6 +12,7 @@ impl Assembly {
         Self {
             funcs: HashMap::new(),
             data: Block::new(),
+            metadata: Block::new(),
         }
     }
 
@@ -22,8 +24,12 @@ impl Assembly {
         self.data = data;
     }
 
-    pub fn code<S: Syntax>(&self) -> String {
-        S::asm(self)
+    pub fn set_metadata(&mut self, metadata: Block) {
+        self.metadata = metadata;
+    }
+
+    pub fn code<S: Syntax>(&self, indent: Indent) -> String {
+        S::asm(self, indent)
     }
 }
 
@@ -61,6 +67,10 @@ impl Block {
     pub fn emit_label(&mut self, s: &str) {
         self.code.push(Line::Label(s.to_owned()));
     }
+
+    pub fn emit_comment(&mut self, s: &str) {
+        self.code.push(Line::Comment(s.to_owned()));
+    }
 }
 
 impl std::ops::AddAssign<Block> for Block {
@@ -87,6 +97,8 @@ pub enum Line {
     Instruction(AsmX32),
     Directive(Directive),
     Label(Label),
+    /// Comment is an annotation which is rendered in the syntax's own comment style.
+    Comment(String),
 }
 
 impl Line {
@@ -98,17 +110,65 @@ impl Line {
     }
 }
 
+impl AsmX32 {
+    /// indirects_mut returns the memory operands of the instruction.
+    pub fn indirects_mut(&mut self) -> Vec<&mut Indirect> {
+        match self {
+            Self::Mov(p, v)
+            | Self::Movzx(p, v)
+            | Self::Movsx(p, v)
+            | Self::Lea(p, v)
+            | Self::And(p, v)
+            | Self::Or(p, v)
+            | Self::Xor(p, v)
+            | Self::Add(p, v)
+            | Self::Sub(p, v)
+            | Self::Mul(p, v)
+            | Self::Sal(p, v)
+            | Self::Sar(p, v)
+            | Self::Cmp(p, v) => p.indirect_mut().into_iter().chain(v.indirect_mut()).collect(),
+            Self::Div(p)
+            | Self::Neg(p)
+            | Self::Not(p)
+            | Self::Sete(p)
+            | Self::Setne(p)
+            | Self::Setl(p)
+            | Self::Setle(p)
+            | Self::Setg(p)
+            | Self::Setge(p)
+            | Self::Pop(p) => p.indirect_mut().into_iter().collect(),
+            Self::Imul(_, v, _) | Self::Push(v) | Self::CallIndirect(v) => {
+                v.indirect_mut().into_iter().collect()
+            }
+            Self::Metadata(..)
+            | Self::Label(..)
+            | Self::Convert(..)
+            | Self::Jmp(..)
+            | Self::Je(..)
+            | Self::Jne(..)
+            | Self::Call(..)
+            | Self::Leave
+            | Self::Ret => Vec::new(),
+        }
+    }
+}
+
 pub enum AsmX32 {
     Metadata(String),
     Label(String),
     Mov(Place, Value),
     Movzx(Place, Value),
+    /// Movsx copies a value to a wider place extending its sign.
+    Movsx(Place, Value),
+    Lea(Place, Value),
     And(Place, Value),
     Or(Place, Value),
     Xor(Place, Value),
     Add(Place, Value),
     Sub(Place, Value),
     Mul(Place, Value),
+    Sal(Place, Value),
+    Sar(Place, Value),
     Imul(Const, Value, Register),
     Div(Place),
     Neg(Place),
@@ -127,6 +187,8 @@ pub enum AsmX32 {
     Push(Value),
     Pop(Place),
     Call(String),
+    CallIndirect(Value),
+    Leave,
     Ret,
 }
 
@@ -141,15 +203,19 @@ pub enum Value {
 impl Value {
     pub fn size(&self) -> Size {
         match self {
-            Self::Register(Register::Register(..)) => Size::Quadword,
-            Self::Register(Register::Sub(.., Part::Doubleword)) => Size::Doubleword,
-            Self::Register(Register::Sub(.., Part::Word)) => Size::Word,
-            Self::Register(Register::Sub(.., Part::Byte)) => Size::Byte,
+            Self::Register(reg) => reg.size(),
             Self::Indirect(Indirect { size, .. }) => size.clone(),
             Self::Const(..) => Size::Doubleword,
             Self::Static(.., size) => size.clone(),
         }
     }
+
+    pub fn indirect_mut(&mut self) -> Option<&mut Indirect> {
+        match self {
+            Self::Indirect(i) => Some(i),
+            _ => None,
+        }
+    }
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -162,14 +228,18 @@ pub enum Place {
 impl Place {
     pub fn size(&self) -> Size {
         match self {
-            Self::Register(Register::Register(..)) => Size::Quadword,
-            Self::Register(Register::Sub(.., Part::Doubleword)) => Size::Doubleword,
-            Self::Register(Register::Sub(.., Part::Word)) => Size::Word,
-            Self::Register(Register::Sub(.., Part::Byte)) => Size::Byte,
+            Self::Register(reg) => reg.size(),
             Self::Indirect(Indirect { size, .. }) => size.clone(),
             Self::Static(.., size) => size.clone(),
         }
     }
+
+    pub fn indirect_mut(&mut self) -> Option<&mut Indirect> {
+        match self {
+            Self::Indirect(i) => Some(i),
+            _ => None,
+        }
+    }
 }
 
 pub type Const = i32;
@@ -182,6 +252,17 @@ pub enum Register {
     Sub(RegisterX64, Part),
 }
 
+impl Register {
+    pub fn size(&self) -> Size {
+        match self {
+            Self::Register(..) => Size::Quadword,
+            Self::Sub(.., Part::Doubleword) => Size::Doubleword,
+            Self::Sub(.., Part::Word) => Size::Word,
+            Self::Sub(.., Part::Byte) => Size::Byte,
+        }
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct Indirect {
     pub reg: Register,
@@ -201,7 +282,10 @@ impl Indirect {
 
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum Offset {
+    /// Static is an offset below the register, e.g. a local variable.
     Static(usize),
+    /// Above is an offset above the register, e.g. a parameter passed on the stack.
+    Above(usize),
     Label(Label),
 }
 
@@ -234,6 +318,30 @@ pub enum Size {
     Byte,
 }
 
+impl Size {
+    /// size returns a number of bytes
+    pub fn size(&self) -> usize {
+        match self {
+            Self::Quadword => 8,
+            Self::Doubleword => 4,
+            Self::Word => 2,
+            Self::Byte => 1,
+        }
+    }
+
+    /// from_bytes returns a size of an operand which takes n bytes
+    /// and None if there's no such operand.
+    pub fn from_bytes(n: usize) -> Option<Self> {
+        match n {
+            8 => Some(Self::Quadword),
+            4 => Some(Self::Doubleword),
+            2 => Some(Self::Word),
+            1 => Some(Self::Byte),
+            _ => None,
+        }
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum Part {
     Doubleword,
@@ -358,3 +466,21 @@ impl Into<Value> for Place {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn size() {
+        assert_eq!(Size::Byte.size(), 1);
+        assert_eq!(Size::Quadword.size(), 8);
+        assert_eq!(Size::from_bytes(8), Some(Size::Quadword));
+        assert_eq!(Size::from_bytes(4), Some(Size::Doubleword));
+        assert_eq!(Size::from_bytes(3), None);
+
+        for size in &[Size::Quadword, Size::Doubleword, Size::Word, Size::Byte] {
+            assert_eq!(Size::from_bytes(size.size()).as_ref(), Some(size));
+        }
+    }
+}
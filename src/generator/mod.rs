@@ -2,37 +2,135 @@ mod allocator;
 mod asm;
 pub mod syntax;
 
+use super::ast;
+use super::il::pretty;
 use super::il::tac::{self, File, InstructionLine};
-use asm::{AsmX32, Indirect, Part, Place, Register, RegisterX64, Size, Value};
+use asm::{AsmX32, Indirect, Offset, Part, Place, Register, RegisterX64, Size, Value};
 use std::collections::HashMap;
 
-pub fn gen<S: syntax::Syntax>(ir: File) -> String {
-    let g = Generator::new(ir);
+pub fn gen<S: syntax::Syntax>(ir: File, opts: &Options) -> String {
+    let g = Generator::new(ir, opts);
     let asm = g.gen();
     // allocator::alloc(&mut asm);
 
-    asm.code::<S>()
+    asm.code::<S>(opts.indent.unwrap_or(S::INDENT))
 }
 
-struct Generator {
+/// Options tunes the output of the generator.
+pub struct Options {
+    /// ident emits `.ident` directive to record the producer of the file, like gcc does.
+    pub ident: bool,
+    /// tail_calls lowers a call which result is returned right away as a jump.
+    pub tail_calls: bool,
+    /// use_leave tears down a stack frame by `leave` instead of `mov %rbp, %rsp; pop %rbp`.
+    pub use_leave: bool,
+    /// indent is a prefix of instructions and directives in the output,
+    /// by default it's the one of the syntax.
+    pub indent: Option<syntax::Indent>,
+    /// omit_frame_pointer addresses locals of leaf functions relative to `%rsp`
+    /// and drops the `%rbp` setup for them.
+    pub omit_frame_pointer: bool,
+    /// annotate precedes the code of each IR instruction by the instruction as a comment.
+    pub annotate: bool,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Self {
+            ident: true,
+            tail_calls: false,
+            use_leave: false,
+            indent: None,
+            omit_frame_pointer: false,
+            annotate: false,
+        }
+    }
+}
+
+struct Generator<'a> {
     ir: File,
     code: asm::Assembly,
+    opts: &'a Options,
+    /// return_types keeps return types of defined and declared functions by name.
+    return_types: HashMap<String, ast::Type>,
+    // rodata is a read-only data of the current function.
+    // It's flushed right after the function's code.
+    rodata: asm::Block,
 }
 
-impl Generator {
-    fn new(ir: File) -> Self {
+impl<'a> Generator<'a> {
+    fn new(ir: File, opts: &'a Options) -> Self {
+        let return_types = ir
+            .code
+            .iter()
+            .map(|f| (f.name.clone(), f.return_type))
+            .chain(
+                ir.prototypes
+                    .iter()
+                    .map(|(name, sig)| (name.clone(), sig.return_type)),
+            )
+            .collect();
+
         Self {
             ir,
+            return_types,
             code: asm::Assembly::new(),
+            opts,
+            rodata: asm::Block::new(),
         }
     }
 
+    /// const_data saves a read-only blob which can be referenced by the label
+    /// from the code of the current function.
+    ///
+    /// It's a base for jump tables and literals which don't fit in an immediate value.
+    #[allow(dead_code)]
+    fn const_data(&mut self, label: &str, bytes: &[u8]) {
+        let bytes = bytes
+            .iter()
+            .map(|b| b.to_string())
+            .collect::<Vec<_>>()
+            .join(", ");
+
+        self.rodata.emit_label(label);
+        self.rodata.emit_directive(&format!(".byte {}", bytes));
+    }
+
     fn gen_function(&mut self, func: tac::FuncDef) {
         let (mut allocator, params) = allocator::Allocator::new(&self.ir, &func);
         let mut code = Vec::new();
         code.push(params);
 
+        let tail_calls = if self.opts.tail_calls {
+            find_tail_calls(&func.instructions)
+        } else {
+            Vec::new()
+        };
+
         for (line, i) in func.instructions.into_iter().enumerate() {
+            if self.opts.annotate {
+                let mut annotation = asm::Block::new();
+                for text in pretty::pretty_instruction(&i, &func.ctx).lines() {
+                    annotation.emit_comment(text.trim());
+                }
+                code.push(annotation);
+            }
+
+            if tail_calls.contains(&line) {
+                if let InstructionLine(tac::Instruction::Call(call), _) = &i {
+                    // the result has to be extended to the return type after the call,
+                    // unless the callee has already returned a value of the type
+                    let extended = !return_value(func.return_type).code.is_empty()
+                        && self.return_types.get(&call.name) != Some(&func.return_type);
+                    if !extended {
+                        if let Some(b) = tail_call(&mut allocator, call, self.opts) {
+                            code.push(b);
+                            continue;
+                        }
+                    }
+                }
+            }
+
             code.push(translate(line, &mut allocator, i));
         }
 
@@ -44,7 +142,22 @@ impl Generator {
             header
         };
 
-        let (prologue, epilogue) = {
+        // A leaf function never moves the stack pointer,
+        // so its locals can be addressed from it as long as they fit in the red zone.
+        let omit_frame = self.opts.omit_frame_pointer
+            && !func.has_function_call
+            && allocator.stack_size <= RED_ZONE_SIZE;
+        if omit_frame {
+            for b in &mut code {
+                address_by_stack_pointer(b);
+            }
+        }
+
+        let (prologue, epilogue) = if omit_frame {
+            let mut epilogue = asm::Block::new();
+            epilogue.emit(AsmX32::Ret);
+            (asm::Block::new(), epilogue)
+        } else {
             let mut prologue = asm::Block::new();
             prologue.emit(AsmX32::Push(Value::Register(Register::Register(
                 RegisterX64::RBP,
@@ -55,25 +168,31 @@ impl Generator {
             ));
 
             let mut epilogue = asm::Block::new();
-            if func.has_function_call {
-                // todo: stack alignment
-                // comment: now it's always allocated by 4 bytes so its got to be ok
-                let stack_size = allocator.stack_size;
-                prologue.emit(AsmX32::Sub(
-                    Place::Register(Register::Register(RegisterX64::RSP)),
-                    Value::Const(stack_size as i32),
-                ));
-                epilogue.emit(AsmX32::Add(
-                    Place::Register(Register::Register(RegisterX64::RSP)),
-                    Value::Const(stack_size as i32),
-                ));
-                epilogue.emit(AsmX32::Mov(
-                    Place::Register(Register::Register(RegisterX64::RSP)),
-                    Value::Register(Register::Register(RegisterX64::RBP)),
-                ));
-                epilogue.emit(AsmX32::Pop(Place::Register(Register::Register(
-                    RegisterX64::RBP,
-                ))));
+            // a leaf function may keep its locals in the red zone without reserving them,
+            // otherwise they would overlap frames of the called functions
+            if func.has_function_call || allocator.stack_size > RED_ZONE_SIZE {
+                // FuncDef::frame_size isn't used since it counts a slot for every IR value
+                // before the passes run, whereas the allocator knows the deepest slot
+                // the code addresses, including the spills made while generating it.
+                let mut stack_size = allocator.stack_size;
+                // System V ABI requires `%rsp` to be aligned by 16 bytes at a call,
+                // the return address and the saved `%rbp` take 16 bytes together.
+                if func.has_function_call {
+                    stack_size = (stack_size + 15) / 16 * 16;
+                }
+                if stack_size != 0 {
+                    prologue.emit(AsmX32::Sub(
+                        Place::Register(Register::Register(RegisterX64::RSP)),
+                        Value::Const(stack_size as i32),
+                    ));
+                    if !self.opts.use_leave {
+                        epilogue.emit(AsmX32::Add(
+                            Place::Register(Register::Register(RegisterX64::RSP)),
+                            Value::Const(stack_size as i32),
+                        ));
+                    }
+                }
+                epilogue += leave_frame(self.opts);
                 epilogue.emit(AsmX32::Ret);
             } else {
                 epilogue.emit(AsmX32::Pop(Place::Register(Register::Register(
@@ -88,8 +207,16 @@ impl Generator {
         let mut c = vec![header];
         c.push(prologue);
         c.extend(code);
+        c.push(return_value(func.return_type));
         c.push(epilogue);
 
+        if !self.rodata.code.is_empty() {
+            let mut rodata = asm::Block::new();
+            rodata.emit_directive(".section .rodata");
+            rodata += std::mem::replace(&mut self.rodata, asm::Block::new());
+            c.push(rodata);
+        }
+
         self.code.emit_function(&func.name, c);
     }
 
@@ -101,14 +228,14 @@ impl Generator {
                     block.emit_directive(&format!(".globl _var_{}", var));
                     block.emit_directive(&format!(".data"));
                     block.emit_directive(&format!(".align 8"));
-                    block.emit_directive(&format!("_var_{}:", var));
+                    block.emit_label(&format!("_var_{}", var));
                     block.emit_directive(&format!(".long {}", value));
                 }
                 None => {
                     block.emit_directive(&format!(".globl _var_{}", var));
                     block.emit_directive(&format!(".bss"));
                     block.emit_directive(&format!(".align 8"));
-                    block.emit_directive(&format!("_var_{}:", var));
+                    block.emit_label(&format!("_var_{}", var));
                     block.emit_directive(&format!(".zero 4"));
                 }
             }
@@ -127,6 +254,15 @@ impl Generator {
             self.gen_function(func);
         }
 
+        if self.opts.ident {
+            let mut metadata = asm::Block::new();
+            metadata.emit(AsmX32::Metadata(format!(
+                ".ident \"simple-c-compiler {}\"",
+                env!("CARGO_PKG_VERSION")
+            )));
+            self.code.set_metadata(metadata);
+        }
+
         self.code
     }
 }
@@ -310,190 +446,281 @@ fn imul_constant(
     b
 }
 
-fn space_for_divisor(
+/// divide divides lhs by rhs leaving the quotient in `%eax` and the remainder in `%edx`.
+///
+/// Neither of them is allocated so they are free to be clobbered.
+fn divide(
     line: usize,
     al: &mut allocator::Allocator,
-    rhs: i32,
-) -> (Place, asm::Block, asm::Block) {
-    if let Some(reg) = al
-        .free_at(line)
-        .iter()
-        .find(|reg| reg != &&RegisterX64::RAX && reg != &&RegisterX64::RDX)
-    {
-        let mut spill = asm::Block::new();
-        let place = Place::Register(Register::Sub(reg.clone(), Part::Doubleword));
-        spill.emit(AsmX32::Mov(place.clone(), Value::Const(rhs)));
+    lhs: tac::Value,
+    rhs: tac::Value,
+) -> asm::Block {
+    let mut b = asm::Block::new();
+    let lhs = match lhs {
+        tac::Value::ID(lhs) => al.get(lhs).into(),
+        tac::Value::Const(tac::Const::Int(lhs)) => Value::Const(lhs),
+    };
+    b.emit(AsmX32::Mov(
+        Place::Register(Register::Sub(RegisterX64::RAX, Part::Doubleword)),
+        lhs,
+    ));
 
-        (place, spill, asm::Block::new())
-    } else {
-        let offset = al.alloc_stack();
-        let mut spill = asm::Block::new();
-        let place = Place::Indirect(Indirect::new(
-            Register::Register(RegisterX64::RBP),
-            offset,
-            Size::Doubleword,
-        ));
-        spill.emit(AsmX32::Mov(place.clone(), Value::Const(rhs)));
+    // idiv doesn't take an immediate operand
+    let divisor = match rhs {
+        tac::Value::ID(rhs) => al.get(rhs),
+        tac::Value::Const(tac::Const::Int(rhs)) => {
+            let divisor = match al.find_free_at(line) {
+                Some(reg) => Place::Register(Register::Sub(reg, Part::Doubleword)),
+                None => Place::Indirect(Indirect::new(
+                    Register::Register(RegisterX64::RBP),
+                    al.alloc_stack(),
+                    Size::Doubleword,
+                )),
+            };
+            b.emit(AsmX32::Mov(divisor.clone(), Value::Const(rhs)));
+            divisor
+        }
+    };
 
-        (place, spill, asm::Block::new())
-    }
+    b.emit(AsmX32::Convert(Size::Doubleword));
+    b.emit(AsmX32::Div(divisor));
+    b
 }
 
-fn spill_eax(line: usize, al: &mut allocator::Allocator) -> (asm::Block, asm::Block) {
-    if al.free_at(line).contains(&RegisterX64::RAX) {
-        (asm::Block::new(), asm::Block::new())
-    } else {
-        let offset = al.alloc_stack();
-        let mut spill = asm::Block::new();
-        spill.emit(AsmX32::Mov(
-            Place::Indirect(Indirect::new(
-                Register::Register(RegisterX64::RBP),
-                offset,
-                Size::Doubleword,
-            )),
-            Value::Register(Register::Sub(RegisterX64::RAX, Part::Doubleword)),
-        ));
-
-        let mut unspill = asm::Block::new();
-        unspill.emit(AsmX32::Mov(
-            Place::Register(Register::Sub(RegisterX64::RAX, Part::Doubleword)),
-            Value::Indirect(Indirect::new(
-                Register::Register(RegisterX64::RBP),
-                offset,
-                Size::Doubleword,
-            )),
-        ));
-
-        (spill, unspill)
+/// move_division_result moves a result of `idiv` from the register to the place
+/// unless it's the same register.
+fn move_from_register(place: Place, reg: RegisterX64) -> asm::Block {
+    let reg = Register::Sub(reg, Part::Doubleword);
+    let mut b = asm::Block::new();
+    if place != Place::Register(reg.clone()) {
+        b.emit(AsmX32::Mov(place, Value::Register(reg)));
     }
+
+    b
 }
 
-fn spill_eax_div(
-    line: usize,
-    al: &mut allocator::Allocator,
-    lhs: tac::ID,
-    id: tac::ID,
-) -> (asm::Block, asm::Block) {
-    let (mut spill, unspill) = if matches!(
-        al.get(lhs),
-        Place::Register(Register::Sub(RegisterX64::RAX, Part::Doubleword))
-    ) || al.free_at(line).contains(&RegisterX64::RAX)
-        || matches!(
-            al.get(id),
-            Place::Register(Register::Sub(RegisterX64::RAX, Part::Doubleword))
-        ) {
-        (asm::Block::new(), asm::Block::new())
-    } else {
-        spill_eax(line, al)
+/// find_tail_calls returns lines of calls which result is returned right after the call.
+///
+/// The result may be returned directly or through the function's return slot
+/// in case there are several returns.
+fn find_tail_calls(instructions: &[InstructionLine]) -> Vec<usize> {
+    let is_return_of = |line: usize, id: tac::ID| {
+        matches!(
+            instructions.get(line),
+            Some(InstructionLine(
+                tac::Instruction::ControlOp(tac::ControlOp::Return(tac::Value::ID(ret))),
+                _,
+            )) if *ret == id
+        )
+    };
+    let label_line = |label: tac::Label| {
+        instructions.iter().position(|i| {
+            matches!(i.0, tac::Instruction::ControlOp(tac::ControlOp::Label(l)) if l == label)
+        })
     };
 
-    spill.emit(AsmX32::Mov(
-        Place::Register(Register::Sub(RegisterX64::RAX, Part::Doubleword)),
-        al.get(lhs).into(),
-    ));
+    instructions
+        .iter()
+        .enumerate()
+        .filter_map(|(line, i)| match i {
+            InstructionLine(tac::Instruction::Call(..), Some(id)) => {
+                if is_return_of(line + 1, *id) {
+                    return Some(line);
+                }
 
-    (spill, unspill)
+                match (instructions.get(line + 1), instructions.get(line + 2)) {
+                    (
+                        Some(InstructionLine(
+                            tac::Instruction::Assignment(slot, tac::Value::ID(v)),
+                            _,
+                        )),
+                        Some(InstructionLine(
+                            tac::Instruction::ControlOp(tac::ControlOp::Branch(
+                                tac::Branch::GOTO(label),
+                            )),
+                            _,
+                        )),
+                    ) if v == id => label_line(*label)
+                        .filter(|l| is_return_of(l + 1, *slot))
+                        .map(|_| line),
+                    _ => None,
+                }
+            }
+            _ => None,
+        })
+        .collect()
 }
 
-fn spill_edx_if_not(
-    line: usize,
-    al: &mut allocator::Allocator,
-    not_in: &[tac::ID],
-) -> (asm::Block, asm::Block) {
-    if not_in.iter().any(|id| {
-        matches!(
-            al.get(*id),
-            Place::Register(Register::Sub(RegisterX64::RDX, Part::Doubleword))
-        )
-    }) {
-        (asm::Block::new(), asm::Block::new())
-    } else {
-        spill_edx_div_c(line, al)
+/// tail_call lowers a call as a jump so the callee reuses the caller's frame.
+///
+/// It's possible only when all arguments are passed by registers.
+fn tail_call(
+    map: &mut allocator::Allocator,
+    call: &tac::Call,
+    opts: &Options,
+) -> Option<asm::Block> {
+    if call.params.len() > 6 || matches!(call.tp, tac::FnType::ACall(..)) {
+        return None;
     }
+
+    let mut b = pass_arguments(map, &call.params, Vec::new());
+    // tear down the frame, the callee will return to our caller
+    b += leave_frame(opts);
+    b.emit(AsmX32::Jmp(call.name.clone()));
+
+    Some(b)
 }
 
-fn spill_edx_div_c(line: usize, al: &mut allocator::Allocator) -> (asm::Block, asm::Block) {
-    if al.live_at(line).contains(&Place::Register(Register::Sub(
-        RegisterX64::RDX,
-        Part::Doubleword,
-    ))) {
-        let offset = al.alloc_stack();
-        let mut spill = asm::Block::new();
-        spill.emit(AsmX32::Mov(
-            Place::Indirect(Indirect::new(
-                Register::Register(RegisterX64::RBP),
-                offset,
-                Size::Doubleword,
-            )),
-            Value::Register(Register::Sub(RegisterX64::RDX, Part::Doubleword)),
-        ));
+/// pass_arguments moves the parameters which are passed by registers to them.
+///
+/// A parameter which is kept in a register that is set to a former one
+/// is read from its copy on the stack, `copies` are the copies which are already made.
+fn pass_arguments(
+    map: &mut allocator::Allocator,
+    params: &[tac::Value],
+    mut copies: Vec<(RegisterX64, usize)>,
+) -> asm::Block {
+    use RegisterX64::*;
+    let regs = [RDI, RSI, RDX, RCX, R8, R9];
+    let slot = |offset| Indirect::new(Register::Register(RBP), offset, Size::Doubleword);
 
-        let mut unspill = asm::Block::new();
-        unspill.emit(AsmX32::Mov(
-            Place::Register(Register::Sub(RegisterX64::RDX, Part::Doubleword)),
-            Value::Indirect(Indirect::new(
-                Register::Register(RegisterX64::RBP),
-                offset,
-                Size::Doubleword,
-            )),
+    let mut b = asm::Block::new();
+    for (i, p) in params.iter().enumerate().take(regs.len()) {
+        if let tac::Value::ID(p) = p {
+            if let Place::Register(reg) = map.get(*p) {
+                let reg = reg.base();
+                if regs[..i].contains(&reg) && copies.iter().all(|(copy, _)| *copy != reg) {
+                    let offset = map.alloc_stack();
+                    b.emit(AsmX32::Mov(
+                        Place::Indirect(slot(offset)),
+                        Value::Register(Register::Sub(reg.clone(), Part::Doubleword)),
+                    ));
+                    copies.push((reg, offset));
+                }
+            }
+        }
+    }
+
+    for (i, (p, reg)) in params.iter().zip(&regs).enumerate() {
+        let value = match p {
+            tac::Value::ID(p) => match map.get(*p) {
+                Place::Register(r) if regs[..i].contains(&r.base()) => {
+                    let (_, offset) = copies.iter().find(|(copy, _)| *copy == r.base()).unwrap();
+                    Value::Indirect(slot(*offset))
+                }
+                place => place.into(),
+            },
+            tac::Value::Const(tac::Const::Int(p)) => Value::Const(*p),
+        };
+        b.emit(AsmX32::Mov(
+            Place::Register(Register::Sub(reg.clone(), Part::Doubleword)),
+            value,
         ));
+    }
 
-        (spill, unspill)
-    } else {
-        (asm::Block::new(), asm::Block::new())
+    b
+}
+
+/// RED_ZONE_SIZE is a number of bytes below `%rsp` which a function
+/// may use without allocating them, according to System V ABI.
+const RED_ZONE_SIZE: usize = 128;
+
+/// address_by_stack_pointer rewrites `%rbp` based operands of the block to `%rsp` based ones.
+fn address_by_stack_pointer(b: &mut asm::Block) {
+    for i in b.code.iter_mut().filter_map(|l| l.instruction_mut()) {
+        for ind in i.indirects_mut() {
+            if ind.reg == Register::Register(RegisterX64::RBP) {
+                ind.reg = Register::Register(RegisterX64::RSP);
+                // there's no saved rbp between the stack pointer and the arguments
+                if let Offset::Above(offset) = &mut ind.offset {
+                    *offset -= Size::Quadword.size();
+                }
+            }
+        }
     }
 }
 
-fn spill_eax_div_cc(
-    line: usize,
-    al: &mut allocator::Allocator,
-    lhs: i32,
-    rhs: tac::ID,
-    id: tac::ID,
-) -> (asm::Block, asm::Block) {
-    let (mut spill, unspill) = if matches!(
-        al.get(rhs),
-        Place::Register(Register::Sub(RegisterX64::RAX, Part::Doubleword))
-    ) || al.free_at(line).contains(&RegisterX64::RAX)
-        || matches!(
-            al.get(id),
-            Place::Register(Register::Sub(RegisterX64::RAX, Part::Doubleword))
-        ) {
-        (asm::Block::new(), asm::Block::new())
+/// leave_frame restores stack and frame pointers of a caller.
+/// It's supposed to be used only when the frame was set up by the prologue.
+fn leave_frame(opts: &Options) -> asm::Block {
+    let mut b = asm::Block::new();
+    if opts.use_leave {
+        b.emit(AsmX32::Leave);
     } else {
-        spill_eax(line, al)
-    };
+        b.emit(AsmX32::Mov(
+            Place::Register(Register::Register(RegisterX64::RSP)),
+            Value::Register(Register::Register(RegisterX64::RBP)),
+        ));
+        b.emit(AsmX32::Pop(Place::Register(Register::Register(
+            RegisterX64::RBP,
+        ))));
+    }
 
-    spill.emit(AsmX32::Mov(
-        Place::Register(Register::Sub(RegisterX64::RAX, Part::Doubleword)),
-        Value::Const(lhs),
-    ));
+    b
+}
+
+/// return_value extends a result which is computed in `%eax` to the size of the return type.
+fn return_value(return_type: ast::Type) -> asm::Block {
+    let mut b = asm::Block::new();
+    match Size::from_bytes(return_type.size()) {
+        Some(Size::Quadword) => b.emit(AsmX32::Movsx(
+            Place::Register(Register::Register(RegisterX64::RAX)),
+            Value::Register(Register::Sub(RegisterX64::RAX, Part::Doubleword)),
+        )),
+        Some(Size::Byte) => b.emit(AsmX32::Movsx(
+            Place::Register(Register::Sub(RegisterX64::RAX, Part::Doubleword)),
+            Value::Register(Register::Sub(RegisterX64::RAX, Part::Byte)),
+        )),
+        // int is already in eax and void has no value
+        _ => (),
+    }
+
+    b
+}
 
-    (spill, unspill)
+fn bitwise(op: tac::BitwiseOp, place: Place, value: Value) -> AsmX32 {
+    match op {
+        tac::BitwiseOp::And => AsmX32::And(place, value),
+        tac::BitwiseOp::Or => AsmX32::Or(place, value),
+        tac::BitwiseOp::Xor => AsmX32::Xor(place, value),
+        _ => unreachable!(),
+    }
 }
 
-fn spill_eax_div_ccc(
+fn checked_bitwise(
     line: usize,
     al: &mut allocator::Allocator,
-    lhs: i32,
-    id: tac::ID,
-) -> (asm::Block, asm::Block) {
-    let (mut spill, unspill) = if al.free_at(line).contains(&RegisterX64::RAX)
-        || matches!(
-            al.get(id),
-            Place::Register(Register::Sub(RegisterX64::RAX, Part::Doubleword))
-        ) {
-        (asm::Block::new(), asm::Block::new())
+    op: tac::BitwiseOp,
+    from: tac::ID,
+    to: tac::ID,
+) -> asm::Block {
+    let mut b = asm::Block::new();
+    if matches!(al.get(from), Place::Indirect(..)) && matches!(al.get(to), Place::Indirect(..)) {
+        let (reg, spill, unspill) = get_register(line, al);
+        b += spill;
+        b.emit(AsmX32::Mov(
+            Place::Register(Register::Sub(reg.clone(), Part::Doubleword)),
+            al.get(from).into(),
+        ));
+        b.emit(bitwise(
+            op,
+            al.get(to),
+            Value::Register(Register::Sub(reg, Part::Doubleword)),
+        ));
+        b += unspill;
     } else {
-        spill_eax(line, al)
-    };
-
-    spill.emit(AsmX32::Mov(
-        Place::Register(Register::Sub(RegisterX64::RAX, Part::Doubleword)),
-        Value::Const(lhs),
-    ));
+        b.emit(bitwise(op, al.get(to), al.get(from).into()));
+    }
+    b
+}
 
-    (spill, unspill)
+fn shift(op: tac::BitwiseOp, place: Place, count: i32) -> AsmX32 {
+    let count = Value::Const(count & 0x1f);
+    match op {
+        tac::BitwiseOp::LShift => AsmX32::Sal(place, count),
+        tac::BitwiseOp::RShift => AsmX32::Sar(place, count),
+        _ => unreachable!(),
+    }
 }
 
 fn translate(
@@ -603,194 +830,27 @@ fn translate(
         // DIV
         tac::Instruction::Op(tac::Op::Op(
             tac::TypeOp::Arithmetic(tac::ArithmeticOp::Div),
-            tac::Value::ID(lhs),
-            tac::Value::ID(rhs),
-        )) => {
-            let (eax_spill, eax_un_spill) = spill_eax_div(line, map, lhs, id.unwrap());
-            let (spill_edx, un_spill_edx) = spill_edx_if_not(line, map, &[rhs, id.unwrap()]);
-
-            b += eax_spill;
-            b += spill_edx;
-
-            b.emit(AsmX32::Convert(Size::Doubleword));
-            b.emit(AsmX32::Div(map.get(rhs).into()));
-            b.emit(AsmX32::Mov(
-                map.get(id.unwrap()),
-                Value::Register(Register::Sub(RegisterX64::RAX, Part::Doubleword)),
-            ));
-
-            b += eax_un_spill;
-            b += un_spill_edx;
-        }
-        tac::Instruction::Op(tac::Op::Op(
-            tac::TypeOp::Arithmetic(tac::ArithmeticOp::Div),
-            tac::Value::ID(lhs),
-            tac::Value::Const(tac::Const::Int(rhs)),
-        )) => {
-            let (eax_spill, eax_un_spill) = spill_eax_div(line, map, lhs, id.unwrap());
-            let (spill_edx, un_spill_edx) = spill_edx_if_not(line, map, &[id.unwrap()]);
-            let (divisor, divisor_spill, divisor_unspill) = space_for_divisor(line, map, rhs);
-
-            b += eax_spill;
-            b += spill_edx;
-            b += divisor_spill;
-
-            b.emit(AsmX32::Convert(Size::Doubleword));
-            b.emit(AsmX32::Div(divisor));
-            b.emit(AsmX32::Mov(
-                map.get(id.unwrap()),
-                Value::Register(Register::Sub(RegisterX64::RAX, Part::Doubleword)),
-            ));
-
-            b += eax_un_spill;
-            b += un_spill_edx;
-            b += divisor_unspill;
-        }
-        tac::Instruction::Op(tac::Op::Op(
-            tac::TypeOp::Arithmetic(tac::ArithmeticOp::Div),
-            tac::Value::Const(tac::Const::Int(lhs)),
-            tac::Value::ID(rhs),
-        )) => {
-            let (eax_spill, eax_un_spill) = spill_eax_div_cc(line, map, lhs, rhs, id.unwrap());
-            let (spill_edx, un_spill_edx) = spill_edx_if_not(line, map, &[id.unwrap()]);
-
-            b += eax_spill;
-            b += spill_edx;
-
-            b.emit(AsmX32::Convert(Size::Doubleword));
-            b.emit(AsmX32::Div(map.get(rhs).into()));
-            b.emit(AsmX32::Mov(
-                map.get(id.unwrap()),
-                Value::Register(Register::Sub(RegisterX64::RAX, Part::Doubleword)),
-            ));
-
-            b += eax_un_spill;
-            b += un_spill_edx;
-        }
-        tac::Instruction::Op(tac::Op::Op(
-            tac::TypeOp::Arithmetic(tac::ArithmeticOp::Div),
-            tac::Value::Const(tac::Const::Int(lhs)),
-            tac::Value::Const(tac::Const::Int(rhs)),
+            lhs,
+            rhs,
         )) => {
-            let (eax_spill, eax_un_spill) = spill_eax_div_ccc(line, map, lhs, id.unwrap());
-            let (spill_edx, un_spill_edx) = spill_edx_if_not(line, map, &[id.unwrap()]);
-            let (divisor, divisor_spill, divisor_unspill) = space_for_divisor(line, map, rhs);
-
-            b += eax_spill;
-            b += spill_edx;
-            b += divisor_spill;
-
-            b.emit(AsmX32::Convert(Size::Doubleword));
-            b.emit(AsmX32::Div(divisor));
-            b.emit(AsmX32::Mov(
-                map.get(id.unwrap()),
-                Value::Register(Register::Sub(RegisterX64::RAX, Part::Doubleword)),
-            ));
-
-            b += eax_un_spill;
-            b += un_spill_edx;
-            b += divisor_unspill;
+            b += divide(line, map, lhs, rhs);
+            b += move_from_register(map.get(id.unwrap()), RegisterX64::RAX);
         }
         // MOD
         tac::Instruction::Op(tac::Op::Op(
             tac::TypeOp::Arithmetic(tac::ArithmeticOp::Mod),
-            tac::Value::ID(lhs),
-            tac::Value::ID(rhs),
+            lhs,
+            rhs,
         )) => {
-            let (eax_spill, eax_un_spill) = spill_eax_div(line, map, lhs, id.unwrap());
-            let (spill_edx, un_spill_edx) = spill_edx_if_not(line, map, &[rhs, id.unwrap()]);
-
-            b += eax_spill;
-            b += spill_edx;
-
-            b.emit(AsmX32::Convert(Size::Doubleword));
-            b.emit(AsmX32::Div(map.get(rhs).into()));
-            b.emit(AsmX32::Mov(
-                map.get(id.unwrap()),
-                Value::Register(Register::Sub(RegisterX64::RDX, Part::Doubleword)),
-            ));
-
-            b += eax_un_spill;
-            b += un_spill_edx;
-        }
-        tac::Instruction::Op(tac::Op::Op(
-            tac::TypeOp::Arithmetic(tac::ArithmeticOp::Mod),
-            tac::Value::ID(lhs),
-            tac::Value::Const(tac::Const::Int(rhs)),
-        )) => {
-            let (eax_spill, eax_un_spill) = spill_eax_div(line, map, lhs, id.unwrap());
-            let (spill_edx, un_spill_edx) = spill_edx_if_not(line, map, &[id.unwrap()]);
-            let (divisor, divisor_spill, divisor_unspill) = space_for_divisor(line, map, rhs);
-
-            b += eax_spill;
-            b += spill_edx;
-            b += divisor_spill;
-
-            b.emit(AsmX32::Convert(Size::Doubleword));
-            b.emit(AsmX32::Div(divisor));
-            b.emit(AsmX32::Mov(
-                map.get(id.unwrap()),
-                Value::Register(Register::Sub(RegisterX64::RDX, Part::Doubleword)),
-            ));
-
-            b += eax_un_spill;
-            b += un_spill_edx;
-            b += divisor_unspill;
-        }
-        tac::Instruction::Op(tac::Op::Op(
-            tac::TypeOp::Arithmetic(tac::ArithmeticOp::Mod),
-            tac::Value::Const(tac::Const::Int(lhs)),
-            tac::Value::ID(rhs),
-        )) => {
-            let (eax_spill, eax_un_spill) = spill_eax_div_cc(line, map, lhs, rhs, id.unwrap());
-            let (spill_edx, un_spill_edx) = spill_edx_if_not(line, map, &[id.unwrap()]);
-
-            b += eax_spill;
-            b += spill_edx;
-
-            b.emit(AsmX32::Convert(Size::Doubleword));
-            b.emit(AsmX32::Div(map.get(rhs)));
-            b.emit(AsmX32::Mov(
-                map.get(id.unwrap()),
-                Value::Register(Register::Sub(RegisterX64::RDX, Part::Doubleword)),
-            ));
-
-            b += eax_un_spill;
-            b += un_spill_edx;
-        }
-        tac::Instruction::Op(tac::Op::Op(
-            tac::TypeOp::Arithmetic(tac::ArithmeticOp::Mod),
-            tac::Value::Const(tac::Const::Int(lhs)),
-            tac::Value::Const(tac::Const::Int(rhs)),
-        )) => {
-            let (eax_spill, eax_un_spill) = spill_eax_div_ccc(line, map, lhs, id.unwrap());
-            let (spill_edx, un_spill_edx) = spill_edx_if_not(line, map, &[id.unwrap()]);
-            let (divisor, divisor_spill, divisor_unspill) = space_for_divisor(line, map, rhs);
-
-            b += eax_spill;
-            b += spill_edx;
-            b += divisor_spill;
-
-            b.emit(AsmX32::Convert(Size::Doubleword));
-            b.emit(AsmX32::Div(divisor));
-            b.emit(AsmX32::Mov(
-                map.get(id.unwrap()),
-                Value::Register(Register::Sub(RegisterX64::RDX, Part::Doubleword)),
-            ));
-
-            b += eax_un_spill;
-            b += un_spill_edx;
-            b += divisor_unspill;
+            b += divide(line, map, lhs, rhs);
+            b += move_from_register(map.get(id.unwrap()), RegisterX64::RDX);
         }
         // NEG
         tac::Instruction::Op(tac::Op::Unary(tac::UnOp::Neg, tac::Value::ID(v))) => {
-            // comment: clang approach with 1 line instruction(sub) require to have
-            // prepared register(equal 0)
-            b.emit(AsmX32::Xor(
-                map.get(id.unwrap()),
-                map.get(id.unwrap()).into(),
-            ));
-            b.emit(AsmX32::Sub(map.get(id.unwrap()), map.get(v).into()));
+            // neg is used instead of clang's xor + sub
+            // since the both operands might be on the stack.
+            b += checked_mov(line, &mut map, v, id.unwrap());
+            b.emit(AsmX32::Neg(map.get(id.unwrap())));
         }
         tac::Instruction::Op(tac::Op::Unary(
             tac::UnOp::Neg,
@@ -801,15 +861,15 @@ fn translate(
         }
         // Bitwise
         tac::Instruction::Op(tac::Op::Unary(tac::UnOp::BitComplement, tac::Value::ID(v))) => {
-            b.emit(AsmX32::Mov(map.get(id.unwrap()), map.get(v).into()));
-            b.emit(AsmX32::Xor(map.get(id.unwrap()), Value::Const(-1)));
+            b += checked_mov(line, &mut map, v, id.unwrap());
+            b.emit(AsmX32::Not(map.get(id.unwrap())));
         }
         tac::Instruction::Op(tac::Op::Unary(
             tac::UnOp::BitComplement,
             tac::Value::Const(tac::Const::Int(v)),
         )) => {
             b.emit(AsmX32::Mov(map.get(id.unwrap()), Value::Const(v)));
-            b.emit(AsmX32::Xor(map.get(id.unwrap()), Value::Const(-1)));
+            b.emit(AsmX32::Not(map.get(id.unwrap())));
         }
         // Logicneg
         tac::Instruction::Op(tac::Op::Unary(tac::UnOp::LogicNeg, tac::Value::ID(v))) => {
@@ -834,14 +894,10 @@ fn translate(
             b += spill;
 
             b.emit(AsmX32::Cmp(map.get(v), Value::Const(0)));
-            b.emit(AsmX32::Setne(Place::Register(Register::Sub(
+            b.emit(AsmX32::Sete(Place::Register(Register::Sub(
                 reg.clone(),
                 Part::Byte,
             ))));
-            b.emit(AsmX32::Xor(
-                Place::Register(Register::Sub(reg.clone(), Part::Byte)),
-                Value::Const(-1),
-            ));
             b.emit(AsmX32::And(
                 Place::Register(Register::Sub(reg.clone(), Part::Byte)),
                 Value::Const(1),
@@ -881,14 +937,10 @@ fn translate(
             b.emit(AsmX32::Mov(reg_place.clone(), Value::Const(v)));
 
             b.emit(AsmX32::Cmp(reg_place.clone(), Value::Const(0)));
-            b.emit(AsmX32::Setne(Place::Register(Register::Sub(
+            b.emit(AsmX32::Sete(Place::Register(Register::Sub(
                 reg.clone(),
                 Part::Byte,
             ))));
-            b.emit(AsmX32::Xor(
-                Place::Register(Register::Sub(reg.clone(), Part::Byte)),
-                Value::Const(-1),
-            ));
             b.emit(AsmX32::And(
                 Place::Register(Register::Sub(reg.clone(), Part::Byte)),
                 Value::Const(1),
@@ -929,10 +981,7 @@ fn translate(
                 Place::Register(Register::Sub(reg.clone(), Part::Doubleword)),
                 Value::Register(Register::Sub(reg.clone(), Part::Byte)),
             ));
-            b.emit(AsmX32::Mov(
-                map.get(id.unwrap()),
-                Value::Register(Register::Sub(reg, Part::Doubleword)),
-            ));
+            b += move_from_register(map.get(id.unwrap()), reg);
 
             b += unspill;
         }
@@ -964,10 +1013,7 @@ fn translate(
                 Place::Register(Register::Sub(reg.clone(), Part::Doubleword)),
                 Value::Register(Register::Sub(reg.clone(), Part::Byte)),
             ));
-            b.emit(AsmX32::Mov(
-                map.get(id.unwrap()),
-                Value::Register(Register::Sub(reg, Part::Doubleword)),
-            ));
+            b += move_from_register(map.get(id.unwrap()), reg);
 
             b += unspill;
         }
@@ -999,10 +1045,7 @@ fn translate(
                 Place::Register(Register::Sub(reg.clone(), Part::Doubleword)),
                 Value::Register(Register::Sub(reg.clone(), Part::Byte)),
             ));
-            b.emit(AsmX32::Mov(
-                map.get(id.unwrap()),
-                Value::Register(Register::Sub(reg, Part::Doubleword)),
-            ));
+            b += move_from_register(map.get(id.unwrap()), reg);
 
             b += unspill;
         }
@@ -1042,10 +1085,7 @@ fn translate(
                 Place::Register(Register::Sub(reg.clone(), Part::Doubleword)),
                 Value::Register(Register::Sub(reg.clone(), Part::Byte)),
             ));
-            b.emit(AsmX32::Mov(
-                map.get(id.unwrap()),
-                Value::Register(Register::Sub(reg, Part::Doubleword)),
-            ));
+            b += move_from_register(map.get(id.unwrap()), reg);
 
             b += unspill;
         }
@@ -1078,10 +1118,7 @@ fn translate(
                 Place::Register(Register::Sub(reg.clone(), Part::Doubleword)),
                 Value::Register(Register::Sub(reg.clone(), Part::Byte)),
             ));
-            b.emit(AsmX32::Mov(
-                map.get(id.unwrap()),
-                Value::Register(Register::Sub(reg, Part::Doubleword)),
-            ));
+            b += move_from_register(map.get(id.unwrap()), reg);
 
             b += unspill;
         }
@@ -1113,10 +1150,7 @@ fn translate(
                 Place::Register(Register::Sub(reg.clone(), Part::Doubleword)),
                 Value::Register(Register::Sub(reg.clone(), Part::Byte)),
             ));
-            b.emit(AsmX32::Mov(
-                map.get(id.unwrap()),
-                Value::Register(Register::Sub(reg, Part::Doubleword)),
-            ));
+            b += move_from_register(map.get(id.unwrap()), reg);
 
             b += unspill;
         }
@@ -1148,10 +1182,7 @@ fn translate(
                 Place::Register(Register::Sub(reg.clone(), Part::Doubleword)),
                 Value::Register(Register::Sub(reg.clone(), Part::Byte)),
             ));
-            b.emit(AsmX32::Mov(
-                map.get(id.unwrap()),
-                Value::Register(Register::Sub(reg, Part::Doubleword)),
-            ));
+            b += move_from_register(map.get(id.unwrap()), reg);
 
             b += unspill;
         }
@@ -1191,10 +1222,7 @@ fn translate(
                 Place::Register(Register::Sub(reg.clone(), Part::Doubleword)),
                 Value::Register(Register::Sub(reg.clone(), Part::Byte)),
             ));
-            b.emit(AsmX32::Mov(
-                map.get(id.unwrap()),
-                Value::Register(Register::Sub(reg, Part::Doubleword)),
-            ));
+            b += move_from_register(map.get(id.unwrap()), reg);
 
             b += unspill;
         }
@@ -1227,10 +1255,7 @@ fn translate(
                 Place::Register(Register::Sub(reg.clone(), Part::Doubleword)),
                 Value::Register(Register::Sub(reg.clone(), Part::Byte)),
             ));
-            b.emit(AsmX32::Mov(
-                map.get(id.unwrap()),
-                Value::Register(Register::Sub(reg, Part::Doubleword)),
-            ));
+            b += move_from_register(map.get(id.unwrap()), reg);
 
             b += unspill;
         }
@@ -1262,10 +1287,7 @@ fn translate(
                 Place::Register(Register::Sub(reg.clone(), Part::Doubleword)),
                 Value::Register(Register::Sub(reg.clone(), Part::Byte)),
             ));
-            b.emit(AsmX32::Mov(
-                map.get(id.unwrap()),
-                Value::Register(Register::Sub(reg, Part::Doubleword)),
-            ));
+            b += move_from_register(map.get(id.unwrap()), reg);
 
             b += unspill;
         }
@@ -1304,10 +1326,7 @@ fn translate(
                 Place::Register(Register::Sub(reg.clone(), Part::Doubleword)),
                 Value::Register(Register::Sub(reg.clone(), Part::Byte)),
             ));
-            b.emit(AsmX32::Mov(
-                map.get(id.unwrap()),
-                Value::Register(Register::Sub(reg, Part::Doubleword)),
-            ));
+            b += move_from_register(map.get(id.unwrap()), reg);
 
             b += unspill;
         }
@@ -1347,10 +1366,7 @@ fn translate(
                 Place::Register(Register::Sub(reg.clone(), Part::Doubleword)),
                 Value::Register(Register::Sub(reg.clone(), Part::Byte)),
             ));
-            b.emit(AsmX32::Mov(
-                map.get(id.unwrap()),
-                Value::Register(Register::Sub(reg, Part::Doubleword)),
-            ));
+            b += move_from_register(map.get(id.unwrap()), reg);
 
             b += unspill;
         }
@@ -1383,10 +1399,7 @@ fn translate(
                 Place::Register(Register::Sub(reg.clone(), Part::Doubleword)),
                 Value::Register(Register::Sub(reg.clone(), Part::Byte)),
             ));
-            b.emit(AsmX32::Mov(
-                map.get(id.unwrap()),
-                Value::Register(Register::Sub(reg, Part::Doubleword)),
-            ));
+            b += move_from_register(map.get(id.unwrap()), reg);
 
             b += unspill;
         }
@@ -1418,10 +1431,7 @@ fn translate(
                 Place::Register(Register::Sub(reg.clone(), Part::Doubleword)),
                 Value::Register(Register::Sub(reg.clone(), Part::Byte)),
             ));
-            b.emit(AsmX32::Mov(
-                map.get(id.unwrap()),
-                Value::Register(Register::Sub(reg, Part::Doubleword)),
-            ));
+            b += move_from_register(map.get(id.unwrap()), reg);
 
             b += unspill;
         }
@@ -1460,10 +1470,7 @@ fn translate(
                 Place::Register(Register::Sub(reg.clone(), Part::Doubleword)),
                 Value::Register(Register::Sub(reg.clone(), Part::Byte)),
             ));
-            b.emit(AsmX32::Mov(
-                map.get(id.unwrap()),
-                Value::Register(Register::Sub(reg, Part::Doubleword)),
-            ));
+            b += move_from_register(map.get(id.unwrap()), reg);
 
             b += unspill;
         }
@@ -1503,10 +1510,7 @@ fn translate(
                 Place::Register(Register::Sub(reg.clone(), Part::Doubleword)),
                 Value::Register(Register::Sub(reg.clone(), Part::Byte)),
             ));
-            b.emit(AsmX32::Mov(
-                map.get(id.unwrap()),
-                Value::Register(Register::Sub(reg, Part::Doubleword)),
-            ));
+            b += move_from_register(map.get(id.unwrap()), reg);
 
             b += unspill;
         }
@@ -1539,10 +1543,7 @@ fn translate(
                 Place::Register(Register::Sub(reg.clone(), Part::Doubleword)),
                 Value::Register(Register::Sub(reg.clone(), Part::Byte)),
             ));
-            b.emit(AsmX32::Mov(
-                map.get(id.unwrap()),
-                Value::Register(Register::Sub(reg, Part::Doubleword)),
-            ));
+            b += move_from_register(map.get(id.unwrap()), reg);
 
             b += unspill;
         }
@@ -1574,10 +1575,7 @@ fn translate(
                 Place::Register(Register::Sub(reg.clone(), Part::Doubleword)),
                 Value::Register(Register::Sub(reg.clone(), Part::Byte)),
             ));
-            b.emit(AsmX32::Mov(
-                map.get(id.unwrap()),
-                Value::Register(Register::Sub(reg, Part::Doubleword)),
-            ));
+            b += move_from_register(map.get(id.unwrap()), reg);
 
             b += unspill;
         }
@@ -1616,10 +1614,7 @@ fn translate(
                 Place::Register(Register::Sub(reg.clone(), Part::Doubleword)),
                 Value::Register(Register::Sub(reg.clone(), Part::Byte)),
             ));
-            b.emit(AsmX32::Mov(
-                map.get(id.unwrap()),
-                Value::Register(Register::Sub(reg, Part::Doubleword)),
-            ));
+            b += move_from_register(map.get(id.unwrap()), reg);
 
             b += unspill;
         }
@@ -1659,10 +1654,7 @@ fn translate(
                 Place::Register(Register::Sub(reg.clone(), Part::Doubleword)),
                 Value::Register(Register::Sub(reg.clone(), Part::Byte)),
             ));
-            b.emit(AsmX32::Mov(
-                map.get(id.unwrap()),
-                Value::Register(Register::Sub(reg, Part::Doubleword)),
-            ));
+            b += move_from_register(map.get(id.unwrap()), reg);
 
             b += unspill;
         }
@@ -1695,10 +1687,7 @@ fn translate(
                 Place::Register(Register::Sub(reg.clone(), Part::Doubleword)),
                 Value::Register(Register::Sub(reg.clone(), Part::Byte)),
             ));
-            b.emit(AsmX32::Mov(
-                map.get(id.unwrap()),
-                Value::Register(Register::Sub(reg, Part::Doubleword)),
-            ));
+            b += move_from_register(map.get(id.unwrap()), reg);
 
             b += unspill;
         }
@@ -1730,10 +1719,7 @@ fn translate(
                 Place::Register(Register::Sub(reg.clone(), Part::Doubleword)),
                 Value::Register(Register::Sub(reg.clone(), Part::Byte)),
             ));
-            b.emit(AsmX32::Mov(
-                map.get(id.unwrap()),
-                Value::Register(Register::Sub(reg, Part::Doubleword)),
-            ));
+            b += move_from_register(map.get(id.unwrap()), reg);
 
             b += unspill;
         }
@@ -1772,10 +1758,7 @@ fn translate(
                 Place::Register(Register::Sub(reg.clone(), Part::Doubleword)),
                 Value::Register(Register::Sub(reg.clone(), Part::Byte)),
             ));
-            b.emit(AsmX32::Mov(
-                map.get(id.unwrap()),
-                Value::Register(Register::Sub(reg, Part::Doubleword)),
-            ));
+            b += move_from_register(map.get(id.unwrap()), reg);
 
             b += unspill;
         }
@@ -1815,19 +1798,113 @@ fn translate(
                 Place::Register(Register::Sub(reg.clone(), Part::Doubleword)),
                 Value::Register(Register::Sub(reg.clone(), Part::Byte)),
             ));
-            b.emit(AsmX32::Mov(
-                map.get(id.unwrap()),
-                Value::Register(Register::Sub(reg, Part::Doubleword)),
-            ));
+            b += move_from_register(map.get(id.unwrap()), reg);
 
             b += unspill;
         }
-        // OR
+        // SHIFT
+        //
+        // the count is masked to 5 bits as the processor does it
+        // so an immediate stays in a range of imm8.
         tac::Instruction::Op(tac::Op::Op(
-            tac::TypeOp::Bit(tac::BitwiseOp::And),
+            tac::TypeOp::Bit(op @ tac::BitwiseOp::LShift),
+            tac::Value::ID(lhs),
+            tac::Value::Const(tac::Const::Int(rhs)),
+        ))
+        | tac::Instruction::Op(tac::Op::Op(
+            tac::TypeOp::Bit(op @ tac::BitwiseOp::RShift),
+            tac::Value::ID(lhs),
+            tac::Value::Const(tac::Const::Int(rhs)),
+        )) => {
+            b += checked_mov(line, &mut map, lhs, id.unwrap());
+            b.emit(shift(op, map.get(id.unwrap()), rhs));
+        }
+        tac::Instruction::Op(tac::Op::Op(
+            tac::TypeOp::Bit(op @ tac::BitwiseOp::LShift),
+            tac::Value::Const(tac::Const::Int(lhs)),
+            tac::Value::Const(tac::Const::Int(rhs)),
+        ))
+        | tac::Instruction::Op(tac::Op::Op(
+            tac::TypeOp::Bit(op @ tac::BitwiseOp::RShift),
+            tac::Value::Const(tac::Const::Int(lhs)),
+            tac::Value::Const(tac::Const::Int(rhs)),
+        )) => {
+            b.emit(AsmX32::Mov(map.get(id.unwrap()), Value::Const(lhs)));
+            b.emit(shift(op, map.get(id.unwrap()), rhs));
+        }
+        // AND, OR, XOR
+        //
+        // operands are doublewords the same as int
+        // so the sign bits are handled like gcc does.
+        tac::Instruction::Op(tac::Op::Op(
+            tac::TypeOp::Bit(op @ tac::BitwiseOp::And),
+            tac::Value::ID(lhs),
+            tac::Value::ID(rhs),
+        ))
+        | tac::Instruction::Op(tac::Op::Op(
+            tac::TypeOp::Bit(op @ tac::BitwiseOp::Or),
+            tac::Value::ID(lhs),
+            tac::Value::ID(rhs),
+        ))
+        | tac::Instruction::Op(tac::Op::Op(
+            tac::TypeOp::Bit(op @ tac::BitwiseOp::Xor),
             tac::Value::ID(lhs),
             tac::Value::ID(rhs),
-        )) => unimplemented!(),
+        )) => {
+            b += checked_mov(line, &mut map, lhs, id.unwrap());
+            b += checked_bitwise(line, &mut map, op, rhs, id.unwrap());
+        }
+        tac::Instruction::Op(tac::Op::Op(
+            tac::TypeOp::Bit(op @ tac::BitwiseOp::And),
+            tac::Value::ID(v),
+            tac::Value::Const(tac::Const::Int(c)),
+        ))
+        | tac::Instruction::Op(tac::Op::Op(
+            tac::TypeOp::Bit(op @ tac::BitwiseOp::Or),
+            tac::Value::ID(v),
+            tac::Value::Const(tac::Const::Int(c)),
+        ))
+        | tac::Instruction::Op(tac::Op::Op(
+            tac::TypeOp::Bit(op @ tac::BitwiseOp::Xor),
+            tac::Value::ID(v),
+            tac::Value::Const(tac::Const::Int(c)),
+        ))
+        | tac::Instruction::Op(tac::Op::Op(
+            tac::TypeOp::Bit(op @ tac::BitwiseOp::And),
+            tac::Value::Const(tac::Const::Int(c)),
+            tac::Value::ID(v),
+        ))
+        | tac::Instruction::Op(tac::Op::Op(
+            tac::TypeOp::Bit(op @ tac::BitwiseOp::Or),
+            tac::Value::Const(tac::Const::Int(c)),
+            tac::Value::ID(v),
+        ))
+        | tac::Instruction::Op(tac::Op::Op(
+            tac::TypeOp::Bit(op @ tac::BitwiseOp::Xor),
+            tac::Value::Const(tac::Const::Int(c)),
+            tac::Value::ID(v),
+        )) => {
+            b += checked_mov(line, &mut map, v, id.unwrap());
+            b.emit(bitwise(op, map.get(id.unwrap()), Value::Const(c)));
+        }
+        tac::Instruction::Op(tac::Op::Op(
+            tac::TypeOp::Bit(op @ tac::BitwiseOp::And),
+            tac::Value::Const(tac::Const::Int(lhs)),
+            tac::Value::Const(tac::Const::Int(rhs)),
+        ))
+        | tac::Instruction::Op(tac::Op::Op(
+            tac::TypeOp::Bit(op @ tac::BitwiseOp::Or),
+            tac::Value::Const(tac::Const::Int(lhs)),
+            tac::Value::Const(tac::Const::Int(rhs)),
+        ))
+        | tac::Instruction::Op(tac::Op::Op(
+            tac::TypeOp::Bit(op @ tac::BitwiseOp::Xor),
+            tac::Value::Const(tac::Const::Int(lhs)),
+            tac::Value::Const(tac::Const::Int(rhs)),
+        )) => {
+            b.emit(AsmX32::Mov(map.get(id.unwrap()), Value::Const(lhs)));
+            b.emit(bitwise(op, map.get(id.unwrap()), Value::Const(rhs)));
+        }
         // // ALLOC
         tac::Instruction::Alloc(tac::Value::Const(tac::Const::Int(v))) => {
             b.emit(AsmX32::Mov(map.get(id.unwrap()), Value::Const(v)));
@@ -1887,62 +1964,55 @@ fn translate(
             b.emit(AsmX32::Cmp(tmp, Value::Const(0)));
             b.emit(AsmX32::Je(format!("_L{}", label)));
         }
-        tac::Instruction::Call(tac::Call { name, params, .. }) => {
+        // IfNotZeroGOTO
+        tac::Instruction::ControlOp(tac::ControlOp::Branch(tac::Branch::IfNotZeroGOTO(
+            tac::Value::ID(v),
+            label,
+        ))) => {
+            b.emit(AsmX32::Cmp(map.get(v), Value::Const(0)));
+            b.emit(AsmX32::Jne(format!("_L{}", label)));
+        }
+        tac::Instruction::ControlOp(tac::ControlOp::Branch(tac::Branch::IfNotZeroGOTO(
+            tac::Value::Const(tac::Const::Int(c)),
+            label,
+        ))) => {
+            if c != 0 {
+                b.emit(AsmX32::Jmp(format!("_L{}", label)));
+            }
+        }
+        // FUNCTION ADDRESS
+        tac::Instruction::FuncAddr(ptr, name) => {
+            let tmp = Register::Register(RegisterX64::R11);
+            b.emit(AsmX32::Lea(
+                Place::Register(tmp.clone()),
+                Value::Indirect(Indirect {
+                    reg: Register::Register(RegisterX64::RIP),
+                    offset: Offset::Label(name),
+                    size: Size::Quadword,
+                }),
+            ));
+            b.emit(AsmX32::Mov(map.get(ptr), Value::Register(tmp)));
+        }
+        tac::Instruction::Call(tac::Call {
+            name, params, tp, ..
+        }) => {
             let mut unspills = Vec::new();
 
             use RegisterX64::*;
             let regs = [RDI, RSI, RDX, RCX, R8, R9];
-            for (p, reg) in params.iter().zip(&regs) {
-                let p = match p {
-                    tac::Value::ID(p) => map.get(*p).into(),
-                    tac::Value::Const(tac::Const::Int(p)) => Value::Const(*p),
-                };
-
-                if map.live_at(line).contains(&Place::Register(Register::Sub(
-                    reg.clone(),
-                    Part::Doubleword,
-                ))) && {
-                    match map.get(id.unwrap()) {
-                        Place::Register(Register::Register(reg))
-                        | Place::Register(Register::Sub(reg, ..)) => !regs.contains(&reg),
-                        _ => true,
-                    }
-                } {
-                    let offset = map.alloc_stack();
-                    let tmp = Place::Indirect(Indirect::new(
-                        Register::Register(RegisterX64::RBP),
-                        offset,
-                        Size::Doubleword,
-                    ));
-                    let mut spill = asm::Block::new();
-                    spill.emit(AsmX32::Mov(
-                        tmp.clone(),
-                        Value::Register(Register::Sub(reg.clone(), Part::Doubleword)),
-                    ));
-
-                    b += spill;
-                    b.emit(AsmX32::Mov(
-                        Place::Register(Register::Sub(reg.clone(), Part::Doubleword)),
-                        p,
-                    ));
-
-                    let mut unspill = asm::Block::new();
-                    unspill.emit(AsmX32::Mov(
-                        Place::Register(Register::Sub(reg.clone(), Part::Doubleword)),
-                        tmp.into(),
-                    ));
-
-                    unspills.push(unspill);
-                } else {
-                    b.emit(AsmX32::Mov(
-                        Place::Register(Register::Sub(reg.clone(), Part::Doubleword)),
-                        p,
+            // the arguments passed on the stack are pushed first
+            // so the moves to the argument registers don't clobber them
+            let mut stack_reserved = 0;
+            if params.len() > regs.len() {
+                // a padding keeps the stack aligned at the call
+                if (params.len() - regs.len()) % 2 != 0 {
+                    stack_reserved += Size::Quadword.size();
+                    b.emit(AsmX32::Sub(
+                        Place::Register(Register::Register(RegisterX64::RSP)),
+                        Value::Const(stack_reserved as i32),
                     ));
                 }
-            }
 
-            let mut stack_reserved = 0;
-            if params.len() > regs.len() {
                 params
                     .iter()
                     .rev()
@@ -1952,44 +2022,59 @@ fn translate(
                             tac::Value::ID(p) => map.get(*p).into(),
                             tac::Value::Const(tac::Const::Int(p)) => Value::Const(*p),
                         };
+                        // push always stores 8 bytes in the 64 bit mode
+                        let p = match p {
+                            Value::Register(Register::Sub(reg, ..)) => {
+                                Value::Register(Register::Register(reg))
+                            }
+                            Value::Indirect(i) => Value::Indirect(Indirect {
+                                size: Size::Quadword,
+                                ..i
+                            }),
+                            p => p,
+                        };
 
-                        const PLATFORM_WORD_SIZE: usize = 8;
-                        stack_reserved += PLATFORM_WORD_SIZE;
+                        stack_reserved += Size::Quadword.size();
 
                         b.emit(AsmX32::Push(p));
                     });
             };
 
-            if map.live_at(line).contains(&Place::Register(Register::Sub(
-                RegisterX64::RAX,
-                Part::Doubleword,
-            ))) && map.get(id.unwrap())
-                != Place::Register(Register::Sub(RegisterX64::RAX, Part::Doubleword))
-            {
-                let offset = map.alloc_stack();
-                let tmp = Place::Indirect(Indirect::new(
+            // a callee may clobber any of the allocated registers
+            // so the ones which are needed after the call are saved around it
+            let saved = map
+                .live_across(line)
+                .into_iter()
+                .map(|reg| (reg, map.alloc_stack()))
+                .collect::<Vec<_>>();
+            for (reg, offset) in &saved {
+                let slot = Indirect::new(
                     Register::Register(RegisterX64::RBP),
-                    offset,
+                    *offset,
                     Size::Doubleword,
-                ));
-                let mut spill = asm::Block::new();
-                spill.emit(AsmX32::Mov(
-                    tmp.clone(),
-                    Value::Register(Register::Sub(RegisterX64::RAX, Part::Doubleword)),
+                );
+                let reg = Register::Sub(reg.clone(), Part::Doubleword);
+                b.emit(AsmX32::Mov(
+                    Place::Indirect(slot.clone()),
+                    Value::Register(reg.clone()),
                 ));
 
-                b += spill;
-
                 let mut unspill = asm::Block::new();
-                unspill.emit(AsmX32::Mov(
-                    Place::Register(Register::Sub(RegisterX64::RAX, Part::Doubleword)),
-                    tmp.into(),
-                ));
-
+                unspill.emit(AsmX32::Mov(Place::Register(reg), Value::Indirect(slot)));
                 unspills.push(unspill);
             }
 
-            b.emit(AsmX32::Call(name.to_owned()));
+            b += pass_arguments(map, &params, saved);
+
+            match tp {
+                tac::FnType::LCall => b.emit(AsmX32::Call(name.to_owned())),
+                tac::FnType::ACall(ptr) => {
+                    // r11 is neither used for arguments nor preserved by a callee
+                    let tmp = Register::Register(RegisterX64::R11);
+                    b.emit(AsmX32::Mov(Place::Register(tmp.clone()), map.get(ptr).into()));
+                    b.emit(AsmX32::CallIndirect(Value::Register(tmp)));
+                }
+            }
 
             if map.get(id.unwrap())
                 != Place::Register(Register::Sub(RegisterX64::RAX, Part::Doubleword))
@@ -2001,7 +2086,7 @@ fn translate(
             }
 
             if stack_reserved != 0 {
-                b.emit(AsmX32::Sub(
+                b.emit(AsmX32::Add(
                     Place::Register(Register::Register(RegisterX64::RSP)),
                     Value::Const(stack_reserved as i32),
                 ));
@@ -2018,3 +2103,326 @@ fn translate(
     }
     b
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{lexer::Lexer, parser};
+
+    fn compile(program: &str, opts: &Options) -> String {
+        let tokens = Lexer::new().lex_str(program).unwrap();
+        let ast = parser::parse(tokens).unwrap();
+        gen::<syntax::GASM>(tac::il(&ast, &tac::Options::default()).unwrap(), opts)
+    }
+
+    #[test]
+    fn ident_directive() {
+        let ident = format!(".ident \"simple-c-compiler {}\"", env!("CARGO_PKG_VERSION"));
+
+        let asm = compile("int main() { return 0; }", &Options::default());
+        assert!(asm.contains(&ident));
+
+        let opts = Options {
+            ident: false,
+            ..Options::default()
+        };
+        let asm = compile("int main() { return 0; }", &opts);
+        assert!(!asm.contains(".ident"));
+    }
+
+    #[test]
+    fn default_indent() {
+        let tokens = Lexer::new().lex_str("int main() { return 0; }").unwrap();
+        let ast = parser::parse(tokens).unwrap();
+        let ir = || tac::il(&ast, &tac::Options::default()).unwrap();
+
+        assert!(gen::<syntax::GASM>(ir(), &Options::default()).contains("\n    ret\n"));
+        assert!(gen::<syntax::Intel>(ir(), &Options::default()).contains("\n\tret\n"));
+
+        let opts = Options {
+            indent: Some(syntax::Indent::Spaces(2)),
+            ..Options::default()
+        };
+        assert!(gen::<syntax::Intel>(ir(), &opts).contains("\n  ret\n"));
+    }
+
+    #[test]
+    fn annotate() {
+        let program = "int inc(int a) { return a + 1; } int main() { return inc(1); }";
+
+        let asm = compile(program, &Options::default());
+        assert!(!asm.contains('#'));
+
+        let opts = Options {
+            annotate: true,
+            ..Options::default()
+        };
+        let asm = compile(program, &opts);
+        assert!(asm.contains("# t1: a + 1\n"));
+        assert!(asm.contains("# PushParam 1\n"));
+        assert!(asm.contains("# t2: LCall _inc\n"));
+        assert!(asm.contains("# PopParams 4\n"));
+    }
+
+    #[test]
+    fn const_data() {
+        let tokens = Lexer::new().lex_str("int main() { return 0; }").unwrap();
+        let ast = parser::parse(tokens).unwrap();
+        let opts = Options::default();
+
+        let mut g = Generator::new(tac::il(&ast, &tac::Options::default()).unwrap(), &opts);
+        g.const_data("_c0", &[1, 2, 255]);
+        let asm = g
+            .gen()
+            .code::<syntax::GASM>(<syntax::GASM as syntax::Syntax>::INDENT);
+
+        let rodata = asm.find(".section .rodata").unwrap();
+        let label = asm.find("_c0:").unwrap();
+        let bytes = asm.find(".byte 1, 2, 255").unwrap();
+        assert!(asm.find("ret").unwrap() < rodata);
+        assert!(rodata < label && label < bytes);
+    }
+
+    #[test]
+    fn tail_call() {
+        let program = r"
+            int sum(int n, int acc) {
+                if (n == 0)
+                    return acc;
+                return sum(n - 1, acc + n);
+            }
+        ";
+
+        let asm = compile(program, &Options::default());
+        assert!(asm.contains("call sum"));
+        assert!(!asm.contains("jmp sum"));
+
+        let opts = Options {
+            tail_calls: true,
+            ..Options::default()
+        };
+        let asm = compile(program, &opts);
+        assert!(asm.contains("jmp sum"));
+        assert!(!asm.contains("call sum"));
+    }
+
+    #[test]
+    fn leave() {
+        let program = r"
+            int inc(int a) { return a + 1; }
+            int main() { return inc(1); }
+        ";
+
+        let asm = compile(program, &Options::default());
+        assert!(!asm.contains("leave"));
+        assert!(asm.contains("movq %rbp, %rsp\n    popq %rbp"));
+
+        let opts = Options {
+            use_leave: true,
+            ..Options::default()
+        };
+        let asm = compile(program, &opts);
+        assert!(asm.contains("leave\n    ret"));
+        assert!(!asm.contains("movq %rbp, %rsp"));
+    }
+
+    #[test]
+    fn omit_frame_pointer() {
+        let program = r"
+            int twice(int a) { int b = a * 2; return b; }
+        ";
+
+        let asm = compile(program, &Options::default());
+        assert!(asm.contains("pushq %rbp"));
+
+        let opts = Options {
+            omit_frame_pointer: true,
+            ..Options::default()
+        };
+        let asm = compile(program, &opts);
+        assert!(!asm.contains("%rbp"));
+        assert!(asm.contains("(%rsp)"));
+
+        // a function which calls others keeps its frame
+        let asm = compile("int f(int a); int main() { return f(1); }", &opts);
+        assert!(asm.contains("pushq %rbp"));
+    }
+
+    #[test]
+    fn negated_relation() {
+        let asm = compile(
+            "int main() { int a = 1; int b = 2; return !(a < b); }",
+            &Options::default(),
+        );
+
+        assert_eq!(asm.matches("setge").count(), 1);
+        assert!(!asm.contains("setl"));
+        assert!(!asm.contains("setne"));
+    }
+
+    #[test]
+    fn return_type() {
+        let asm = compile("int f() { return -1; }", &Options::default());
+        assert!(!asm.contains("movs"));
+
+        let asm = compile("long f() { return -1; }", &Options::default());
+        assert!(asm.contains("movslq %eax, %rax\n"));
+
+        let asm = compile("char f() { return 300; }", &Options::default());
+        assert!(asm.contains("movsbl %al, %eax\n"));
+    }
+
+    #[test]
+    fn sub() {
+        let asm = compile("int main() { return 10 - 3; }", &Options::default());
+        assert!(asm.contains("movl $10, %ecx\n    subl $3, %ecx\n"));
+
+        let asm = compile("int main() { int a = 10; return a - 3; }", &Options::default());
+        assert!(asm.contains("movl %ecx, %esi\n    subl $3, %esi\n"));
+
+        // the constant is loaded first since it's the minuend
+        let asm = compile("int main() { int a = 10; return 3 - a; }", &Options::default());
+        assert!(asm.contains("movl $3, %esi\n    subl %ecx, %esi\n"));
+    }
+
+    #[test]
+    fn mul() {
+        let asm = compile("int main() { return 6 * 7; }", &Options::default());
+        assert!(asm.contains("movl $6, %ecx\n    imull $7, %ecx\n"));
+
+        // a constant multiplier is taken by the three operand form
+        let asm = compile("int main() { int a = 6; return a * 7; }", &Options::default());
+        assert!(asm.contains("imull $7, %ecx, %esi\n"));
+    }
+
+    #[test]
+    fn div() {
+        let asm = compile("int main() { return 17 / 5; }", &Options::default());
+        assert!(asm.contains(
+            "movl $17, %eax\n    movl $5, %esi\n    cltd\n    idivl %esi\n    movl %eax, %ecx\n"
+        ));
+
+        let asm = compile("int main() { return 17 % 5; }", &Options::default());
+        assert!(asm.contains(
+            "movl $17, %eax\n    movl $5, %esi\n    cltd\n    idivl %esi\n    movl %edx, %ecx\n"
+        ));
+    }
+
+    #[test]
+    fn relational() {
+        let asm = compile(
+            "int main() { int a = 1; int b = 2; return a < b; }",
+            &Options::default(),
+        );
+        assert!(asm.contains(
+            "cmpl %esi, %ecx\n    setl %dil\n    andb $1, %dil\n    movzbl %dil, %edi\n    movl %edi, %eax\n"
+        ));
+
+        let asm = compile("int main() { int a = 1; return a == 2; }", &Options::default());
+        assert!(asm.contains(
+            "cmpl $2, %ecx\n    sete %sil\n    andb $1, %sil\n    movzbl %sil, %esi\n    movl %esi, %eax\n"
+        ));
+    }
+
+    #[test]
+    fn unary() {
+        let asm = compile("int main() { int a = 5; return -a; }", &Options::default());
+        assert!(asm.contains("movl %ecx, %esi\n    negl %esi\n"));
+
+        let asm = compile("int main() { int a = 5; return ~a; }", &Options::default());
+        assert!(asm.contains("movl %ecx, %esi\n    notl %esi\n"));
+
+        let asm = compile("int main() { int a = 5; return !a; }", &Options::default());
+        assert!(asm.contains("cmpl $0, %ecx\n    sete %sil\n"));
+        assert!(!asm.contains("setne"));
+    }
+
+    #[test]
+    fn branches() {
+        let asm = compile("int f(int a) { if (a) return 1; return 2; }", &Options::default());
+
+        // IfZ jumps to the else branch when the condition is 0
+        assert!(asm.contains("cmpl $0, -4(%rbp)\n    je _L1\n"));
+        assert!(asm.contains("jmp _L0\n_L1:\n"));
+        assert!(asm.contains("_L1:\n    movl $2, %ecx\n"));
+    }
+
+    #[test]
+    fn assignment() {
+        let asm = compile("int main() { int a = 5; return a; }", &Options::default());
+        assert!(asm.contains("movl $5, %ecx\n    movl %ecx, %eax\n"));
+
+        // the shared return slot keeps the same register on every assignment
+        let asm = compile("int f(int a) { if (a) return 1; return 2; }", &Options::default());
+        assert!(asm.contains("movl $0, %ecx\n"));
+        assert!(asm.contains("movl $1, %ecx\n"));
+        assert!(!asm.contains("-8(%rbp)"));
+    }
+
+    #[test]
+    fn call() {
+        let asm = compile(
+            "int add(int a, int b) { return a + b; } int main() { return add(1, 2); }",
+            &Options::default(),
+        );
+        assert!(asm.contains("movl $1, %edi\n    movl $2, %esi\n    call add\n"));
+
+        // the seventh argument is passed on the stack above the saved rbp and the return address
+        let asm = compile(
+            "int g(int a, int b, int c, int d, int e, int f, int h) { return h; }
+            int main() { return g(1, 2, 3, 4, 5, 6, 7); }",
+            &Options::default(),
+        );
+        // and it's padded to keep the stack aligned
+        assert!(asm.contains("subq $8, %rsp\n    pushq $7\n"));
+        assert!(asm.contains("call g\n"));
+        assert!(asm.contains("addq $16, %rsp\n"));
+        assert!(asm.contains("movl 16(%rbp), %eax\n"));
+    }
+
+    #[test]
+    fn registers() {
+        // both sums are live at the multiplication so they get different registers
+        let asm = compile(
+            "int f(int a, int b, int c, int d) { return (a + b) * (c + d); }",
+            &Options::default(),
+        );
+        assert!(asm.contains("(%rbp), %ecx\n    addl"));
+        assert!(asm.contains("(%rbp), %esi\n    addl"));
+        assert!(asm.contains("movl %ecx, %edi\n    imull %esi, %edi\n"));
+    }
+
+    #[test]
+    fn frame() {
+        // a and b are saved below rbp since the call may overwrite their registers,
+        // the frame is rounded up to keep the stack aligned at the call
+        let asm = compile(
+            "int g() { return 1; } int f() { int a = 1; int b = 2; g(); return a + b; }",
+            &Options::default(),
+        );
+        assert!(asm.contains("f:\n    pushq %rbp\n    movq %rsp, %rbp\n    subq $16, %rsp\n"));
+        assert!(asm.contains("addq $16, %rsp\n    movq %rbp, %rsp\n    popq %rbp\n    ret\n"));
+
+        // a leaf function keeps its locals in the red zone
+        let asm = compile("int g() { int a = 1; int b = 2; return a + b; }", &Options::default());
+        assert!(!asm.contains("subq"));
+
+        // unless they don't fit it
+        let locals = (0..40)
+            .map(|i| format!("int a{} = {};", i, i))
+            .collect::<String>();
+        let sum = (0..40)
+            .map(|i| format!("a{}", i))
+            .collect::<Vec<_>>()
+            .join(" + ");
+        let asm = compile(
+            &format!("int g() {{ {} return {}; }}", locals, sum),
+            &Options::default(),
+        );
+        assert!(asm.contains("movq %rsp, %rbp\n    subq $"));
+
+        // there's nothing to reserve
+        let asm = compile("int g() { return 1; } int main() { return g(); }", &Options::default());
+        assert!(!asm.contains("$0, %rsp"));
+    }
+}
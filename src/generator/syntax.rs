@@ -1,38 +1,95 @@
 use super::asm::{AsmX32, Assembly, Indirect, Line, Offset, Place, Size, Value};
+use std::fmt;
+use std::str::FromStr;
 
 pub trait Syntax {
-    fn asm(asm: &Assembly) -> String;
-    fn translate(l: &Line) -> String;
+    /// INDENT is used when the indentation isn't set explicitly.
+    const INDENT: Indent;
+
+    fn asm(asm: &Assembly, indent: Indent) -> String;
+    fn instruction(i: &AsmX32) -> String;
+    fn comment(text: &str) -> String;
+
+    /// translate places a line regarding the indentation rules
+    /// which are the same for all syntaxes.
+    fn translate(l: &Line, indent: Indent) -> String {
+        match l {
+            Line::Label(label) | Line::Instruction(AsmX32::Label(label)) => format!("{}:", label),
+            Line::Directive(directive) => format!("{}{}", indent, directive),
+            Line::Instruction(i) => format!("{}{}", indent, Self::instruction(i)),
+            Line::Comment(text) => format!("{}{}", indent, Self::comment(text)),
+        }
+    }
+}
+
+/// Indent is a prefix of instructions and directives,
+/// labels are always placed at the beginning of a line.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Indent {
+    Tab,
+    Spaces(usize),
+}
+
+impl fmt::Display for Indent {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Indent::Tab => write!(f, "\t"),
+            Indent::Spaces(n) => write!(f, "{:1$}", "", n),
+        }
+    }
+}
+
+impl FromStr for Indent {
+    type Err = String;
+
+    /// from_str parses `tab` or `spaces:N`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "tab" => Ok(Indent::Tab),
+            _ => s
+                .strip_prefix("spaces:")
+                .and_then(|n| n.parse().ok())
+                .map(Indent::Spaces)
+                .ok_or_else(|| format!("expected `tab` or `spaces:N` but got {:?}", s)),
+        }
+    }
 }
 
 pub struct GASM;
 
 impl Syntax for GASM {
-    fn asm(asm: &Assembly) -> String {
+    const INDENT: Indent = Indent::Spaces(4);
+
+    fn asm(asm: &Assembly, indent: Indent) -> String {
         let mut buf = String::new();
         for i in asm.data.into_iter() {
-            buf.push_str(&Self::translate(i));
+            buf.push_str(&Self::translate(i, indent));
             buf.push('\n');
         }
 
         for func in asm.funcs.values() {
             for i in func.instructions() {
-                buf.push_str(&Self::translate(i));
+                buf.push_str(&Self::translate(i, indent));
                 buf.push('\n');
             }
 
             buf.push('\n');
         }
 
+        for i in asm.metadata.into_iter() {
+            buf.push_str(&Self::translate(i, indent));
+            buf.push('\n');
+        }
+
         buf
     }
 
-    fn translate(l: &Line) -> String {
-        match l {
-            Line::Label(label) => format!("{}:", label),
-            Line::Directive(directive) => format!("  {}", directive),
-            Line::Instruction(i) => format!("  {}", Self::translate_instruction(i)),
-        }
+    fn instruction(i: &AsmX32) -> String {
+        Self::translate_instruction(i)
+    }
+
+    fn comment(text: &str) -> String {
+        format!("# {}", text)
     }
 }
 
@@ -40,9 +97,9 @@ impl GASM {
     fn translate_instruction(asm: &AsmX32) -> String {
         match asm {
             AsmX32::Label(label) => format!("{}:", label),
-            AsmX32::Metadata(data) => format!("  {}", data),
+            AsmX32::Metadata(data) => format!("{}", data),
             AsmX32::Mov(p, v) => format!(
-                "  mov{} {}, {}",
+                "mov{} {}, {}",
                 Self::suffix(&v.size()),
                 Self::fmt_value(&v),
                 Self::fmt_place(&p)
@@ -54,83 +111,111 @@ impl GASM {
                 };
 
                 format!(
-                    "  {}{} {}, {}",
+                    "{}{} {}, {}",
                     instruction,
                     Self::suffix(&p.size()),
                     Self::fmt_value(&v),
                     Self::fmt_place(&p)
                 )
             }
+            AsmX32::Movsx(p, v) => format!(
+                "movs{}{} {}, {}",
+                Self::suffix(&v.size()),
+                Self::suffix(&p.size()),
+                Self::fmt_value(&v),
+                Self::fmt_place(&p)
+            ),
+            AsmX32::Lea(p, v) => format!(
+                "lea{} {}, {}",
+                Self::suffix(&p.size()),
+                Self::fmt_value(&v),
+                Self::fmt_place(&p)
+            ),
             AsmX32::And(p, v) => format!(
-                "  and{} {}, {}",
+                "and{} {}, {}",
                 Self::suffix(&p.size()),
                 Self::fmt_value(&v),
                 Self::fmt_place(&p)
             ),
             AsmX32::Or(p, v) => format!(
-                "  or{} {}, {}",
+                "or{} {}, {}",
                 Self::suffix(&v.size()),
                 Self::fmt_value(&v),
                 Self::fmt_place(&p)
             ),
             AsmX32::Xor(p, v) => format!(
-                "  xor{} {}, {}",
+                "xor{} {}, {}",
                 Self::suffix(&p.size()),
                 Self::fmt_value(&v),
                 Self::fmt_place(&p)
             ),
             AsmX32::Add(p, v) => format!(
-                "  add{} {}, {}",
+                "add{} {}, {}",
                 Self::suffix(&p.size()),
                 Self::fmt_value(&v),
                 Self::fmt_place(&p)
             ),
             AsmX32::Sub(p, v) => format!(
-                "  sub{} {}, {}",
+                "sub{} {}, {}",
+                Self::suffix(&p.size()),
+                Self::fmt_value(&v),
+                Self::fmt_place(&p)
+            ),
+            AsmX32::Sal(p, v) => format!(
+                "sal{} {}, {}",
+                Self::suffix(&p.size()),
+                Self::fmt_value(&v),
+                Self::fmt_place(&p)
+            ),
+            AsmX32::Sar(p, v) => format!(
+                "sar{} {}, {}",
                 Self::suffix(&p.size()),
                 Self::fmt_value(&v),
                 Self::fmt_place(&p)
             ),
             AsmX32::Mul(p, v) => format!(
-                "  imul{} {}, {}",
+                "imul{} {}, {}",
                 Self::suffix(&v.size()),
                 Self::fmt_value(&v),
                 Self::fmt_place(&p)
             ),
             AsmX32::Imul(c, v, reg) => format!(
-                "  imul{} ${}, {}, %{}",
+                "imul{} ${}, {}, %{}",
                 Self::suffix(&v.size()),
                 c,
                 Self::fmt_value(&v),
                 reg
             ),
-            AsmX32::Div(p) => format!("  idiv{} {}", Self::suffix(&p.size()), Self::fmt_place(&p),),
-            AsmX32::Sete(p) => format!("  sete {}", Self::fmt_place(&p),),
-            AsmX32::Setne(p) => format!("  setne {}", Self::fmt_place(&p),),
-            AsmX32::Setl(p) => format!("  setl {}", Self::fmt_place(&p),),
-            AsmX32::Setle(p) => format!("  setle {}", Self::fmt_place(&p),),
-            AsmX32::Setg(p) => format!("  setg {}", Self::fmt_place(&p),),
-            AsmX32::Setge(p) => format!("  setge {}", Self::fmt_place(&p),),
-            AsmX32::Neg(p) => format!("  neg{} {}", Self::suffix(&p.size()), Self::fmt_place(&p),),
-            AsmX32::Not(p) => format!("  not{} {}", Self::suffix(&p.size()), Self::fmt_place(&p),),
+            AsmX32::Div(p) => format!("idiv{} {}", Self::suffix(&p.size()), Self::fmt_place(&p),),
+            AsmX32::Sete(p) => format!("sete {}", Self::fmt_place(&p),),
+            AsmX32::Setne(p) => format!("setne {}", Self::fmt_place(&p),),
+            AsmX32::Setl(p) => format!("setl {}", Self::fmt_place(&p),),
+            AsmX32::Setle(p) => format!("setle {}", Self::fmt_place(&p),),
+            AsmX32::Setg(p) => format!("setg {}", Self::fmt_place(&p),),
+            AsmX32::Setge(p) => format!("setge {}", Self::fmt_place(&p),),
+            AsmX32::Neg(p) => format!("neg{} {}", Self::suffix(&p.size()), Self::fmt_place(&p),),
+            AsmX32::Not(p) => format!("not{} {}", Self::suffix(&p.size()), Self::fmt_place(&p),),
             AsmX32::Convert(t) => match t {
-                Size::Doubleword => format!("  cltd"),
-                Size::Quadword => format!("  cqto"),
+                Size::Doubleword => format!("cltd"),
+                Size::Quadword => format!("cqto"),
                 _ => unimplemented!(),
             },
-            AsmX32::Push(v) => format!("  push{} {}", Self::suffix(&v.size()), Self::fmt_value(&v)),
-            AsmX32::Pop(p) => format!("  pop{} {}", Self::suffix(&p.size()), Self::fmt_place(&p)),
+            // push always stores 8 bytes in the 64 bit mode
+            AsmX32::Push(v) => format!("pushq {}", Self::fmt_value(&v)),
+            AsmX32::Pop(p) => format!("pop{} {}", Self::suffix(&p.size()), Self::fmt_place(&p)),
             AsmX32::Cmp(rhs, lhs) => format!(
-                "  cmp{} {}, {}",
+                "cmp{} {}, {}",
                 Self::suffix(&rhs.size()),
                 Self::fmt_value(&lhs),
                 Self::fmt_place(&rhs),
             ),
-            AsmX32::Jmp(label) => format!("  jmp {}", label),
-            AsmX32::Je(label) => format!("  je {}", label),
-            AsmX32::Jne(label) => format!("  jne {}", label),
-            AsmX32::Ret => format!("  ret"),
-            AsmX32::Call(name) => format!("  call {}", name),
+            AsmX32::Jmp(label) => format!("jmp {}", label),
+            AsmX32::Je(label) => format!("je {}", label),
+            AsmX32::Jne(label) => format!("jne {}", label),
+            AsmX32::Leave => "leave".to_string(),
+            AsmX32::Ret => format!("ret"),
+            AsmX32::Call(name) => format!("call {}", name),
+            AsmX32::CallIndirect(v) => format!("call *{}", Self::fmt_value(&v)),
         }
     }
 
@@ -140,6 +225,7 @@ impl GASM {
             Place::Indirect(Indirect { offset, reg, .. }) => match offset {
                 Offset::Label(offset) => format!("{}(%{})", offset, reg),
                 Offset::Static(offset) => format!("-{}(%{})", offset, reg),
+                Offset::Above(offset) => format!("{}(%{})", offset, reg),
             },
             Place::Static(label, ..) => label.to_owned(),
         }
@@ -167,37 +253,45 @@ impl GASM {
 pub struct Intel;
 
 impl Syntax for Intel {
-    fn asm(asm: &Assembly) -> String {
+    const INDENT: Indent = Indent::Tab;
+
+    fn asm(asm: &Assembly, indent: Indent) -> String {
         let mut buf = String::new();
         for i in asm.data.into_iter() {
-            buf.push_str(&Self::translate(i));
+            buf.push_str(&Self::translate(i, indent));
             buf.push('\n');
         }
 
         buf.push('\n');
 
         for func in asm.funcs.values() {
-            buf.push_str(&Self::translate(&Line::Directive(
-                ".intel_syntax noprefix".to_owned(),
-            )));
+            buf.push_str(&Self::translate(
+                &Line::Directive(".intel_syntax noprefix".to_owned()),
+                indent,
+            ));
             buf.push('\n');
             for i in func.instructions() {
-                buf.push_str(&Self::translate(i));
+                buf.push_str(&Self::translate(i, indent));
                 buf.push('\n');
             }
 
             buf.push('\n');
         }
 
+        for i in asm.metadata.into_iter() {
+            buf.push_str(&Self::translate(i, indent));
+            buf.push('\n');
+        }
+
         buf
     }
 
-    fn translate(l: &Line) -> String {
-        match l {
-            Line::Label(label) => format!("{}:", label),
-            Line::Directive(directive) => format!("\t{}", directive),
-            Line::Instruction(i) => format!("\t{}", Self::translate_instruction(i)),
-        }
+    fn instruction(i: &AsmX32) -> String {
+        Self::translate_instruction(i)
+    }
+
+    fn comment(text: &str) -> String {
+        format!("; {}", text)
     }
 }
 
@@ -222,6 +316,22 @@ impl Intel {
                     Self::fmt_place(&p)
                 )
             }
+            AsmX32::Movsx(p, v) => {
+                let instruction = match v.size() {
+                    Size::Doubleword => "movsxd",
+                    _ => "movsx",
+                };
+
+                format!(
+                    "{} {2}, {1}",
+                    instruction,
+                    Self::fmt_value(&v),
+                    Self::fmt_place(&p)
+                )
+            }
+            AsmX32::Lea(p, v) => {
+                format!("lea {1}, {0}", Self::fmt_value(&v), Self::fmt_place(&p))
+            }
             AsmX32::And(p, v) => {
                 format!("and {1}, {0}", Self::fmt_value(&v), Self::fmt_place(&p))
             }
@@ -235,6 +345,12 @@ impl Intel {
             AsmX32::Sub(p, v) => {
                 format!("sub {1}, {0}", Self::fmt_value(&v), Self::fmt_place(&p))
             }
+            AsmX32::Sal(p, v) => {
+                format!("sal {1}, {0}", Self::fmt_value(&v), Self::fmt_place(&p))
+            }
+            AsmX32::Sar(p, v) => {
+                format!("sar {1}, {0}", Self::fmt_value(&v), Self::fmt_place(&p))
+            }
             AsmX32::Mul(p, v) => {
                 format!("imul {1}, {0}", Self::fmt_value(&v), Self::fmt_place(&p))
             }
@@ -263,18 +379,28 @@ impl Intel {
             AsmX32::Jmp(label) => format!("jmp {}", label),
             AsmX32::Je(label) => format!("je {}", label),
             AsmX32::Jne(label) => format!("jne {}", label),
+            AsmX32::Leave => "leave".to_string(),
             AsmX32::Ret => format!("ret"),
             AsmX32::Call(name) => format!("call {}", name),
+            AsmX32::CallIndirect(v) => format!("call {}", Self::fmt_value(&v)),
         }
     }
 
     fn fmt_place(p: &Place) -> String {
         match p {
             Place::Register(reg) => format!("{}", reg),
-            Place::Indirect(Indirect { offset, reg, .. }) => match offset {
-                Offset::Label(offset) => format!("dword ptr {1}[{0}]", reg, offset),
-                Offset::Static(offset) => format!("dword ptr [{} - {}]", reg, offset),
-            },
+            Place::Indirect(Indirect { offset, reg, size }) => {
+                let ptr = match size {
+                    Size::Quadword => "qword ptr",
+                    _ => "dword ptr",
+                };
+
+                match offset {
+                    Offset::Label(offset) => format!("{} {2}[{1}]", ptr, reg, offset),
+                    Offset::Static(offset) => format!("{} [{} - {}]", ptr, reg, offset),
+                    Offset::Above(offset) => format!("{} [{} + {}]", ptr, reg, offset),
+                }
+            }
             Place::Static(label, ..) => label.to_owned(),
         }
     }
@@ -313,9 +439,85 @@ mod gasm {
             Value::Const(4),
         );
 
-        assert_eq!("  addl $1, %eax", GASM::translate_instruction(&full_double));
-        assert_eq!("  addq $2, %rax", GASM::translate_instruction(&full_quad));
-        assert_eq!("  addq $3, %eax", GASM::translate_instruction(&const_quad));
-        assert_eq!("  addl $4, %rax", GASM::translate_instruction(&place_quad));
+        assert_eq!("addl $1, %eax", GASM::translate_instruction(&full_double));
+        assert_eq!("addq $2, %rax", GASM::translate_instruction(&full_quad));
+        assert_eq!("addq $3, %eax", GASM::translate_instruction(&const_quad));
+        assert_eq!("addl $4, %rax", GASM::translate_instruction(&place_quad));
+    }
+
+    #[test]
+    fn instructions() {
+        let eax = || Place::Register(Register::Sub(RegisterX64::RAX, Part::Doubleword));
+        let ecx = || Value::Register(Register::Sub(RegisterX64::RCX, Part::Doubleword));
+        let local = Place::Indirect(Indirect::new(
+            Register::Register(RegisterX64::RBP),
+            4,
+            Size::Doubleword,
+        ));
+        let param = Value::Indirect(Indirect {
+            reg: Register::Register(RegisterX64::RBP),
+            offset: Offset::Above(16),
+            size: Size::Doubleword,
+        });
+        let al = Place::Register(Register::Sub(RegisterX64::RAX, Part::Byte));
+
+        let cases = vec![
+            (AsmX32::Sub(eax(), Value::Const(1)), "subl $1, %eax"),
+            (AsmX32::Sub(local.clone(), ecx()), "subl %ecx, -4(%rbp)"),
+            (AsmX32::Mul(eax(), ecx()), "imull %ecx, %eax"),
+            (
+                AsmX32::Imul(3, param, Register::Sub(RegisterX64::RAX, Part::Doubleword)),
+                "imull $3, 16(%rbp), %eax",
+            ),
+            (
+                AsmX32::Cmp(local.clone(), Value::Const(0)),
+                "cmpl $0, -4(%rbp)",
+            ),
+            (AsmX32::Jmp("_L0".to_owned()), "jmp _L0"),
+            (AsmX32::Je("_L1".to_owned()), "je _L1"),
+            (AsmX32::Jne("_L2".to_owned()), "jne _L2"),
+            (AsmX32::Neg(eax()), "negl %eax"),
+            (AsmX32::Not(local), "notl -4(%rbp)"),
+            (AsmX32::Sete(al.clone()), "sete %al"),
+            (AsmX32::Setne(al.clone()), "setne %al"),
+            (AsmX32::Setl(al.clone()), "setl %al"),
+            (AsmX32::Setle(al.clone()), "setle %al"),
+            (AsmX32::Setg(al.clone()), "setg %al"),
+            (AsmX32::Setge(al), "setge %al"),
+            (AsmX32::Push(Value::Const(7)), "pushq $7"),
+        ];
+
+        for (instruction, expected) in cases {
+            assert_eq!(expected, GASM::translate_instruction(&instruction));
+        }
+    }
+
+    #[test]
+    fn comment() {
+        assert_eq!("# foo", GASM::comment("foo"));
+        assert_eq!("; foo", Intel::comment("foo"));
+
+        let comment = Line::Comment("foo".to_owned());
+        assert_eq!("\t# foo", GASM::translate(&comment, Indent::Tab));
+        assert_eq!("  ; foo", Intel::translate(&comment, Indent::Spaces(2)));
+    }
+
+    #[test]
+    fn indent() {
+        let label = Line::Label("main".to_owned());
+        let directive = Line::Directive(".text".to_owned());
+        let ret = Line::Instruction(AsmX32::Ret);
+
+        assert_eq!("main:", GASM::translate(&label, Indent::Tab));
+        assert_eq!("\t.text", GASM::translate(&directive, Indent::Tab));
+        assert_eq!("\tret", GASM::translate(&ret, Indent::Tab));
+
+        assert_eq!("main:", Intel::translate(&label, Indent::Spaces(2)));
+        assert_eq!("  .text", Intel::translate(&directive, Indent::Spaces(2)));
+        assert_eq!("  ret", Intel::translate(&ret, Indent::Spaces(2)));
+
+        assert_eq!("tab".parse(), Ok(Indent::Tab));
+        assert_eq!("spaces:8".parse(), Ok(Indent::Spaces(8)));
+        assert!("spaces".parse::<Indent>().is_err());
     }
 }
@@ -0,0 +1,119 @@
+use super::tac::{Branch, ControlOp, FuncDef, Instruction, InstructionLine, Label};
+use std::collections::{BTreeSet, HashMap};
+use std::ops::Range;
+
+/// Cfg is a control flow graph of a function,
+/// the first block is an entry of the function.
+pub struct Cfg {
+    pub blocks: Vec<Block>,
+}
+
+/// Block is a basic block, a sequence of instructions
+/// which can be entered only at the beginning and left only at the end.
+pub struct Block {
+    /// instructions is a range of the block's instructions in a function.
+    pub instructions: Range<usize>,
+    pub predecessors: Vec<usize>,
+    pub successors: Vec<usize>,
+}
+
+/// build splits the function's instructions into basic blocks
+/// on labels and after branches and returns.
+pub fn build(func: &FuncDef) -> Cfg {
+    let instructions = &func.instructions;
+
+    let mut leaders = BTreeSet::new();
+    leaders.insert(0);
+    for (index, InstructionLine(i, _)) in instructions.iter().enumerate() {
+        match i {
+            Instruction::ControlOp(ControlOp::Label(..)) => {
+                leaders.insert(index);
+            }
+            Instruction::ControlOp(ControlOp::Branch(..))
+            | Instruction::ControlOp(ControlOp::Return(..)) => {
+                leaders.insert(index + 1);
+            }
+            _ => (),
+        }
+    }
+
+    let starts = leaders
+        .into_iter()
+        .filter(|&index| index < instructions.len())
+        .collect::<Vec<_>>();
+    let mut blocks = starts
+        .iter()
+        .enumerate()
+        .map(|(n, &start)| Block {
+            instructions: start..starts.get(n + 1).cloned().unwrap_or(instructions.len()),
+            predecessors: Vec::new(),
+            successors: Vec::new(),
+        })
+        .collect::<Vec<_>>();
+
+    let labels = blocks
+        .iter()
+        .enumerate()
+        .filter_map(
+            |(n, block)| match instructions[block.instructions.start].0 {
+                Instruction::ControlOp(ControlOp::Label(label)) => Some((label, n)),
+                _ => None,
+            },
+        )
+        .collect::<HashMap<Label, usize>>();
+
+    for n in 0..blocks.len() {
+        let next = Some(n + 1).filter(|&next| next < blocks.len());
+        let mut successors = match &instructions[blocks[n].instructions.end - 1].0 {
+            Instruction::ControlOp(ControlOp::Return(..)) => Vec::new(),
+            Instruction::ControlOp(ControlOp::Branch(Branch::GOTO(label))) => vec![labels[label]],
+            Instruction::ControlOp(ControlOp::Branch(Branch::IfGOTO(_, label)))
+            | Instruction::ControlOp(ControlOp::Branch(Branch::IfNotZeroGOTO(_, label))) => {
+                std::iter::once(labels[label]).chain(next).collect()
+            }
+            _ => next.into_iter().collect(),
+        };
+        successors.dedup();
+
+        for &s in &successors {
+            blocks[s].predecessors.push(n);
+        }
+        blocks[n].successors = successors;
+    }
+
+    Cfg { blocks }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::il::tac;
+    use crate::{lexer::Lexer, parser};
+
+    #[test]
+    fn while_loop() {
+        let program = "int main() { int i = 0; while (i < 10) i = i + 1; return i; }";
+        let tokens = Lexer::new().lex_str(program).unwrap();
+        let ast = parser::parse(tokens).unwrap();
+        let opts = tac::Options {
+            simple_loops: true,
+            ..tac::Options::default()
+        };
+        let func = tac::il(&ast, &opts).unwrap().code.remove(0);
+        let cfg = build(&func);
+
+        // i: 0
+        // header: condition and the exit branch
+        // body: increment and the jump back
+        // exit: return
+        assert_eq!(cfg.blocks.len(), 4);
+        assert_eq!(cfg.blocks[0].successors, vec![1]);
+        assert_eq!(cfg.blocks[1].successors, vec![3, 2]);
+        assert_eq!(cfg.blocks[2].successors, vec![1]);
+        assert!(cfg.blocks[3].successors.is_empty());
+
+        // the back edge from the body to the header
+        assert_eq!(cfg.blocks[1].predecessors, vec![0, 2]);
+        assert_eq!(cfg.blocks[3].predecessors, vec![1]);
+    }
+}
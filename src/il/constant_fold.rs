@@ -15,16 +15,18 @@ fn op_fold(constants: &HashMap<ID, i32>, i: &mut Instruction) {
     use tac::{ArithmeticOp::*, BitwiseOp::*, EqualityOp::*, RelationalOp::*, TypeOp::*};
 
     let checks: Vec<(TypeOp, Box<dyn FnOnce(i32, i32) -> i32>)> = vec![
-        (Arithmetic(Add), Box::new(|lhs: i32, rhs: i32| lhs + rhs)),
-        (Arithmetic(Sub), Box::new(|lhs: i32, rhs: i32| lhs - rhs)),
-        (Arithmetic(Mul), Box::new(|lhs: i32, rhs: i32| lhs * rhs)),
-        (Arithmetic(Mod), Box::new(|lhs: i32, rhs: i32| lhs % rhs)),
-        (Arithmetic(Div), Box::new(|lhs: i32, rhs: i32| lhs / rhs)),
+        // overflow wraps the same way it does in the generated code
+        (Arithmetic(Add), Box::new(|lhs: i32, rhs: i32| lhs.wrapping_add(rhs))),
+        (Arithmetic(Sub), Box::new(|lhs: i32, rhs: i32| lhs.wrapping_sub(rhs))),
+        (Arithmetic(Mul), Box::new(|lhs: i32, rhs: i32| lhs.wrapping_mul(rhs))),
+        (Arithmetic(Mod), Box::new(|lhs: i32, rhs: i32| lhs.wrapping_rem(rhs))),
+        (Arithmetic(Div), Box::new(|lhs: i32, rhs: i32| lhs.wrapping_div(rhs))),
         (Bit(And), Box::new(|lhs: i32, rhs: i32| lhs & rhs)),
         (Bit(Or), Box::new(|lhs: i32, rhs: i32| lhs | rhs)),
         (Bit(Xor), Box::new(|lhs: i32, rhs: i32| lhs ^ rhs)),
-        (Bit(LShift), Box::new(|lhs: i32, rhs: i32| lhs << rhs)),
-        (Bit(RShift), Box::new(|lhs: i32, rhs: i32| lhs >> rhs)),
+        // shift count is masked to 5 bits the same way x86 does it
+        (Bit(LShift), Box::new(|lhs: i32, rhs: i32| lhs.wrapping_shl(rhs as u32))),
+        (Bit(RShift), Box::new(|lhs: i32, rhs: i32| lhs.wrapping_shr(rhs as u32))),
         (
             Equality(Equal),
             Box::new(|lhs: i32, rhs: i32| (lhs == rhs) as i32),
@@ -60,7 +62,7 @@ fn op_fold(constants: &HashMap<ID, i32>, i: &mut Instruction) {
 
     use UnOp::*;
     let checks: Vec<(UnOp, Box<dyn FnOnce(i32) -> i32>)> = vec![
-        (Neg, Box::new(|v: i32| -v)),
+        (Neg, Box::new(|v: i32| v.wrapping_neg())),
         (BitComplement, Box::new(|v: i32| !v)),
         (LogicNeg, Box::new(|v: i32| i32::from(v == 0))),
     ];
@@ -0,0 +1,141 @@
+use super::tac::{self, ControlOp, FuncDef, Instruction, InstructionLine, Op, Value, ID};
+use super::unused_code;
+
+/// propagate replaces a temporary which only copies a variable, e.g. `t0: a`,
+/// by the variable in the single place the temporary is used.
+///
+/// The copy is kept when the variable may change before the use,
+/// which is when it's redefined or a label or a call is met on the way.
+pub fn propagate(mut func: FuncDef) -> FuncDef {
+    let mut index = 0;
+    while index < func.instructions.len() {
+        let copy = match &func.instructions[index] {
+            InstructionLine(Instruction::Alloc(Value::ID(src)), Some(dst))
+                if !func.ctx.is_variable(*dst) =>
+            {
+                Some((*dst, *src))
+            }
+            _ => None,
+        };
+
+        let found = copy.and_then(|(dst, src)| {
+            find_use(&func.instructions, index, dst, src).map(|use_index| (dst, src, use_index))
+        });
+
+        match found {
+            Some((dst, src, use_index)) => {
+                for_values(&mut func.instructions[use_index].0, |value| {
+                    if matches!(value, Value::ID(id) if *id == dst) {
+                        *value = Value::ID(src);
+                    }
+                });
+                func.instructions.remove(index);
+            }
+            None => index += 1,
+        }
+    }
+
+    func
+}
+
+/// find_use returns the position of the only use of `dst`
+/// if `src` still holds the copied value there.
+fn find_use(instructions: &[InstructionLine], index: usize, dst: ID, src: ID) -> Option<usize> {
+    let count_uses = |i: &Instruction| unused_code::used(i).iter().filter(|&&id| id == dst).count();
+    if instructions.iter().map(|i| count_uses(&i.0)).sum::<usize>() != 1 {
+        return None;
+    }
+
+    for (use_index, InstructionLine(i, id)) in instructions.iter().enumerate().skip(index + 1) {
+        if count_uses(i) == 1 {
+            // an address of a call isn't a value so it can't be replaced
+            return match i {
+                Instruction::Call(tac::Call {
+                    tp: tac::FnType::ACall(id),
+                    ..
+                }) if *id == dst => None,
+                _ => Some(use_index),
+            };
+        }
+
+        let redefined = *id == Some(src) || *id == Some(dst);
+        let barrier = matches!(
+            i,
+            Instruction::ControlOp(ControlOp::Label(..)) | Instruction::Call(..)
+        );
+        if redefined || barrier {
+            return None;
+        }
+    }
+
+    None
+}
+
+/// for_values calls `f` on every value the instruction reads.
+fn for_values<F: FnMut(&mut Value)>(i: &mut Instruction, mut f: F) {
+    match i {
+        Instruction::Assignment(_, v)
+        | Instruction::Op(Op::Unary(_, v))
+        | Instruction::ControlOp(ControlOp::Return(v))
+        | Instruction::ControlOp(ControlOp::Branch(tac::Branch::IfGOTO(v, ..)))
+        | Instruction::ControlOp(ControlOp::Branch(tac::Branch::IfNotZeroGOTO(v, ..))) => f(v),
+        Instruction::Op(Op::Op(_, v1, v2)) => {
+            f(v1);
+            f(v2);
+        }
+        Instruction::Call(call) => call.params.iter_mut().for_each(f),
+        Instruction::Alloc(..)
+        | Instruction::FuncAddr(..)
+        | Instruction::ControlOp(ControlOp::Label(..))
+        | Instruction::ControlOp(ControlOp::Branch(tac::Branch::GOTO(..))) => (),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::il::il_main;
+    use crate::il::tac::{ArithmeticOp, Const, TypeOp};
+
+    fn add_one(value: Value) -> Instruction {
+        Instruction::Op(Op::Op(
+            TypeOp::Arithmetic(ArithmeticOp::Add),
+            value,
+            Value::Const(Const::Int(1)),
+        ))
+    }
+
+    #[test]
+    fn propagate_copy() {
+        let mut func = il_main("int main() { int a = 1; return a; }");
+        let a = 0;
+        assert!(func.ctx.is_variable(a));
+
+        // t10: a
+        // t11: t10 + 1
+        // Return t11
+        func.instructions = vec![
+            InstructionLine(Instruction::Alloc(Value::ID(a)), Some(10)),
+            InstructionLine(add_one(Value::ID(10)), Some(11)),
+            InstructionLine(
+                Instruction::ControlOp(ControlOp::Return(Value::ID(11))),
+                None,
+            ),
+        ];
+
+        let func = propagate(func);
+        assert_eq!(func.instructions.len(), 2);
+        assert!(matches!(
+            func.instructions[0],
+            InstructionLine(Instruction::Op(Op::Op(_, Value::ID(id), _)), Some(11)) if id == a
+        ));
+    }
+
+    #[test]
+    fn keep_redefined_copy() {
+        // a copy for the postfix increment is read after the variable is changed
+        let func = il_main("int main() { int a = 1; int b = a++ + 1; return b; }");
+        let len = func.instructions.len();
+        assert_eq!(propagate(func).instructions.len(), len);
+    }
+}
@@ -1,4 +1,8 @@
-use super::tac::{Branch, Call, ControlOp, Instruction, InstructionLine, Op, Value, ID};
+use super::cfg::Cfg;
+use super::liveness::Liveness;
+use super::tac::{
+    Branch, Call, ControlOp, FnType, FuncDef, Instruction, InstructionLine, Op, Value, ID,
+};
 use std::collections::BTreeMap;
 
 pub struct LiveIntervals(pub BTreeMap<ID, Range>);
@@ -23,39 +27,41 @@ impl Range {
 }
 
 impl LiveIntervals {
-    pub fn new(instructions: &[InstructionLine]) -> Self {
-        let mut intervals = BTreeMap::new();
-        for (index, InstructionLine(i, id)) in instructions.iter().enumerate().rev() {
-            match id {
-                Some(id) => {
-                    // end is equal index + 1
-                    // to show that it's should live more then index
-                    // but on index + 1 it can be utilized as well
-                    //
-                    // Otherwise we could not recognize difference
-                    // between both these expressions
-                    //
-                    // `a = b + c` where let's imagine it's a place of `b`'s last absence
-                    // and `d = e + f` where let's imagine all of these live after this expression
-                    intervals
-                        .entry(*id)
-                        .and_modify(|e: &mut Range| e.start = index)
-                        .or_insert(Range {
-                            start: index,
-                            end: index + 1,
-                        });
-                }
-                _ => (),
+    /// from_liveness builds intervals which span every instruction
+    /// where an id is live according to the dataflow analysis.
+    ///
+    /// An id which is live on the entry or the exit of a block
+    /// covers the whole block, so the ones used in a loop live through all of it.
+    pub fn from_liveness(func: &FuncDef, cfg: &Cfg, liveness: &Liveness) -> Self {
+        let mut intervals: BTreeMap<ID, Range> = BTreeMap::new();
+        let mut extend = |id: ID, index: usize| {
+            intervals
+                .entry(id)
+                .and_modify(|r| {
+                    r.start = r.start.min(index);
+                    r.end = r.end.max(index);
+                })
+                .or_insert(Range {
+                    start: index,
+                    end: index,
+                });
+        };
+
+        for (index, InstructionLine(i, id)) in func.instructions.iter().enumerate() {
+            id.iter()
+                .chain(instruction_ids(i).iter())
+                .for_each(|id| extend(*id, index));
+            if let Instruction::Assignment(id, ..) = i {
+                extend(*id, index);
             }
+        }
 
-            for id in instruction_ids(i).into_iter() {
-                intervals
-                    .entry(id)
-                    .and_modify(|e: &mut Range| e.start = index)
-                    .or_insert(Range {
-                        start: index,
-                        end: index,
-                    });
+        for (n, block) in cfg.blocks.iter().enumerate() {
+            for id in &liveness.live_in[n] {
+                extend(*id, block.instructions.start);
+            }
+            for id in &liveness.live_out[n] {
+                extend(*id, block.instructions.end - 1);
             }
         }
 
@@ -83,6 +89,14 @@ fn instruction_ids(i: &Instruction) -> Vec<ID> {
         }
     }
 
+    if let Instruction::Call(Call {
+        tp: FnType::ACall(id),
+        ..
+    }) = i
+    {
+        ids.push(*id)
+    }
+
     ids
 }
 
@@ -91,6 +105,7 @@ fn instruction_values(i: &Instruction) -> Vec<&Value> {
     match i {
         Instruction::Alloc(v) => values.push(v),
         Instruction::Assignment(.., v) => values.push(v),
+        Instruction::FuncAddr(..) => (),
         Instruction::Op(Op::Unary(.., v)) => values.push(v),
         Instruction::Op(Op::Op(.., v1, v2)) => {
             values.push(v1);
@@ -102,7 +117,8 @@ fn instruction_values(i: &Instruction) -> Vec<&Value> {
             }
         }
         Instruction::ControlOp(op) => match op {
-            ControlOp::Branch(Branch::IfGOTO(v, ..)) => values.push(v),
+            ControlOp::Branch(Branch::IfGOTO(v, ..))
+            | ControlOp::Branch(Branch::IfNotZeroGOTO(v, ..)) => values.push(v),
             ControlOp::Return(v) => values.push(v),
             _ => (),
         },
@@ -0,0 +1,95 @@
+use super::cfg::Cfg;
+use super::tac::{FuncDef, InstructionLine, ID};
+use super::unused_code;
+use std::collections::HashSet;
+
+/// Liveness holds ids which are live on the entry and the exit of each block,
+/// it's indexed the same way as `Cfg::blocks`.
+pub struct Liveness {
+    pub live_in: Vec<HashSet<ID>>,
+    pub live_out: Vec<HashSet<ID>>,
+}
+
+/// liveness computes live ids of blocks
+/// by a backward dataflow analysis which runs until nothing changes.
+pub fn liveness(func: &FuncDef, cfg: &Cfg) -> Liveness {
+    let (uses, defs): (Vec<_>, Vec<_>) = cfg
+        .blocks
+        .iter()
+        .map(|block| uses_and_defs(&func.instructions[block.instructions.clone()]))
+        .unzip();
+
+    let mut live_in = vec![HashSet::new(); cfg.blocks.len()];
+    let mut live_out = vec![HashSet::new(); cfg.blocks.len()];
+    let mut changed = true;
+    while changed {
+        changed = false;
+        for n in (0..cfg.blocks.len()).rev() {
+            let exit = cfg.blocks[n]
+                .successors
+                .iter()
+                .flat_map(|&s| live_in[s].iter().cloned())
+                .collect::<HashSet<ID>>();
+            let entry = uses[n]
+                .union(&exit.difference(&defs[n]).cloned().collect())
+                .cloned()
+                .collect::<HashSet<ID>>();
+
+            if entry != live_in[n] || exit != live_out[n] {
+                changed = true;
+                live_in[n] = entry;
+                live_out[n] = exit;
+            }
+        }
+    }
+
+    Liveness { live_in, live_out }
+}
+
+/// uses_and_defs returns ids which are read in the block before they're defined in it
+/// and ids which are defined in the block.
+fn uses_and_defs(instructions: &[InstructionLine]) -> (HashSet<ID>, HashSet<ID>) {
+    let mut uses = HashSet::new();
+    let mut defs = HashSet::new();
+    for InstructionLine(i, id) in instructions {
+        for used in unused_code::used(i) {
+            if !defs.contains(&used) {
+                uses.insert(used);
+            }
+        }
+
+        if let Some(id) = id {
+            defs.insert(*id);
+        }
+    }
+
+    (uses, defs)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::il::{cfg, il_main};
+
+    #[test]
+    fn not_live_across_branch() {
+        let program = "int f(int c) { int a = c + 1; int b = a * 2; if (c) return b; return 0; }";
+        let func = il_main(program);
+        let var = |name| {
+            func.instructions
+                .iter()
+                .filter_map(|i| i.1)
+                .find(|&id| func.ctx.ident_by_id(id) == Some(name))
+                .unwrap()
+        };
+        let (c, a, b) = (func.parameters[0], var("a"), var("b"));
+
+        let cfg = cfg::build(&func);
+        let live = liveness(&func, &cfg);
+
+        // `a` is used only to compute `b` before the branch on `c`
+        assert_eq!(live.live_in[0], vec![c].into_iter().collect());
+        assert_eq!(live.live_out[0], vec![b].into_iter().collect());
+        assert!(live.live_in.iter().all(|ids| !ids.contains(&a)));
+    }
+}
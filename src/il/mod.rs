@@ -1,4 +1,20 @@
 pub mod tac;
 pub mod lifeinterval;
+pub mod cfg;
+pub mod liveness;
 pub mod constant_fold;
+pub mod copy_propagation;
 pub mod unused_code;
+pub mod passes;
+pub mod pretty;
+
+/// il_main lowers the program and returns its first defined function.
+#[cfg(test)]
+fn il_main(program: &str) -> tac::FuncDef {
+    let tokens = crate::lexer::Lexer::new().lex_str(program).unwrap();
+    let ast = crate::parser::parse(tokens).unwrap();
+    tac::il(&ast, &tac::Options::default())
+        .unwrap()
+        .code
+        .remove(0)
+}
@@ -0,0 +1,104 @@
+use super::constant_fold;
+use super::copy_propagation;
+use super::tac::FuncDef;
+use super::unused_code;
+
+/// Pass is an optimization over a function's three address code.
+pub struct Pass {
+    pub name: &'static str,
+    pub description: &'static str,
+    run: fn(FuncDef) -> FuncDef,
+}
+
+/// PASSES lists the optimizations in the order they are run by `-O`.
+pub const PASSES: &[Pass] = &[
+    Pass {
+        name: "const-fold",
+        description: "evaluates operations over constants at compile time",
+        run: const_fold,
+    },
+    Pass {
+        name: "copy-prop",
+        description: "replaces copies of variables by the variables where it's safe",
+        run: copy_propagation::propagate,
+    },
+    Pass {
+        name: "unused-code",
+        description: "removes temporaries which are never used",
+        run: unused_code::remove_unused,
+    },
+];
+
+pub fn find(name: &str) -> Option<&'static Pass> {
+    PASSES.iter().find(|p| p.name == name)
+}
+
+/// run applies every pass which isn't listed in `disabled`.
+pub fn run<S: AsRef<str>>(mut func: FuncDef, disabled: &[S]) -> FuncDef {
+    for pass in PASSES {
+        if !disabled.iter().any(|name| name.as_ref() == pass.name) {
+            func = (pass.run)(func);
+        }
+    }
+
+    func
+}
+
+fn const_fold(mut func: FuncDef) -> FuncDef {
+    constant_fold::fold(&mut func.instructions);
+    func
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::il::il_main;
+    use crate::il::tac::{self, Instruction};
+
+    fn count_ops(func: &FuncDef) -> usize {
+        func.instructions
+            .iter()
+            .filter(|i| matches!(i.0, Instruction::Op(..)))
+            .count()
+    }
+
+    #[test]
+    fn disable_pass() {
+        let program = "int main() { 1 + 2; return 2 * 3; }";
+        assert_eq!(count_ops(&il_main(program)), 2);
+
+        let all = run::<&str>(il_main(program), &[]);
+        assert_eq!(count_ops(&all), 0);
+
+        let no_fold = run(il_main(program), &["const-fold"]);
+        assert_eq!(count_ops(&no_fold), 1);
+
+        let no_unused = run(il_main(program), &["unused-code"]);
+        assert_eq!(count_ops(&no_unused), 0);
+        assert_eq!(no_unused.instructions.len(), 3);
+    }
+
+    #[test]
+    fn fold_nested_constants() {
+        let program = "int main() { return 2 * 3 + 4; }";
+        let func = il_main(program);
+        assert_eq!(count_ops(&func), 2);
+        assert_eq!(func.instructions.len(), 3);
+
+        let func = run::<&str>(func, &[]);
+        assert_eq!(count_ops(&func), 0);
+        assert_eq!(func.instructions.len(), 2);
+        assert!(matches!(
+            func.instructions[0].0,
+            Instruction::Alloc(tac::Value::Const(tac::Const::Int(10)))
+        ));
+    }
+
+    #[test]
+    fn find_pass() {
+        assert!(find("const-fold").is_some());
+        assert!(find("copy-prop").is_some());
+        assert!(find("unused-code").is_some());
+        assert!(find("inline").is_none());
+    }
+}
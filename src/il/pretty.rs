@@ -0,0 +1,238 @@
+use std::fmt::Write;
+
+use super::tac;
+
+pub fn pretty(fun: &tac::FuncDef) -> String {
+    let mut w = String::new();
+    writeln!(w, "{}:", pretty_fun_name(&fun.name)).unwrap();
+    fun.parameters
+        .iter()
+        .map(|id| format!("param {}", pretty_id(id, &fun.ctx)))
+        .for_each(|p| {
+            writeln!(w, "  {}", p).unwrap();
+        });
+    writeln!(w, "  BeginFunc {}", fun.frame_size).unwrap();
+
+    for line in &fun.instructions {
+        w += &pretty_instruction(line, &fun.ctx);
+    }
+
+    w
+}
+
+/// pretty_instruction renders an instruction the way `pretty` does,
+/// a call takes several lines as it's surrounded by its parameters.
+pub fn pretty_instruction(line: &tac::InstructionLine, ctx: &tac::Context) -> String {
+    let tac::InstructionLine(inst, id) = line;
+    let mut w = String::new();
+    match inst {
+        tac::Instruction::Alloc(val) => {
+            writeln!(
+                w,
+                "  {}: {}",
+                pretty_id(id.as_ref().unwrap(), ctx),
+                pretty_value(val, ctx),
+            )
+            .unwrap();
+        }
+        tac::Instruction::Assignment(id1, v) => {
+            writeln!(w, "  {}: {}", pretty_id(id1, ctx), pretty_value(v, ctx)).unwrap();
+        }
+        tac::Instruction::FuncAddr(ptr, name) => {
+            writeln!(w, "  {}: &{}", pretty_id(ptr, ctx), pretty_fun_name(name)).unwrap();
+        }
+        tac::Instruction::Call(call) => {
+            for p in call.params.iter() {
+                writeln!(w, "  PushParam {}", pretty_value(p, ctx)).unwrap();
+            }
+
+            let callee = match call.tp {
+                tac::FnType::LCall => format!("LCall {}", pretty_fun_name(&call.name)),
+                tac::FnType::ACall(ptr) => format!("ACall {}", pretty_id(&ptr, ctx)),
+            };
+            writeln!(w, "  {}: {}", pretty_id(id.as_ref().unwrap(), ctx), callee).unwrap();
+            writeln!(w, "  PopParams {}", call.pop_size).unwrap();
+        }
+        tac::Instruction::Op(op) => {
+            match op {
+                tac::Op::Op(t, v1, v2) => {
+                    writeln!(
+                        w,
+                        "  {}: {} {} {}",
+                        pretty_id(id.as_ref().unwrap(), ctx),
+                        pretty_value(v1, ctx),
+                        pretty_type(t),
+                        pretty_value(v2, ctx)
+                    )
+                    .unwrap();
+                }
+                tac::Op::Unary(op, v1) => {
+                    writeln!(
+                        w,
+                        "  {}: {} {}",
+                        pretty_id(id.as_ref().unwrap(), ctx),
+                        pretty_unary_op(op),
+                        pretty_value(v1, ctx),
+                    )
+                    .unwrap();
+                }
+            };
+        }
+        tac::Instruction::ControlOp(cop) => match cop {
+            tac::ControlOp::Label(label) => {
+                writeln!(w, "{}:", pretty_label(label)).unwrap();
+            }
+            tac::ControlOp::Branch(lb) => match lb {
+                tac::Branch::GOTO(label) => {
+                    writeln!(w, "  Goto {}", pretty_label(label)).unwrap();
+                }
+                tac::Branch::IfGOTO(v, label) => {
+                    writeln!(
+                        w,
+                        "  IfZ {} Goto {}",
+                        pretty_value(v, ctx),
+                        pretty_label(label)
+                    )
+                    .unwrap();
+                }
+                tac::Branch::IfNotZeroGOTO(v, label) => {
+                    writeln!(
+                        w,
+                        "  IfNZ {} Goto {}",
+                        pretty_value(v, ctx),
+                        pretty_label(label)
+                    )
+                    .unwrap();
+                }
+            },
+            tac::ControlOp::Return(v) => writeln!(w, "  Return {}", pretty_value(v, ctx)).unwrap(),
+        },
+    }
+
+    w
+}
+
+pub fn pretty_value(v: &tac::Value, ctx: &tac::Context) -> String {
+    match v {
+        tac::Value::Const(tac::Const::Int(c)) => format!("{}", c),
+        tac::Value::ID(id) => pretty_id(id, &ctx),
+    }
+}
+
+pub fn pretty_id(id: &tac::ID, ctx: &tac::Context) -> String {
+    match ctx.ident_by_id(*id) {
+        Some(name) => format!("{}", name),
+        None => format!("t{}", id),
+    }
+}
+
+pub fn pretty_label(label: &tac::Label) -> String {
+    format!("_L{}", label)
+}
+
+pub fn pretty_fun_name(name: &str) -> String {
+    if name == "main" {
+        name.to_string()
+    } else {
+        format!("_{}", name)
+    }
+}
+
+pub fn pretty_type(op: &tac::TypeOp) -> String {
+    match op {
+        tac::TypeOp::Arithmetic(op) => pretty_arith_op(op),
+        tac::TypeOp::Relational(op) => pretty_rel_op(op),
+        tac::TypeOp::Equality(op) => pretty_eq_op(op),
+        tac::TypeOp::Bit(op) => pretty_bit_op(op),
+    }
+}
+
+pub fn pretty_arith_op(op: &tac::ArithmeticOp) -> String {
+    match op {
+        tac::ArithmeticOp::Add => "+".to_string(),
+        tac::ArithmeticOp::Sub => "-".to_string(),
+        tac::ArithmeticOp::Mul => "*".to_string(),
+        tac::ArithmeticOp::Div => "/".to_string(),
+        tac::ArithmeticOp::Mod => "%".to_string(),
+    }
+}
+
+pub fn pretty_rel_op(op: &tac::RelationalOp) -> String {
+    match op {
+        tac::RelationalOp::Less => "<".to_string(),
+        tac::RelationalOp::LessOrEq => "<=".to_string(),
+        tac::RelationalOp::Greater => ">".to_string(),
+        tac::RelationalOp::GreaterOrEq => ">=".to_string(),
+    }
+}
+
+pub fn pretty_eq_op(op: &tac::EqualityOp) -> String {
+    match op {
+        tac::EqualityOp::Equal => "==".to_string(),
+        tac::EqualityOp::NotEq => "!=".to_string(),
+    }
+}
+
+pub fn pretty_bit_op(op: &tac::BitwiseOp) -> String {
+    match op {
+        tac::BitwiseOp::And => "&".to_string(),
+        tac::BitwiseOp::Or => "|".to_string(),
+        tac::BitwiseOp::Xor => "^".to_string(),
+        tac::BitwiseOp::LShift => "<<".to_string(),
+        tac::BitwiseOp::RShift => ">>".to_string(),
+    }
+}
+
+pub fn pretty_unary_op(op: &tac::UnOp) -> String {
+    match op {
+        tac::UnOp::Neg => "-".to_string(),
+        tac::UnOp::LogicNeg => "!".to_string(),
+        tac::UnOp::BitComplement => "~".to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{il::tac, lexer::Lexer, parser};
+
+    #[test]
+    fn pretty_function() {
+        let program = "int inc(int a) { return a + 1; }";
+        let tokens = Lexer::new().lex_str(program).unwrap();
+        let ast = parser::parse(tokens).unwrap();
+        let file = tac::il(&ast, &tac::Options::default()).unwrap();
+
+        assert_eq!(
+            super::pretty(&file.code[0]),
+            "_inc:\n  \
+               param a\n  \
+               BeginFunc 4\n  \
+               t1: a + 1\n  \
+               Return t1\n"
+        );
+    }
+
+    #[test]
+    fn pretty_control_flow() {
+        let program = "int main() { int i = 0; while (i < 10) i = i + 1; return i; }";
+        let tokens = Lexer::new().lex_str(program).unwrap();
+        let ast = parser::parse(tokens).unwrap();
+        let file = tac::il(&ast, &tac::Options::default()).unwrap();
+
+        assert_eq!(
+            super::pretty(&file.code[0]),
+            "main:\n  \
+               BeginFunc 12\n  \
+               i: 0\n  \
+               Goto _L0\n\
+             _L2:\n  \
+               t1: i + 1\n  \
+               i: t1\n\
+             _L0:\n  \
+               t2: i < 10\n  \
+               IfNZ t2 Goto _L2\n\
+             _L1:\n  \
+               Return i\n"
+        );
+    }
+}
@@ -1,23 +1,99 @@
 use super::constant_fold;
 use super::unused_code;
 use crate::ast;
+use crate::lexer::Pos;
 use std::collections::{HashMap, HashSet};
+use std::error;
+use std::fmt;
 
 pub struct File {
     pub code: Vec<FuncDef>,
     pub global_data: HashMap<ID, Option<Const>>,
+    /// prototypes keeps signatures of functions which are declared but not defined,
+    /// e.g. the ones from libc.
+    pub prototypes: HashMap<String, Signature>,
 }
 
-pub fn il(p: &ast::Program) -> File {
+/// Signature describes types of a function.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Signature {
+    pub return_type: ast::Type,
+    pub parameters: Vec<ast::Type>,
+}
+
+/// Options which affects the shape of generated code.
+#[derive(Default)]
+pub struct Options {
+    /// simple_loops emits loops with a condition on the top
+    /// and an unconditional jump back at the end of each iteration.
+    pub simple_loops: bool,
+    /// zero_init_locals stores 0 to every local variable
+    /// which is declared without an initializer.
+    pub zero_init_locals: bool,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum TacError {
+    /// DuplicateDeclaration is raised when a variable is declared twice in the same scope.
+    DuplicateDeclaration { name: String, pos: Pos },
+    /// Unsupported is raised on a construction which can't be lowered yet.
+    Unsupported(&'static str),
+}
+
+impl fmt::Display for TacError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            TacError::DuplicateDeclaration { name, pos } => write!(
+                f,
+                "redeclaration of variable {:?} at {}:{}",
+                name, pos.line, pos.column
+            ),
+            TacError::Unsupported(what) => write!(f, "{} is not supported", what),
+        }
+    }
+}
+
+impl error::Error for TacError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        None
+    }
+}
+
+pub fn il(p: &ast::Program, opts: &Options) -> Result<File, TacError> {
     let mut gen = Generator::new();
+    gen.simple_loops = opts.simple_loops;
+    gen.zero_init_locals = opts.zero_init_locals;
+    gen.functions = p
+        .0
+        .iter()
+        .filter_map(|top| match top {
+            ast::TopLevel::Function(func) => Some((
+                func.name.clone(),
+                Signature {
+                    return_type: func.return_type,
+                    parameters: func.parameters.iter().map(|p| p.ty).collect(),
+                },
+            )),
+            ast::TopLevel::Declaration(..) => None,
+        })
+        .collect();
     let mut funcs = Vec::new();
 
-    p.0.iter().filter_map(|top| match top {
-        ast::TopLevel::Declaration(decl) => match decl { ast::Declaration::Declare{name, ..} => Some((name, decl))},
-        _ => None,
-    }).collect::<HashMap<_, _>>()
-    .into_iter()
-    .for_each(|(_, decl)| gen.global_decl(decl));
+    let mut globals = HashMap::new();
+    for top in &p.0 {
+        match top {
+            ast::TopLevel::Declaration(ast::Declaration::FuncPointer { .. }) => {
+                return Err(TacError::Unsupported("a global function pointer"));
+            }
+            ast::TopLevel::Declaration(decl @ ast::Declaration::Declare { name, .. }) => {
+                globals.insert(name, decl);
+            }
+            ast::TopLevel::Function(..) => (),
+        }
+    }
+    globals
+        .into_iter()
+        .for_each(|(_, decl)| gen.global_decl(decl));
 
     for top in &p.0 {
         match top {
@@ -26,6 +102,9 @@ pub fn il(p: &ast::Program) -> File {
                 if let Some(func) = gen.parse(fun) {
                     funcs.push(func);
                 }
+                if let Some(err) = gen.error.take() {
+                    return Err(err);
+                }
                 gen.context.pop_scope();
                 gen = Generator::from(&gen);
             }
@@ -33,10 +112,17 @@ pub fn il(p: &ast::Program) -> File {
         }
     }
 
-    File {
+    let prototypes = gen
+        .functions
+        .into_iter()
+        .filter(|(name, _)| !funcs.iter().any(|f| &f.name == name))
+        .collect();
+
+    Ok(File {
         code: funcs,
         global_data: gen.context.globals,
-    }
+        prototypes,
+    })
 }
 
 struct Generator {
@@ -46,6 +132,13 @@ struct Generator {
     context: Context,
     label_counter: usize,
     allocated: usize,
+    simple_loops: bool,
+    zero_init_locals: bool,
+    // functions keeps signatures of every declared function.
+    functions: HashMap<String, Signature>,
+    // error is the first issue found in a function,
+    // the generation goes on but its result is discarded.
+    error: Option<TacError>,
 }
 
 // TODO: change the type make the files private and create method instead
@@ -60,9 +153,16 @@ pub struct Context {
     symbols: HashMap<String, Vec<ID>>, // todo: why we are using Vec<ID> here?
     list_symbols: HashMap<String, Vec<ID>>,
     globals: HashMap<ID, Option<Const>>,
+    pointers: HashSet<ID>,
+    // types keeps declared types of variables,
+    // function pointers and temporaries aren't there.
+    types: HashMap<ID, ast::Type>,
     symbols_counter: usize,
     scopes: Vec<HashSet<String>>,
     loop_ctx: Vec<LoopContext>,
+    // break_ctx is a stack of labels where `break` jumps to,
+    // it's pushed by loops and `switch`.
+    break_ctx: Vec<Label>,
     ret_ctx: Option<ReturnContext>,
 }
 
@@ -72,9 +172,12 @@ impl Context {
             symbols: HashMap::new(),
             list_symbols: HashMap::new(),
             globals: HashMap::new(),
+            pointers: HashSet::new(),
+            types: HashMap::new(),
             symbols_counter: 0,
             scopes: vec![HashSet::new()],
             loop_ctx: Vec::new(),
+            break_ctx: Vec::new(),
             ret_ctx: None,
         }
     }
@@ -100,15 +203,8 @@ impl Context {
     }
 
     fn add_symbol(&mut self, name: &str) -> ID {
-        if !self.add_symbol_to_scope(name) {
-            /*
-                TODO: Here should be raised a error since we have added the same variable to scope
-                what is error
-                it may be implemented as a feature, what means that we can pass here a config of polices to such type of behavior
-                It's not handled anywhere above in the chain of compilation process
-            */
-            unimplemented!()
-        }
+        // redeclarations are checked by Generator::redeclared beforehand
+        self.add_symbol_to_scope(name);
 
         let id = self.symbols_counter;
         self.symbols_counter += 1;
@@ -124,10 +220,11 @@ impl Context {
         id
     }
 
-    fn add_gl_symbol(&mut self, name: &str, value: Option<Const>) {
+    fn add_gl_symbol(&mut self, name: &str, ty: ast::Type, value: Option<Const>) {
         // todo: shadowing globals working?
         let id = self.add_symbol(name);
         self.globals.insert(id, value);
+        self.types.insert(id, ty);
     }
 
     // add_tmp method was developed in regard to have the same counter for id
@@ -146,9 +243,13 @@ impl Context {
         self.symbols.get(name).and_then(|ids| ids.last())
     }
 
-    fn add_symbol_to_scope(&mut self, name: &str) -> bool {
+    fn in_current_scope(&self, name: &str) -> bool {
+        self.scopes.last().unwrap().contains(name)
+    }
+
+    fn add_symbol_to_scope(&mut self, name: &str) {
         let last_scope = self.scopes.last_mut().unwrap();
-        last_scope.insert(name.to_owned())
+        last_scope.insert(name.to_owned());
     }
 
     pub fn is_variable(&self, id: ID) -> bool {
@@ -158,6 +259,16 @@ impl Context {
             .map_or(false, |_| true)
     }
 
+    /// var_type returns a declared type of the variable.
+    pub fn var_type(&self, id: ID) -> Option<ast::Type> {
+        self.types.get(&id).cloned()
+    }
+
+    /// is_pointer checks if the variable is a function pointer.
+    pub fn is_pointer(&self, id: ID) -> bool {
+        self.pointers.contains(&id)
+    }
+
     pub fn ident_by_id(&self, id: ID) -> Option<&str> {
         self.list_symbols
             .iter()
@@ -174,8 +285,8 @@ impl Context {
         do some stuff with context, and then it goes off the scope drop will be called
     */
 
-    fn loop_end(&self) -> Label {
-        self.loop_ctx.last().as_ref().unwrap().end
+    fn break_target(&self) -> Label {
+        *self.break_ctx.last().unwrap()
     }
 
     fn loop_start(&self) -> Label {
@@ -187,6 +298,7 @@ impl Context {
         self.scopes.clear();
         self.scopes.push(HashSet::new());
         self.loop_ctx.clear();
+        self.break_ctx.clear();
     }
 }
 
@@ -215,6 +327,10 @@ impl Generator {
             allocated: 0,
             instructions: Vec::new(),
             context: Context::new(),
+            simple_loops: false,
+            zero_init_locals: false,
+            functions: HashMap::new(),
+            error: None,
         }
     }
 
@@ -222,8 +338,14 @@ impl Generator {
         let mut generator = Generator::new();
         // check is it copy or clone in sense of references.
         generator.label_counter = g.label_counter;
+        generator.simple_loops = g.simple_loops;
+        generator.zero_init_locals = g.zero_init_locals;
+        generator.functions = g.functions.clone();
         generator.context.symbols_counter = g.context.symbols_counter;
         generator.context.globals = g.context.globals.clone();
+        for id in generator.context.globals.keys() {
+            generator.context.types.insert(*id, g.context.types[id]);
+        }
 
         // copy global vars
         for (id, val) in &generator.context.globals {
@@ -257,7 +379,11 @@ impl Generator {
                 Don't allocate memory for parameters since
                 this memory was prepared by caller
             */
-            let id = self.remember_var(&p);
+            let id = match self.redeclared(&p.name, &p.pos) {
+                Some(id) => id,
+                None => self.remember_var(&p.name),
+            };
+            self.context.types.insert(id, p.ty);
             params.push(id);
         }
 
@@ -265,7 +391,9 @@ impl Generator {
 
         let has_function_call = has_function_call(&func);
         let (count_returns, has_flat_return) = count_returns(&func);
-        if count_returns > 1 || !has_flat_return {
+        // a function without returns, e.g. `int f() {}`, gets an implicit `return 0`
+        // so it doesn't need a shared return slot.
+        if count_returns > 1 || (count_returns == 1 && !has_flat_return) {
             let ret_id = self
                 .emit(Instruction::Alloc(Value::Const(Const::Int(0))))
                 .unwrap();
@@ -293,6 +421,7 @@ impl Generator {
 
         Some(FuncDef {
             name: func.name.clone(),
+            return_type: func.return_type,
             frame_size: self.allocated_memory(),
             instructions: self.flush(),
             parameters: params,
@@ -305,6 +434,7 @@ impl Generator {
         let id = match &inst {
             Instruction::Op(..) => Some(self.alloc_tmp()),
             Instruction::Assignment(id, ..) => Some(id.clone()),
+            Instruction::FuncAddr(id, ..) => Some(id.clone()),
             Instruction::Alloc(..) => Some(self.alloc_tmp()),
             Instruction::Call(..) => {
                 // TODO: we should handle somehow
@@ -329,8 +459,8 @@ impl Generator {
 
     fn emit_expr(&mut self, exp: &ast::Exp) -> Value {
         match exp {
-            ast::Exp::Var(name) => Value::from(self.recognize_var(name)),
-            ast::Exp::Const(ast::Const::Int(val)) => {
+            ast::Exp::Var(name, _) => Value::from(self.recognize_var(name)),
+            ast::Exp::Const(ast::Const::Int(val), _) => {
                 // TODO: might it should be changed since we whant to handle expresions like this
                 // in this manner.
                 //
@@ -339,19 +469,58 @@ impl Generator {
                 // Without a temporary variable, but its deservers a major discussion
                 Value::from(Const::Int(*val as i32))
             }
-            ast::Exp::FuncCall(name, params) => {
+            ast::Exp::FuncCall(name, params, _) => {
                 // Notion: it might be useful if we don't work with IDs itself here,
                 // instead we could handle types which contains its size and id
                 let values = params.iter().map(|exp| self.emit_expr(exp)).collect();
 
-                let types_size = params.len() * 4;
+                // a local function pointer shadows a function with the same name
+                let pointer = self
+                    .context
+                    .get_symbol(name)
+                    .filter(|id| self.context.is_pointer(**id))
+                    .cloned();
+
+                // parameters of a function pointer are always `int`
+                let types_size = match self.functions.get(name) {
+                    Some(sig) if pointer.is_none() => {
+                        sig.parameters.iter().map(|ty| ty.size()).sum()
+                    }
+                    _ => params.len() * 4,
+                };
 
-                let id = self
-                    .emit(Instruction::Call(Call::new(&name, values, types_size)))
-                    .unwrap();
+                let mut call = Call::new(&name, values, types_size);
+                if let Some(id) = pointer {
+                    call.tp = FnType::ACall(id);
+                }
+
+                let id = self.emit(Instruction::Call(call)).unwrap();
                 Value::from(id)
             }
-            ast::Exp::UnOp(op, exp) => {
+            ast::Exp::Comma(exp1, exp2, _) => {
+                self.emit_expr(exp1);
+                self.emit_expr(exp2)
+            }
+            ast::Exp::AddressOf(..) => {
+                self.fail(TacError::Unsupported(
+                    "an address of a function out of a function pointer assignment",
+                ));
+                Value::from(Const::Int(0))
+            }
+            ast::Exp::UnOp(op, exp, _) => {
+                // `!(a < b)` is the same as `a >= b`
+                // so we don't need to compute the logical negation separately
+                if let (ast::UnOp::LogicalNegation, ast::Exp::BinOp(bin_op, exp1, exp2, _)) =
+                    (op, exp.as_ref())
+                {
+                    if let Some(op) = TypeOp::negated(bin_op) {
+                        let lhs = self.emit_expr(exp1);
+                        let rhs = self.emit_expr(exp2);
+                        let id = self.emit(Instruction::Op(Op::Op(op, lhs, rhs))).unwrap();
+                        return Value::from(id);
+                    }
+                }
+
                 let val = self.emit_expr(exp);
                 // TODO: looks like here the problem with additional tmp variable
                 let id = self
@@ -359,7 +528,7 @@ impl Generator {
                     .unwrap();
                 Value::from(id)
             }
-            ast::Exp::IncOrDec(name, op) => {
+            ast::Exp::IncOrDec(name, op, _) => {
                 let var_id = self.recognize_var(name);
                 let one = Value::Const(Const::Int(1));
 
@@ -398,7 +567,7 @@ impl Generator {
                     Value::from(changed_id)
                 }
             }
-            ast::Exp::BinOp(op, exp1, exp2) => {
+            ast::Exp::BinOp(op, exp1, exp2, _) => {
                 if let ast::BinOp::And = op {
                     let end_label = self.uniq_label();
                     let val1 = self.emit_expr(exp1);
@@ -458,15 +627,20 @@ impl Generator {
                     )
                 }
             }
-            ast::Exp::Assign(name, exp) => {
+            ast::Exp::Assign(name, exp, _) => {
                 let var_id = self.recognize_var(name);
+                if self.context.is_pointer(var_id) {
+                    self.emit_func_addr(var_id, exp);
+                    return Value::from(var_id);
+                }
+
                 let exp_id = self.emit_expr(exp);
                 Value::from(
                     self.emit(Instruction::Assignment(var_id, Value::from(exp_id)))
                         .unwrap(),
                 )
             }
-            ast::Exp::CondExp(cond, exp1, exp2) => {
+            ast::Exp::CondExp(cond, exp1, exp2, _) => {
                 /*
                     NOTION: if we will get a track with assign id an operator
                     it can be simplified by removing tmp_id
@@ -492,7 +666,7 @@ impl Generator {
 
                 Value::from(tmp_id)
             }
-            ast::Exp::AssignOp(name, op, exp) => {
+            ast::Exp::AssignOp(name, op, exp, _) => {
                 let id = self.recognize_var(name);
                 let op = assign_op_to_type_op(op);
                 let val = self.emit_expr(exp);
@@ -507,21 +681,61 @@ impl Generator {
 
     fn emit_decl(&mut self, decl: &ast::Declaration) {
         match decl {
-            ast::Declaration::Declare { name, exp } => {
+            ast::Declaration::Declare {
+                ty, name, exp, pos, ..
+            } => {
                 if let Some(exp) = exp {
                     let exp_id = self.emit_expr(exp);
-                    let var_id = self.alloc_var(name);
+                    let var_id = self.declare_var(name, pos);
+                    self.context.types.insert(var_id, *ty);
                     self.emit(Instruction::Assignment(var_id, exp_id));
                 } else {
                     // Allocate the value to be able to recognize it.
                     // Do that after processing expression since there may be
                     // a variable with the same name in the above scope
-                    let var_id = self.alloc_var(name);
+                    let var_id = self.declare_var(name, pos);
+                    self.context.types.insert(var_id, *ty);
+                    if self.zero_init_locals {
+                        self.emit(Instruction::Assignment(
+                            var_id,
+                            Value::Const(Const::Int(0)),
+                        ));
+                    }
+                }
+            }
+            ast::Declaration::FuncPointer { name, exp, pos, .. } => {
+                let var_id = self.declare_var(name, pos);
+                self.context.pointers.insert(var_id);
+                match exp {
+                    Some(exp) => self.emit_func_addr(var_id, exp),
+                    None => {
+                        self.instructions.push(InstructionLine(
+                            Instruction::Alloc(Value::Const(Const::Int(0))),
+                            Some(var_id),
+                        ));
+                    }
                 }
             }
         }
     }
 
+    fn emit_func_addr(&mut self, pointer: ID, exp: &ast::Exp) {
+        match exp {
+            ast::Exp::AddressOf(name, _) => {
+                self.emit(Instruction::FuncAddr(pointer, name.clone()));
+            }
+            // a function designator is converted to the function's address
+            ast::Exp::Var(name, ..)
+                if self.context.get_symbol(name).is_none() && self.functions.contains_key(name) =>
+            {
+                self.emit(Instruction::FuncAddr(pointer, name.clone()));
+            }
+            _ => self.fail(TacError::Unsupported(
+                "an assignment of anything but a function to a function pointer",
+            )),
+        }
+    }
+
     fn emit_block(&mut self, block: &ast::BlockItem) {
         match block {
             ast::BlockItem::Declaration(decl) => self.emit_decl(decl),
@@ -531,12 +745,12 @@ impl Generator {
 
     fn emit_statement(&mut self, st: &ast::Statement) {
         match st {
-            ast::Statement::Exp { exp: exp } => {
+            ast::Statement::Exp { exp: exp, .. } => {
                 if let Some(exp) = exp {
                     self.emit_expr(exp);
                 }
             }
-            ast::Statement::Return { exp } => {
+            ast::Statement::Return { exp, .. } => {
                 let val = self.emit_expr(exp);
                 if let Some(ret) = self.context.ret_ctx.as_ref() {
                     let save_id = ret.save_id.clone();
@@ -551,6 +765,7 @@ impl Generator {
                 cond_expr,
                 if_block,
                 else_block,
+                ..
             } => {
                 let cond_val = self.emit_expr(cond_expr);
                 let end_label = self.uniq_label();
@@ -573,42 +788,30 @@ impl Generator {
                     self.emit(Instruction::ControlOp(ControlOp::Label(end_label)));
                 }
             }
-            ast::Statement::Compound { list: list } => self.scoped(|g| {
+            ast::Statement::Compound { list: list, .. } => self.scoped(|g| {
                 if let Some(list) = list {
                     for block in list {
                         g.emit_block(block);
                     }
                 }
             }),
-            ast::Statement::While { exp, statement } => {
-                self.loop_scope(|g, ctx| {
-                    g.emit(Instruction::ControlOp(ControlOp::Label(ctx.begin)));
-                    let cond_val = g.emit_expr(exp);
-                    g.emit(Instruction::ControlOp(ControlOp::Branch(Branch::IfGOTO(
-                        cond_val, ctx.end,
-                    ))));
-
-                    g.scoped(|g| g.emit_statement(statement));
-
-                    g.emit(Instruction::ControlOp(ControlOp::Branch(Branch::GOTO(
-                        ctx.begin,
-                    ))));
-                    g.emit(Instruction::ControlOp(ControlOp::Label(ctx.end)));
-                });
+            ast::Statement::While { exp, statement, .. } => {
+                self.loop_scope(|g, ctx| g.emit_loop(ctx, exp, None, statement));
             }
-            ast::Statement::Do { exp, statement } => {
+            ast::Statement::Do { exp, statement, .. } => {
                 self.loop_scope(|g, ctx| {
-                    g.emit(Instruction::ControlOp(ControlOp::Label(ctx.begin)));
+                    // continue is directed to ctx.begin so it must check the condition
+                    // rather than start the body over.
+                    let body_label = g.uniq_label();
+                    g.emit(Instruction::ControlOp(ControlOp::Label(body_label)));
 
                     g.scoped(|g| g.emit_statement(statement));
 
+                    g.emit(Instruction::ControlOp(ControlOp::Label(ctx.begin)));
                     let cond_val = g.emit_expr(exp);
-                    g.emit(Instruction::ControlOp(ControlOp::Branch(Branch::IfGOTO(
-                        cond_val, ctx.end,
-                    ))));
-                    g.emit(Instruction::ControlOp(ControlOp::Branch(Branch::GOTO(
-                        ctx.begin,
-                    ))));
+                    g.emit(Instruction::ControlOp(ControlOp::Branch(
+                        Branch::IfNotZeroGOTO(cond_val, body_label),
+                    )));
                     g.emit(Instruction::ControlOp(ControlOp::Label(ctx.end)));
                 });
             }
@@ -617,35 +820,13 @@ impl Generator {
                 exp2,
                 exp3,
                 statement,
+                ..
             } => {
                 self.loop_scope(|g, ctx| {
-                    let begin_label = if exp3.is_some() {
-                        g.uniq_label()
-                    } else {
-                        ctx.begin
-                    };
-
                     g.scoped(|g| {
                         g.emit_decl(decl);
-
-                        g.emit(Instruction::ControlOp(ControlOp::Label(begin_label)));
-                        let cond_val = g.emit_expr(exp2);
-                        g.emit(Instruction::ControlOp(ControlOp::Branch(Branch::IfGOTO(
-                            cond_val, ctx.end,
-                        ))));
-
-                        g.scoped(|g| g.emit_statement(statement));
-
-                        if let Some(exp3) = exp3 {
-                            g.emit(Instruction::ControlOp(ControlOp::Label(ctx.begin)));
-                            g.emit_expr(exp3);
-                        }
+                        g.emit_loop(ctx, exp2, exp3.as_ref(), statement);
                     });
-
-                    g.emit(Instruction::ControlOp(ControlOp::Branch(Branch::GOTO(
-                        begin_label,
-                    ))));
-                    g.emit(Instruction::ControlOp(ControlOp::Label(ctx.end)));
                 });
             }
             ast::Statement::For {
@@ -653,40 +834,62 @@ impl Generator {
                 exp2,
                 exp3,
                 statement,
+                ..
             } => self.loop_scope(|g, ctx| {
-                let begin_label = if exp3.is_some() {
-                    g.uniq_label()
-                } else {
-                    ctx.begin
-                };
-
                 if let Some(exp) = exp1 {
                     g.emit_expr(exp);
                 }
-                g.emit(Instruction::ControlOp(ControlOp::Label(begin_label)));
-                let cond_val = g.emit_expr(exp2);
-                g.emit(Instruction::ControlOp(ControlOp::Branch(Branch::IfGOTO(
-                    cond_val, ctx.end,
-                ))));
-
-                g.scoped(|g| g.emit_statement(statement));
 
-                if let Some(exp3) = exp3 {
-                    g.emit(Instruction::ControlOp(ControlOp::Label(ctx.begin)));
-
-                    g.emit_expr(exp3);
+                g.emit_loop(ctx, exp2, exp3.as_ref(), statement);
+            }),
+            ast::Statement::Switch { exp, cases, .. } => {
+                let val = self.emit_expr(exp);
+                let end_label = self.uniq_label();
+                let labels = cases.iter().map(|_| self.uniq_label()).collect::<Vec<_>>();
+
+                for (case, label) in cases.iter().zip(&labels) {
+                    if let Some(value) = case.value {
+                        let cmp = self
+                            .emit(Instruction::Op(Op::Op(
+                                TypeOp::Equality(EqualityOp::Equal),
+                                val.clone(),
+                                Value::from(Const::Int(value as i32)),
+                            )))
+                            .unwrap();
+                        self.emit(Instruction::ControlOp(ControlOp::Branch(
+                            Branch::IfNotZeroGOTO(Value::from(cmp), *label),
+                        )));
+                    }
                 }
-                g.emit(Instruction::ControlOp(ControlOp::Branch(Branch::GOTO(
-                    begin_label,
+
+                let default_label = cases
+                    .iter()
+                    .position(|case| case.value.is_none())
+                    .map_or(end_label, |i| labels[i]);
+                self.emit(Instruction::ControlOp(ControlOp::Branch(Branch::GOTO(
+                    default_label,
                 ))));
-                g.emit(Instruction::ControlOp(ControlOp::Label(ctx.end)));
-            }),
-            ast::Statement::Break => {
+
+                // `continue` still refers to an enclosing loop
+                self.context.break_ctx.push(end_label);
+                self.scoped(|g| {
+                    for (case, label) in cases.iter().zip(labels) {
+                        g.emit(Instruction::ControlOp(ControlOp::Label(label)));
+                        for block in &case.body {
+                            g.emit_block(block);
+                        }
+                    }
+                });
+                self.context.break_ctx.pop();
+
+                self.emit(Instruction::ControlOp(ControlOp::Label(end_label)));
+            }
+            ast::Statement::Break { .. } => {
                 self.emit(Instruction::ControlOp(ControlOp::Branch(Branch::GOTO(
-                    self.context.loop_end(),
+                    self.context.break_target(),
                 ))));
             }
-            ast::Statement::Continue => {
+            ast::Statement::Continue { .. } => {
                 self.emit(Instruction::ControlOp(ControlOp::Branch(Branch::GOTO(
                     self.context.loop_start(),
                 ))));
@@ -696,12 +899,13 @@ impl Generator {
 
     fn global_decl(&mut self, decl: &ast::Declaration) {
         match decl {
-            ast::Declaration::Declare { name, exp } => match exp {
-                Some(ast::Exp::Const(ast::Const::Int(value))) => {
-                    self.alloc_gl_var(name, Some(Const::Int(*value as i32)));
+            ast::Declaration::FuncPointer { .. } => unreachable!(),
+            ast::Declaration::Declare { ty, name, exp, .. } => match exp {
+                Some(ast::Exp::Const(ast::Const::Int(value), _)) => {
+                    self.alloc_gl_var(name, *ty, Some(Const::Int(*value as i32)));
                 }
                 None => {
-                    self.alloc_gl_var(name, None);
+                    self.alloc_gl_var(name, *ty, None);
                 }
                 Some(..) => unimplemented!(), // todo: constant evaluation ast:Expr // todo: check if this is a constant expr, otherwise error
             },
@@ -714,10 +918,71 @@ impl Generator {
         self.context.pop_scope();
     }
 
+    /// emit_loop emits a loop which checks the condition before each iteration.
+    ///
+    /// By default the condition is placed after the body so an iteration
+    /// costs a single conditional jump. With simple_loops the condition stays
+    /// on the top and the body ends with an unconditional jump back.
+    fn emit_loop(
+        &mut self,
+        ctx: LoopContext,
+        cond: &ast::Exp,
+        step: Option<&ast::Exp>,
+        statement: &ast::Statement,
+    ) {
+        // continue is directed to ctx.begin,
+        // so if there's a step it must be labeled by ctx.begin.
+        let cond_label = if step.is_some() {
+            self.uniq_label()
+        } else {
+            ctx.begin
+        };
+
+        if self.simple_loops {
+            self.emit(Instruction::ControlOp(ControlOp::Label(cond_label)));
+            let cond_val = self.emit_expr(cond);
+            self.emit(Instruction::ControlOp(ControlOp::Branch(Branch::IfGOTO(
+                cond_val, ctx.end,
+            ))));
+
+            self.scoped(|g| g.emit_statement(statement));
+
+            if let Some(step) = step {
+                self.emit(Instruction::ControlOp(ControlOp::Label(ctx.begin)));
+                self.emit_expr(step);
+            }
+            self.emit(Instruction::ControlOp(ControlOp::Branch(Branch::GOTO(
+                cond_label,
+            ))));
+        } else {
+            let body_label = self.uniq_label();
+            self.emit(Instruction::ControlOp(ControlOp::Branch(Branch::GOTO(
+                cond_label,
+            ))));
+            self.emit(Instruction::ControlOp(ControlOp::Label(body_label)));
+
+            self.scoped(|g| g.emit_statement(statement));
+
+            if let Some(step) = step {
+                self.emit(Instruction::ControlOp(ControlOp::Label(ctx.begin)));
+                self.emit_expr(step);
+            }
+            self.emit(Instruction::ControlOp(ControlOp::Label(cond_label)));
+            let cond_val = self.emit_expr(cond);
+            self.emit(Instruction::ControlOp(ControlOp::Branch(
+                Branch::IfNotZeroGOTO(cond_val, body_label),
+            )));
+        }
+
+        self.emit(Instruction::ControlOp(ControlOp::Label(ctx.end)));
+    }
+
     fn loop_scope<S: FnOnce(&mut Self, LoopContext)>(&mut self, f: S) {
         let ctx = LoopContext::new(self.uniq_label(), self.uniq_label());
         self.context.loop_ctx.push(ctx.clone());
+        self.context.break_ctx.push(ctx.end);
         f(self, ctx);
+        self.context.break_ctx.pop();
         self.context.loop_ctx.pop();
     }
 
@@ -750,8 +1015,39 @@ impl Generator {
         self.remember_var(name)
     }
 
-    fn alloc_gl_var(&mut self, name: &str, value: Option<Const>) {
-        self.context.add_gl_symbol(name, value)
+    /// declare_var allocates a local variable,
+    /// a redeclaration in the same scope is recorded as an error and refers to the first one.
+    fn declare_var(&mut self, name: &str, pos: &Pos) -> ID {
+        match self.redeclared(name, pos) {
+            Some(id) => id,
+            None => self.alloc_var(name),
+        }
+    }
+
+    /// redeclared records an error if the name is already declared in the current scope
+    /// and returns the ID of the first declaration.
+    fn redeclared(&mut self, name: &str, pos: &Pos) -> Option<ID> {
+        if !self.context.in_current_scope(name) {
+            return None;
+        }
+
+        self.fail(TacError::DuplicateDeclaration {
+            name: name.to_owned(),
+            pos: pos.clone(),
+        });
+        self.context.get_symbol(name).cloned()
+    }
+
+    /// fail records the error unless an earlier one is already recorded,
+    /// the lowering goes on so the caller has to provide a placeholder.
+    fn fail(&mut self, err: TacError) {
+        if self.error.is_none() {
+            self.error = Some(err);
+        }
+    }
+
+    fn alloc_gl_var(&mut self, name: &str, ty: ast::Type, value: Option<Const>) {
+        self.context.add_gl_symbol(name, ty, value)
     }
 
     fn remember_var(&mut self, name: &str) -> ID {
@@ -783,6 +1079,8 @@ pub enum Instruction {
     Assignment(ID, Value),
     // Notion: Can alloc be responsible not only for tmp variables?
     Alloc(Value),
+    /// FuncAddr stores an address of the function to a function pointer.
+    FuncAddr(ID, String),
     Op(Op),
     Call(Call),
     ControlOp(ControlOp),
@@ -837,8 +1135,22 @@ impl TypeOp {
             ast::BinOp::LessThan => TypeOp::Relational(RelationalOp::Less),
             ast::BinOp::LessThanOrEqual => TypeOp::Relational(RelationalOp::LessOrEq),
 
-            ast::BinOp::And => unimplemented!(),
-            ast::BinOp::Or => unimplemented!(),
+            ast::BinOp::And | ast::BinOp::Or => {
+                unreachable!("logical operators are lowered to branches")
+            }
+        }
+    }
+
+    /// negated returns an operation which is an opposite to the relational one.
+    fn negated(op: &ast::BinOp) -> Option<Self> {
+        match op {
+            ast::BinOp::Equal => Some(TypeOp::Equality(EqualityOp::NotEq)),
+            ast::BinOp::NotEqual => Some(TypeOp::Equality(EqualityOp::Equal)),
+            ast::BinOp::GreaterThan => Some(TypeOp::Relational(RelationalOp::LessOrEq)),
+            ast::BinOp::GreaterThanOrEqual => Some(TypeOp::Relational(RelationalOp::Less)),
+            ast::BinOp::LessThan => Some(TypeOp::Relational(RelationalOp::GreaterOrEq)),
+            ast::BinOp::LessThanOrEqual => Some(TypeOp::Relational(RelationalOp::Greater)),
+            _ => None,
         }
     }
 }
@@ -857,7 +1169,7 @@ pub enum Const {
     Int(i32),
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub enum Value {
     ID(ID),
     Const(Const),
@@ -957,8 +1269,8 @@ pub enum EqualityOp {
 #[derive(Debug)]
 pub enum Branch {
     GOTO(Label),
-    // might here can be Val?
     IfGOTO(Value, Label),
+    IfNotZeroGOTO(Value, Label),
 }
 
 #[derive(Debug)]
@@ -983,10 +1295,13 @@ impl Call {
 #[derive(Debug)]
 pub enum FnType {
     LCall,
+    /// ACall is a call by an address which is stored in a function pointer.
+    ACall(ID),
 }
 
 pub struct FuncDef {
     pub name: String,
+    pub return_type: ast::Type,
     pub parameters: Vec<usize>,
     pub frame_size: BytesSize,
     pub instructions: Vec<InstructionLine>,
@@ -1056,3 +1371,400 @@ impl<'a> ast::Visitor<'a> for CallCounter {
         ast::visitor::visit_expr(self, exp);
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::il::il_main;
+    use crate::{lexer::Lexer, parser};
+
+    fn il_loop(opts: &Options) -> Vec<InstructionLine> {
+        let program = "int main() { int sum = 0; for (int i = 0; i < 10; i++) sum += i; return sum; }";
+        let tokens = Lexer::new().lex_str(program).unwrap();
+        let ast = parser::parse(tokens).unwrap();
+        il(&ast, opts).unwrap().code.remove(0).instructions
+    }
+
+    fn label_position(instructions: &[InstructionLine], label: Label) -> usize {
+        instructions
+            .iter()
+            .position(|i| matches!(i.0, Instruction::ControlOp(ControlOp::Label(l)) if l == label))
+            .unwrap()
+    }
+
+    // branches_per_iteration counts jumps between the loop's back edge and its target.
+    fn branches_per_iteration(instructions: &[InstructionLine]) -> usize {
+        let position = |label: Label| label_position(instructions, label);
+
+        let (back_edge, target) = instructions
+            .iter()
+            .enumerate()
+            .find_map(|(index, i)| match i.0 {
+                Instruction::ControlOp(ControlOp::Branch(Branch::GOTO(l)))
+                | Instruction::ControlOp(ControlOp::Branch(Branch::IfNotZeroGOTO(_, l)))
+                    if position(l) < index =>
+                {
+                    Some((index, position(l)))
+                }
+                _ => None,
+            })
+            .unwrap();
+
+        instructions[target..=back_edge]
+            .iter()
+            .filter(|i| matches!(i.0, Instruction::ControlOp(ControlOp::Branch(..))))
+            .count()
+    }
+
+    #[test]
+    fn loop_shape() {
+        let simple = il_loop(&Options {
+            simple_loops: true,
+            ..Options::default()
+        });
+        let rotated = il_loop(&Options::default());
+
+        assert_eq!(branches_per_iteration(&simple), 2);
+        assert_eq!(branches_per_iteration(&rotated), 1);
+        // a rotated loop costs only an additional label
+        assert_eq!(rotated.len(), simple.len() + 1);
+    }
+
+    #[test]
+    fn parenthesized_assignment() {
+        let instructions = il_main("int main() { int a; return (a = 5) + 1; }").instructions;
+
+        let a = instructions
+            .iter()
+            .find_map(|i| match i.0 {
+                Instruction::Assignment(id, Value::Const(Const::Int(5))) => Some(id),
+                _ => None,
+            })
+            .unwrap();
+        // the sum uses the variable as the value of the assignment
+        let sum = instructions
+            .iter()
+            .find_map(|i| match &i.0 {
+                Instruction::Op(Op::Op(
+                    TypeOp::Arithmetic(ArithmeticOp::Add),
+                    Value::ID(id),
+                    Value::Const(Const::Int(1)),
+                )) if *id == a => i.1,
+                _ => None,
+            })
+            .unwrap();
+        assert!(instructions.iter().any(|i| matches!(
+            i.0,
+            Instruction::ControlOp(ControlOp::Return(Value::ID(id))) if id == sum
+        )));
+    }
+
+    #[test]
+    fn logical_and() {
+        let instructions =
+            il_main("int f(); int main() { int a = 0; return a && f(); }").instructions;
+
+        let branches = instructions
+            .iter()
+            .enumerate()
+            .filter_map(|(index, i)| match i.0 {
+                Instruction::ControlOp(ControlOp::Branch(Branch::IfGOTO(_, l))) => Some((index, l)),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        // both operands jump to the end if they're 0
+        assert_eq!(branches.len(), 2);
+        assert_eq!(branches[0].1, branches[1].1);
+
+        let end = label_position(&instructions, branches[0].1);
+        let call = instructions
+            .iter()
+            .position(|i| matches!(i.0, Instruction::Call(..)))
+            .unwrap();
+        // the right operand is jumped over
+        assert!(branches[0].0 < call && call < branches[1].0);
+        assert!(matches!(
+            instructions[end - 1].0,
+            Instruction::Assignment(_, Value::Const(Const::Int(1)))
+        ));
+    }
+
+    #[test]
+    fn logical_or() {
+        let instructions =
+            il_main("int f(); int main() { int a = 1; return a || f(); }").instructions;
+
+        let first = instructions
+            .iter()
+            .position(|i| matches!(i.0, Instruction::ControlOp(ControlOp::Branch(..))))
+            .unwrap();
+        let call = instructions
+            .iter()
+            .position(|i| matches!(i.0, Instruction::Call(..)))
+            .unwrap();
+        // a non zero left operand goes to the end right away
+        let end = match instructions[first + 1].0 {
+            Instruction::ControlOp(ControlOp::Branch(Branch::GOTO(l))) => {
+                label_position(&instructions, l)
+            }
+            _ => panic!("expected a jump to the end"),
+        };
+        assert!(first < call && call < end);
+        assert!(matches!(
+            instructions[end - 1].0,
+            Instruction::Assignment(_, Value::Const(Const::Int(0)))
+        ));
+    }
+
+    #[test]
+    fn compound_assignment() {
+        let instructions =
+            il_main("int main() { int a = 1; int b = 2; a += b; a <<= 2; return a; }").instructions;
+        let var = |value| {
+            instructions
+                .iter()
+                .find_map(|i| match i.0 {
+                    Instruction::Assignment(id, Value::Const(Const::Int(v))) if v == value => {
+                        Some(id)
+                    }
+                    _ => None,
+                })
+                .unwrap()
+        };
+        let (a, b) = (var(1), var(2));
+
+        // every operation is followed by an assignment of its result back to the variable
+        let ops = instructions
+            .iter()
+            .enumerate()
+            .filter_map(|(index, i)| match &i.0 {
+                Instruction::Op(Op::Op(op, Value::ID(id), value)) if *id == a => {
+                    match instructions[index + 1].0 {
+                        Instruction::Assignment(var, Value::ID(res)) if var == a && Some(res) == i.1 => {
+                            Some((op, value))
+                        }
+                        _ => None,
+                    }
+                }
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(ops.len(), 2);
+        assert!(matches!(
+            ops[0],
+            (TypeOp::Arithmetic(ArithmeticOp::Add), Value::ID(id)) if *id == b
+        ));
+        assert!(matches!(
+            ops[1],
+            (TypeOp::Bit(BitwiseOp::LShift), Value::Const(Const::Int(2)))
+        ));
+    }
+
+    #[test]
+    fn duplicate_declaration() {
+        let program = "int main() { int a = 1; int a = 2; return a; }";
+        let tokens = Lexer::new().lex_str(program).unwrap();
+        let ast = parser::parse(tokens).unwrap();
+
+        assert!(matches!(
+            il(&ast, &Options::default()),
+            Err(TacError::DuplicateDeclaration { ref name, ref pos })
+                if name == "a" && pos.line == 1 && pos.column == 29
+        ));
+
+        let tokens = Lexer::new()
+            .lex_str("int f(int a, int a) { return a; }")
+            .unwrap();
+        assert!(matches!(
+            il(&parser::parse(tokens).unwrap(), &Options::default()),
+            Err(TacError::DuplicateDeclaration { ref name, ref pos })
+                if name == "a" && pos.column == 18
+        ));
+
+        // a declaration in a nested scope shadows the outer one
+        il_main("int main() { int a = 1; { int a = 2; } return a; }");
+    }
+
+    #[test]
+    fn function_pointer_errors() {
+        let il_err = |program: &str| {
+            let tokens = Lexer::new().lex_str(program).unwrap();
+            match il(&parser::parse(tokens).unwrap(), &Options::default()) {
+                Err(err) => err,
+                Ok(..) => panic!("{:?} is lowered", program),
+            }
+        };
+
+        assert_eq!(
+            il_err("int (*gp)(int); int main() { return 0; }"),
+            TacError::Unsupported("a global function pointer")
+        );
+        assert!(matches!(
+            il_err("int f(); int main() { return &f; }"),
+            TacError::Unsupported(..)
+        ));
+        assert!(matches!(
+            il_err("int main() { int a = 1; int (*fp)() = a; return 0; }"),
+            TacError::Unsupported(..)
+        ));
+
+        // a function designator is the function's address
+        let instructions =
+            il_main("int t(int a); int main() { int (*fp)(int) = t; return fp(1); }").instructions;
+        assert!(instructions
+            .iter()
+            .any(|i| matches!(&i.0, Instruction::FuncAddr(_, name) if name == "t")));
+    }
+
+    #[test]
+    fn outer_scope_lookup() {
+        let instructions = il_main("int main() { int a = 1; { return a; } }").instructions;
+        let a = instructions
+            .iter()
+            .find_map(|i| match i.0 {
+                Instruction::Assignment(id, Value::Const(Const::Int(1))) => Some(id),
+                _ => None,
+            })
+            .unwrap();
+
+        // the returned value is saved to the return slot and comes from `a`
+        assert!(instructions.iter().any(|i| matches!(
+            i.0,
+            Instruction::Assignment(_, Value::ID(id)) if id == a
+        )));
+    }
+
+    #[test]
+    fn for_decl_scope() {
+        let instructions =
+            il_main("int main() { int x = 0; for (int i = 0; i < 3; i = i + 1) x = i; return x; }")
+                .instructions;
+        let vars = instructions
+            .iter()
+            .filter_map(|i| match i.0 {
+                Instruction::Assignment(id, Value::Const(Const::Int(0))) => Some(id),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        let (x, i) = (vars[0], vars[1]);
+
+        let uses_i = |op: &Op| matches!(op, Op::Op(_, Value::ID(id), _) if *id == i);
+        // condition
+        assert!(instructions.iter().any(|line| matches!(
+            &line.0,
+            Instruction::Op(op @ Op::Op(TypeOp::Relational(RelationalOp::Less), ..)) if uses_i(op)
+        )));
+        // body
+        assert!(instructions.iter().any(|line| matches!(
+            line.0,
+            Instruction::Assignment(var, Value::ID(id)) if var == x && id == i
+        )));
+        // post-expression
+        assert!(instructions.iter().any(|line| matches!(
+            &line.0,
+            Instruction::Op(op @ Op::Op(TypeOp::Arithmetic(ArithmeticOp::Add), ..)) if uses_i(op)
+        )));
+    }
+
+    #[test]
+    fn assign_constant() {
+        let instructions = il_main("int main() { int a = 5; return a; }").instructions;
+
+        assert_eq!(instructions.len(), 2);
+        assert!(matches!(
+            instructions[0],
+            InstructionLine(Instruction::Assignment(a, Value::Const(Const::Int(5))), Some(id)) if a == id
+        ));
+        assert!(!instructions
+            .iter()
+            .any(|i| matches!(i.0, Instruction::Alloc(..))));
+    }
+
+    #[test]
+    fn constant_condition() {
+        let instructions = il_main("int main() { if (1) return 2; return 3; }").instructions;
+
+        // the condition isn't put in a temporary
+        assert!(instructions.iter().any(|i| matches!(
+            i.0,
+            Instruction::ControlOp(ControlOp::Branch(Branch::IfGOTO(Value::Const(Const::Int(1)), _)))
+        )));
+        assert!(!instructions.iter().any(|i| matches!(
+            i.0,
+            Instruction::Alloc(Value::Const(Const::Int(1)))
+        )));
+    }
+
+    #[test]
+    fn variable_types() {
+        let func = il_main("long g; int main(char c) { long a = 1; int b = 2; return b; }");
+        let var = |name| {
+            func.ctx
+                .list_symbols
+                .get(name)
+                .and_then(|ids| ids.first())
+                .and_then(|id| func.ctx.var_type(*id))
+                .unwrap()
+        };
+
+        assert_eq!(var("g"), ast::Type::Long);
+        assert_eq!(var("c"), ast::Type::Char);
+        assert_eq!(var("a").size(), 8);
+        assert_eq!(var("b").size(), 4);
+    }
+
+    #[test]
+    fn call_parameters_size() {
+        let instructions = il_main(
+            "long add(long a, long b); int main() { int (*fp)(int, int) = &main; add(1, 2); return fp(1, 2); }",
+        ).instructions;
+        let sizes = instructions
+            .iter()
+            .filter_map(|i| match &i.0 {
+                Instruction::Call(call) => Some(call.pop_size),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+
+        assert_eq!(sizes, vec![16, 8]);
+    }
+
+    #[test]
+    fn prototypes() {
+        let tokens = Lexer::new()
+            .lex_str("int putchar(int c); long f(); int main() { return putchar(72); } long f() { return 1; }")
+            .unwrap();
+        let ast = parser::parse(tokens).unwrap();
+        let file = il(&ast, &Options::default()).unwrap();
+
+        assert_eq!(file.prototypes.len(), 1);
+        assert_eq!(
+            file.prototypes["putchar"],
+            Signature {
+                return_type: ast::Type::Int,
+                parameters: vec![ast::Type::Int],
+            }
+        );
+    }
+
+    #[test]
+    fn implicit_return() {
+        let instructions = il_main("int main() { int a = 1; }").instructions;
+        assert!(matches!(
+            instructions.last().unwrap().0,
+            Instruction::ControlOp(ControlOp::Return(Value::Const(Const::Int(0))))
+        ));
+
+        // the return slot is initialized by 0 for paths which don't return
+        let instructions = il_main("int f(int a) { if (a) return 1; }").instructions;
+        assert!(matches!(
+            instructions[0].0,
+            Instruction::Alloc(Value::Const(Const::Int(0)))
+        ));
+        assert!(matches!(
+            instructions.last().unwrap(),
+            InstructionLine(Instruction::ControlOp(ControlOp::Return(Value::ID(id))), None)
+                if Some(*id) == instructions[0].1
+        ));
+    }
+}
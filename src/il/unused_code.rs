@@ -16,6 +16,7 @@ pub fn remove_unused(mut func: tac::FuncDef) -> tac::FuncDef {
                 && !matches!(i, tac::Instruction::Call(..))
             {
                 func.instructions.remove(index);
+                continue;
             }
         }
 
@@ -28,7 +29,7 @@ pub fn remove_unused(mut func: tac::FuncDef) -> tac::FuncDef {
     func
 }
 
-fn used(i: &Instruction) -> Vec<ID> {
+pub(super) fn used(i: &Instruction) -> Vec<ID> {
     let mut ids = Vec::new();
     match i {
         Instruction::Assignment(.., v) => {
@@ -41,26 +42,62 @@ fn used(i: &Instruction) -> Vec<ID> {
         Instruction::Op(Op::Unary(_, v)) => {
             v.as_id().map(|id| ids.push(*id));
         }
+        Instruction::Alloc(Value::ID(id)) => ids.push(*id),
         Instruction::ControlOp(tac::ControlOp::Return(Value::ID(id))) => ids.push(*id),
-        Instruction::ControlOp(tac::ControlOp::Branch(tac::Branch::IfGOTO(Value::ID(id), ..))) => {
-            ids.push(*id)
+        Instruction::ControlOp(tac::ControlOp::Branch(tac::Branch::IfGOTO(Value::ID(id), ..)))
+        | Instruction::ControlOp(tac::ControlOp::Branch(tac::Branch::IfNotZeroGOTO(
+            Value::ID(id),
+            ..,
+        ))) => ids.push(*id),
+        Instruction::Call(tac::Call { params, tp, .. }) => {
+            params
+                .iter()
+                .filter_map(|v| match v {
+                    Value::ID(id) => Some(*id),
+                    _ => None,
+                })
+                .for_each(|id| ids.push(id));
+
+            if let tac::FnType::ACall(id) = tp {
+                ids.push(*id);
+            }
         }
-        Instruction::Call(tac::Call { params, .. }) => params
-            .iter()
-            .filter_map(|v| match v {
-                Value::ID(id) => Some(*id),
-                _ => None,
-            })
-            .for_each(|id| ids.push(id)),
-        Instruction::Alloc(..)
+        Instruction::Alloc(Value::Const(..))
+        | Instruction::FuncAddr(..)
         | Instruction::ControlOp(tac::ControlOp::Label(..))
         | Instruction::ControlOp(tac::ControlOp::Return(Value::Const(..)))
         | Instruction::ControlOp(tac::ControlOp::Branch(tac::Branch::IfGOTO(
             Value::Const(..),
             ..,
         )))
+        | Instruction::ControlOp(tac::ControlOp::Branch(tac::Branch::IfNotZeroGOTO(
+            Value::Const(..),
+            ..,
+        )))
         | Instruction::ControlOp(tac::ControlOp::Branch(tac::Branch::GOTO(..))) => (),
     }
 
     ids
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::il::il_main;
+
+    #[test]
+    fn remove_discarded_expression() {
+        let func = remove_unused(il_main("int main() { 1 + 2; return 0; }"));
+        assert!(!func
+            .instructions
+            .iter()
+            .any(|i| matches!(i.0, Instruction::Op(..))));
+    }
+
+    #[test]
+    fn keep_calls() {
+        let func = remove_unused(il_main("int f(); int main() { f() + 1; return 0; }"));
+        assert!(matches!(func.instructions[0].0, Instruction::Call(..)));
+        assert_eq!(func.instructions.len(), 2);
+    }
+}
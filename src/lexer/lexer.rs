@@ -1,4 +1,6 @@
 use regex::Regex;
+use std::error;
+use std::fmt;
 use std::io::Read;
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -10,8 +12,12 @@ pub enum TokenType {
     Semicolon,
     Return,
     Int,
+    Char,
+    Long,
+    Void,
     Identifier,
     IntegerLiteral,
+    CharLiteral,
     Negation,
     BitwiseComplement,
     LogicalNegation,
@@ -54,14 +60,89 @@ pub enum TokenType {
     While,
     Break,
     Continue,
+    Switch,
+    Case,
+    Default,
     Comma,
 }
 
+impl fmt::Display for TokenType {
+    /// fmt writes a lexeme of the token or a description
+    /// if there's no single lexeme e.g. for identifiers.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            TokenType::OpenBrace => "{",
+            TokenType::CloseBrace => "}",
+            TokenType::OpenParenthesis => "(",
+            TokenType::CloseParenthesis => ")",
+            TokenType::Semicolon => ";",
+            TokenType::Return => "return",
+            TokenType::Int => "int",
+            TokenType::Char => "char",
+            TokenType::Long => "long",
+            TokenType::Void => "void",
+            TokenType::Identifier => "identifier",
+            TokenType::IntegerLiteral => "integer literal",
+            TokenType::CharLiteral => "character literal",
+            TokenType::Negation => "-",
+            TokenType::BitwiseComplement => "~",
+            TokenType::LogicalNegation => "!",
+            TokenType::BitwiseXor => "^",
+            TokenType::BitwiseOr => "|",
+            TokenType::BitwiseAnd => "&",
+            TokenType::Addition => "+",
+            TokenType::Multiplication => "*",
+            TokenType::Division => "/",
+            TokenType::Modulo => "%",
+            TokenType::And => "&&",
+            TokenType::Or => "||",
+            TokenType::Equal => "==",
+            TokenType::NotEqual => "!=",
+            TokenType::LessThan => "<",
+            TokenType::LessThanOrEqual => "<=",
+            TokenType::GreaterThan => ">",
+            TokenType::GreaterThanOrEqual => ">=",
+            TokenType::BitwiseLeftShift => "<<",
+            TokenType::BitwiseRightShift => ">>",
+            TokenType::Assignment => "=",
+            TokenType::Increment => "++",
+            TokenType::Decrement => "--",
+            TokenType::AssignmentPlus => "+=",
+            TokenType::AssignmentSub => "-=",
+            TokenType::AssignmentMul => "*=",
+            TokenType::AssignmentDiv => "/=",
+            TokenType::AssignmentMod => "%=",
+            TokenType::AssignmentBitLeftShift => "<<=",
+            TokenType::AssignmentBitRightShift => ">>=",
+            TokenType::AssignmentBitAnd => "&=",
+            TokenType::AssignmentBitOr => "|=",
+            TokenType::AssignmentBitXor => "^=",
+            TokenType::If => "if",
+            TokenType::Else => "else",
+            TokenType::Colon => ":",
+            TokenType::QuestionSign => "?",
+            TokenType::For => "for",
+            TokenType::Do => "do",
+            TokenType::While => "while",
+            TokenType::Break => "break",
+            TokenType::Continue => "continue",
+            TokenType::Switch => "switch",
+            TokenType::Case => "case",
+            TokenType::Default => "default",
+            TokenType::Comma => ",",
+        };
+
+        write!(f, "{}", name)
+    }
+}
+
 #[derive(Debug, PartialEq, Eq)]
 pub struct Token {
     pub token_type: TokenType,
     pub pos: Pos,
     pub val: Option<String>,
+    /// lexeme is the source text the token was matched from.
+    pub lexeme: String,
 }
 
 impl Token {
@@ -70,10 +151,142 @@ impl Token {
     }
 }
 
-#[derive(Debug, PartialEq, Eq)]
+/// Pos is a location of a token in a source file.
+///
+/// start and end are byte offsets whereas line and column
+/// starts from 1 and are used in diagnostics.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct Pos {
-    start: usize,
-    end: usize,
+    pub start: usize,
+    pub end: usize,
+    pub line: usize,
+    pub column: usize,
+}
+
+/// LexError is raised when there's a character which is not a part of any token.
+#[derive(Debug, PartialEq, Eq)]
+pub struct LexError {
+    pub c: char,
+    pub pos: Pos,
+}
+
+impl fmt::Display for LexError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "unexpected character {:?} at {}:{}",
+            self.c, self.pos.line, self.pos.column
+        )
+    }
+}
+
+impl error::Error for LexError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        None
+    }
+}
+
+/// decode_escapes replaces escape sequences such as `\n` or `\'` by characters they represent.
+///
+/// An unknown escape is reported by LexError which position is relative to the text.
+pub fn decode_escapes(text: &str) -> Result<String, LexError> {
+    let mut decoded = String::with_capacity(text.len());
+    let mut line = 1;
+    let mut column = 1;
+    let mut chars = text.char_indices();
+    while let Some((start, c)) = chars.next() {
+        if c != '\\' {
+            decoded.push(c);
+            Lexer::move_cursor(&text[start..start + c.len_utf8()], &mut line, &mut column);
+            continue;
+        }
+
+        let escape = chars.next().map(|(_, c)| c);
+        let c = match escape {
+            Some('n') => '\n',
+            Some('t') => '\t',
+            Some('r') => '\r',
+            Some('0') => '\0',
+            Some(c @ '\\') | Some(c @ '\'') | Some(c @ '"') => c,
+            _ => {
+                let c = escape.unwrap_or('\\');
+                let end = start + 1 + escape.map_or(0, |c| c.len_utf8());
+                return Err(LexError {
+                    c,
+                    pos: Pos {
+                        start,
+                        end,
+                        line,
+                        column,
+                    },
+                });
+            }
+        };
+
+        decoded.push(c);
+        column += 2;
+    }
+
+    Ok(decoded)
+}
+
+/// Tokens is a lazy stream of tokens created by Lexer::tokens.
+pub struct Tokens<'a> {
+    lexer: &'a Lexer,
+    file: &'a str,
+    remain_text: &'a str,
+    line: usize,
+    column: usize,
+}
+
+impl<'a> Iterator for Tokens<'a> {
+    type Item = Result<Token, LexError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while !self.remain_text.is_empty() {
+            // offset is derived from what's left to lex
+            // so positions are always absolute in the original file.
+            let offset = self.file.len() - self.remain_text.len();
+            match self.lexer.find_match(self.remain_text) {
+                Some(m) => {
+                    self.remain_text = m.remainingText;
+                    let value = m.value;
+
+                    let mut token = Lexer::create_token_from_match(m);
+                    token.pos.start = offset;
+                    token.pos.end = offset + value.len();
+                    token.pos.line = self.line;
+                    token.pos.column = self.column;
+                    Lexer::move_cursor(value, &mut self.line, &mut self.column);
+
+                    return Some(Ok(token));
+                }
+                None => {
+                    let c = self.remain_text.chars().next().unwrap();
+                    if !c.is_whitespace() {
+                        let err = LexError {
+                            c,
+                            pos: Pos {
+                                start: offset,
+                                end: offset + c.len_utf8(),
+                                line: self.line,
+                                column: self.column,
+                            },
+                        };
+                        self.remain_text = "";
+                        return Some(Err(err));
+                    }
+
+                    let (space, rest) = self.remain_text.split_at(c.len_utf8());
+                    Lexer::move_cursor(space, &mut self.line, &mut self.column);
+                    self.remain_text = rest;
+                }
+            }
+        }
+
+        None
+    }
 }
 
 struct TokenDefinition {
@@ -97,6 +310,8 @@ impl TokenDefinition {
                 pos: Pos {
                     start: m.start(),
                     end: m.end(),
+                    line: 0,
+                    column: 0,
                 },
                 remainingText: &text[m.end()..],
             }),
@@ -120,7 +335,10 @@ impl Lexer {
     pub fn new() -> Self {
         Lexer {
             definition: vec![
-                TokenDefinition::new(TokenType::Int, r"^int"),
+                TokenDefinition::new(TokenType::Int, r"^\bint\b"),
+                TokenDefinition::new(TokenType::Char, r"^\bchar\b"),
+                TokenDefinition::new(TokenType::Long, r"^\blong\b"),
+                TokenDefinition::new(TokenType::Void, r"^\bvoid\b"),
                 TokenDefinition::new(TokenType::Return, r"^\breturn\b"),
                 TokenDefinition::new(TokenType::If, r"^\bif\b"),
                 TokenDefinition::new(TokenType::Else, r"^\belse\b"),
@@ -129,8 +347,17 @@ impl Lexer {
                 TokenDefinition::new(TokenType::While, r"^\bwhile\b"),
                 TokenDefinition::new(TokenType::Continue, r"^\bcontinue\b"),
                 TokenDefinition::new(TokenType::Break, r"^\bbreak\b"),
+                TokenDefinition::new(TokenType::Switch, r"^\bswitch\b"),
+                TokenDefinition::new(TokenType::Case, r"^\bcase\b"),
+                TokenDefinition::new(TokenType::Default, r"^\bdefault\b"),
                 TokenDefinition::new(TokenType::Identifier, r"^[a-zA-Z]\w*"),
-                TokenDefinition::new(TokenType::IntegerLiteral, r"^\d+"),
+                TokenDefinition::new(
+                    TokenType::IntegerLiteral,
+                    // underscores are allowed only between digits e.g. `1_000`
+                    // and it may end with `u`, `l` or `ll` suffixes e.g. `1L`
+                    r"^(0[xX][0-9a-fA-F]+(_[0-9a-fA-F]+)*|0[bB][01]+(_[01]+)*|0[oO][0-7]+(_[0-7]+)*|\d+(_\d+)*)([lL]{1,2}[uU]?|[uU][lL]{0,2})?",
+                ),
+                TokenDefinition::new(TokenType::CharLiteral, r"^'(\\.|[^'\\])'"),
                 TokenDefinition::new(TokenType::OpenParenthesis, r"^\("),
                 TokenDefinition::new(TokenType::CloseParenthesis, r"^\)"),
                 TokenDefinition::new(TokenType::OpenBrace, r"^\{"),
@@ -176,33 +403,40 @@ impl Lexer {
         }
     }
 
-    pub fn lex<R: Read>(&self, mut reader: R) -> Vec<Token> {
+    pub fn lex<R: Read>(&self, mut reader: R) -> Result<Vec<Token>, LexError> {
         let mut file = String::new();
         reader.read_to_string(&mut file).unwrap();
 
-        let mut lexemes = Vec::new();
-        let mut remain_text = file.as_str();
-        let mut offset = 0;
-        while !remain_text.is_empty() {
-            match self.find_match(&remain_text) {
-                Some(m) => {
-                    remain_text = m.remainingText;
+        self.lex_str(&file)
+    }
 
-                    let mut token = Lexer::create_token_from_match(m);
-                    token.pos.start += offset;
-                    token.pos.end += offset;
-                    offset = token.pos.end;
+    /// lex_str is the same as lex but works with a text which is already in memory.
+    pub fn lex_str(&self, file: &str) -> Result<Vec<Token>, LexError> {
+        self.tokens(file).collect()
+    }
 
-                    lexemes.push(token);
-                }
-                None => {
-                    remain_text = &remain_text[1..];
-                    offset += 1;
-                }
-            }
+    /// tokens returns an iterator which lexes the text lazily, token by token.
+    ///
+    /// The iterator stops after the first error.
+    pub fn tokens<'a>(&'a self, file: &'a str) -> Tokens<'a> {
+        Tokens {
+            lexer: self,
+            file,
+            remain_text: file,
+            line: 1,
+            column: 1,
         }
+    }
 
-        lexemes
+    fn move_cursor(text: &str, line: &mut usize, column: &mut usize) {
+        for c in text.chars() {
+            if c == '\n' {
+                *line += 1;
+                *column = 1;
+            } else {
+                *column += 1;
+            }
+        }
     }
 
     fn find_match<'a>(&self, text: &'a str) -> Option<TokenMatch<'a>> {
@@ -220,9 +454,10 @@ impl Lexer {
             pos: m.pos,
             token_type: m.token,
             val: None,
+            lexeme: m.value.to_owned(),
         };
         match m.token {
-            TokenType::Identifier | TokenType::IntegerLiteral => {
+            TokenType::Identifier | TokenType::IntegerLiteral | TokenType::CharLiteral => {
                 token.val = Some(m.value.to_owned())
             }
             _ => (),
@@ -253,33 +488,247 @@ mod tests {
         let buff = Cursor::new(program.as_bytes());
         let lexer = Lexer::new();
 
-        let tokens = lexer.lex(buff);
+        let tokens = lexer.lex(buff).unwrap();
 
         let first_int = Token {
             token_type: TokenType::IntegerLiteral,
-            pos: Pos { start: 0, end: 1 },
+            pos: Pos {
+                start: 0,
+                end: 1,
+                line: 1,
+                column: 1,
+            },
             val: Some(String::from("1")),
+            lexeme: String::from("1"),
         };
         let bin_op = Token {
             token_type: tt,
             pos: Pos {
                 start: 2,
                 end: 2 + op.len(),
+                line: 1,
+                column: 3,
             },
             val: None,
+            lexeme: op.to_owned(),
         };
         let second_int = Token {
             token_type: TokenType::IntegerLiteral,
             pos: Pos {
                 start: bin_op.pos.end + 1,
                 end: bin_op.pos.end + 2,
+                line: 1,
+                column: bin_op.pos.end + 2,
             },
             val: Some(String::from("2")),
+            lexeme: String::from("2"),
         };
 
         assert_eq!(tokens, vec![first_int, bin_op, second_int],);
     }
 
+    #[test]
+    fn radix_literals_test() {
+        for literal in &["0", "010", "0b1010", "0o17", "0x1F", "1_000", "0xFF_FF"] {
+            let buff = Cursor::new(literal.as_bytes());
+            let tokens = Lexer::new().lex(buff).unwrap();
+
+            assert_eq!(
+                tokens,
+                vec![Token {
+                    token_type: TokenType::IntegerLiteral,
+                    pos: Pos {
+                        start: 0,
+                        end: literal.len(),
+                        line: 1,
+                        column: 1,
+                    },
+                    val: Some(literal.to_string()),
+                    lexeme: literal.to_string(),
+                }]
+            );
+        }
+    }
+
+    #[test]
+    fn digit_separators_test() {
+        for (literal, position) in &[("_1", 0), ("1_", 1), ("1__0", 1)] {
+            let err = Lexer::new().lex_str(literal).unwrap_err();
+
+            assert_eq!(err.c, '_');
+            assert_eq!(err.pos.start, *position);
+        }
+    }
+
+    #[test]
+    fn integer_suffix_test() {
+        for literal in &["1L", "1ll", "1u", "1UL", "1LLU", "0x1fL"] {
+            let tokens = Lexer::new().lex_str(literal).unwrap();
+
+            assert_eq!(tokens.len(), 1);
+            assert_eq!(tokens[0].token_type, TokenType::IntegerLiteral);
+            assert_eq!(tokens[0].val.as_deref(), Some(*literal));
+        }
+    }
+
+    #[test]
+    fn line_column_test() {
+        let program = "int main() {\n  int a = 1;\n\treturn a;\n}";
+        let tokens = Lexer::new().lex_str(program).unwrap();
+
+        let ret = tokens
+            .iter()
+            .find(|t| t.is_type(TokenType::Return))
+            .unwrap();
+        assert_eq!(ret.pos.line, 3);
+        assert_eq!(ret.pos.column, 2);
+
+        let close_brace = tokens.last().unwrap();
+        assert_eq!(close_brace.pos.line, 4);
+        assert_eq!(close_brace.pos.column, 1);
+    }
+
+    #[test]
+    fn absolute_offset_test() {
+        let program = "  \n\t int a=10 ;\n  a";
+        let tokens = Lexer::new().lex_str(program).unwrap();
+
+        let offsets = tokens
+            .iter()
+            .map(|t| (t.pos.start, t.pos.end))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            offsets,
+            vec![(5, 8), (9, 10), (10, 11), (11, 13), (14, 15), (18, 19)]
+        );
+
+        for t in &tokens {
+            let text = &program[t.pos.start..t.pos.end];
+            match &t.val {
+                Some(val) => assert_eq!(text, val),
+                None => assert!(!text.trim().is_empty()),
+            }
+        }
+    }
+
+    #[test]
+    fn decode_escapes_test() {
+        assert_eq!(decode_escapes(r"a\nb").unwrap(), "a\nb");
+        assert_eq!(decode_escapes(r"\t").unwrap(), "\t");
+        assert_eq!(decode_escapes(r"\r").unwrap(), "\r");
+        assert_eq!(decode_escapes(r"\\").unwrap(), "\\");
+        assert_eq!(decode_escapes(r#"\""#).unwrap(), "\"");
+        assert_eq!(decode_escapes(r"\'").unwrap(), "'");
+        assert_eq!(decode_escapes(r"\0").unwrap(), "\0");
+        assert_eq!(decode_escapes("no escapes").unwrap(), "no escapes");
+
+        assert_eq!(
+            decode_escapes(r"ab\q"),
+            Err(LexError {
+                c: 'q',
+                pos: Pos {
+                    start: 2,
+                    end: 4,
+                    line: 1,
+                    column: 3,
+                },
+            })
+        );
+    }
+
+    #[test]
+    fn display_test() {
+        assert_eq!(format!("{}", TokenType::Semicolon), ";");
+        assert_eq!(format!("{}", TokenType::CloseParenthesis), ")");
+        assert_eq!(format!("{}", TokenType::AssignmentBitLeftShift), "<<=");
+        assert_eq!(format!("{}", TokenType::While), "while");
+        assert_eq!(format!("{}", TokenType::Identifier), "identifier");
+    }
+
+    #[test]
+    fn keywords_test() {
+        let types = |program| {
+            Lexer::new()
+                .lex_str(program)
+                .unwrap()
+                .into_iter()
+                .map(|t| t.token_type)
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(
+            types("while (1) break;"),
+            vec![
+                TokenType::While,
+                TokenType::OpenParenthesis,
+                TokenType::IntegerLiteral,
+                TokenType::CloseParenthesis,
+                TokenType::Break,
+                TokenType::Semicolon,
+            ]
+        );
+        assert_eq!(
+            types("if else for do continue"),
+            vec![
+                TokenType::If,
+                TokenType::Else,
+                TokenType::For,
+                TokenType::Do,
+                TokenType::Continue,
+            ]
+        );
+        assert_eq!(
+            types("switch case default"),
+            vec![TokenType::Switch, TokenType::Case, TokenType::Default]
+        );
+        assert_eq!(
+            types("int char long void"),
+            vec![TokenType::Int, TokenType::Char, TokenType::Long, TokenType::Void]
+        );
+        assert_eq!(types("integer iffy doing"), vec![TokenType::Identifier; 3]);
+    }
+
+    #[test]
+    fn lazy_tokens_test() {
+        let program = format!("int a{}", "@".repeat(1 << 20));
+        let lexer = Lexer::new();
+
+        let mut tokens = lexer.tokens(&program);
+        assert!(tokens.next().unwrap().unwrap().is_type(TokenType::Int));
+        assert!(tokens
+            .next()
+            .unwrap()
+            .unwrap()
+            .is_type(TokenType::Identifier));
+        // only first invalid character is checked
+        assert_eq!(tokens.next().unwrap().unwrap_err().pos.start, 5);
+        assert!(tokens.next().is_none());
+
+        assert_eq!(
+            lexer.tokens(&program).take(2).filter(|t| t.is_ok()).count(),
+            2
+        );
+    }
+
+    #[test]
+    fn unexpected_character_test() {
+        let program = "int main() {\n  int a = @;\n}";
+        let err = Lexer::new().lex_str(program);
+
+        assert_eq!(
+            err,
+            Err(LexError {
+                c: '@',
+                pos: Pos {
+                    start: 23,
+                    end: 24,
+                    line: 2,
+                    column: 11,
+                },
+            })
+        );
+    }
+
     #[test]
     fn default_test() {
         let program = r#"
@@ -289,57 +738,117 @@ mod tests {
         let buff = Cursor::new(program.as_bytes());
         let lexer = Lexer::new();
 
-        let tokens = lexer.lex(buff);
+        let tokens = lexer.lex(buff).unwrap();
 
         assert_eq!(
             tokens,
             vec![
                 Token {
                     token_type: TokenType::Int,
-                    pos: Pos { start: 9, end: 12 },
-                    val: None
+                    pos: Pos {
+                        start: 9,
+                        end: 12,
+                        line: 2,
+                        column: 9,
+                    },
+                    val: None,
+                    lexeme: "int".to_owned(),
                 },
                 Token {
                     token_type: TokenType::Identifier,
-                    pos: Pos { start: 13, end: 17 },
-                    val: Some("main".to_owned())
+                    pos: Pos {
+                        start: 13,
+                        end: 17,
+                        line: 2,
+                        column: 13,
+                    },
+                    val: Some("main".to_owned()),
+                    lexeme: "main".to_owned(),
                 },
                 Token {
                     token_type: TokenType::OpenParenthesis,
-                    pos: Pos { start: 17, end: 18 },
-                    val: None
+                    pos: Pos {
+                        start: 17,
+                        end: 18,
+                        line: 2,
+                        column: 17,
+                    },
+                    val: None,
+                    lexeme: "(".to_owned(),
                 },
                 Token {
                     token_type: TokenType::CloseParenthesis,
-                    pos: Pos { start: 18, end: 19 },
-                    val: None
+                    pos: Pos {
+                        start: 18,
+                        end: 19,
+                        line: 2,
+                        column: 18,
+                    },
+                    val: None,
+                    lexeme: ")".to_owned(),
                 },
                 Token {
                     token_type: TokenType::OpenBrace,
-                    pos: Pos { start: 20, end: 21 },
-                    val: None
+                    pos: Pos {
+                        start: 20,
+                        end: 21,
+                        line: 2,
+                        column: 20,
+                    },
+                    val: None,
+                    lexeme: "{".to_owned(),
                 },
                 Token {
                     token_type: TokenType::Return,
-                    pos: Pos { start: 34, end: 40 },
-                    val: None
+                    pos: Pos {
+                        start: 34,
+                        end: 40,
+                        line: 3,
+                        column: 13,
+                    },
+                    val: None,
+                    lexeme: "return".to_owned(),
                 },
                 Token {
                     token_type: TokenType::IntegerLiteral,
-                    pos: Pos { start: 41, end: 44 },
-                    val: Some("100".to_owned())
+                    pos: Pos {
+                        start: 41,
+                        end: 44,
+                        line: 3,
+                        column: 20,
+                    },
+                    val: Some("100".to_owned()),
+                    lexeme: "100".to_owned(),
                 },
                 Token {
                     token_type: TokenType::Semicolon,
-                    pos: Pos { start: 44, end: 45 },
-                    val: None
+                    pos: Pos {
+                        start: 44,
+                        end: 45,
+                        line: 3,
+                        column: 23,
+                    },
+                    val: None,
+                    lexeme: ";".to_owned(),
                 },
                 Token {
                     token_type: TokenType::CloseBrace,
-                    pos: Pos { start: 54, end: 55 },
-                    val: None
+                    pos: Pos {
+                        start: 54,
+                        end: 55,
+                        line: 4,
+                        column: 9,
+                    },
+                    val: None,
+                    lexeme: "}".to_owned(),
                 }
             ]
         );
+
+        let open_brace = tokens
+            .iter()
+            .find(|t| t.is_type(TokenType::OpenBrace))
+            .unwrap();
+        assert_eq!(open_brace.lexeme, "{");
     }
 }
@@ -3,6 +3,163 @@ pub mod generator;
 pub mod il;
 pub mod lexer;
 pub mod parser;
+pub mod preprocessor;
 pub mod semantic_checks;
 
 pub use semantic_checks as checks;
+
+use std::error;
+use std::fmt;
+
+/// Options tunes the stages of the compilation.
+#[derive(Default)]
+pub struct Options {
+    /// optimization folds constant expressions and runs the IR passes.
+    pub optimization: bool,
+    /// disabled_passes are names of the IR passes which are skipped by the optimization.
+    pub disabled_passes: Vec<String>,
+    pub tac: il::tac::Options,
+    pub generator: generator::Options,
+}
+
+/// Compilation is a result of a successful compilation.
+pub struct Compilation {
+    pub asm: String,
+    pub warnings: Vec<Warning>,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum Warning {
+    /// ShiftWidth is raised when a constant shift count is not less than the width of int.
+    ShiftWidth,
+}
+
+impl fmt::Display for Warning {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Warning::ShiftWidth => {
+                write!(f, "shift count is greater than or equal to width of type")
+            }
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum CompileError {
+    Semantic(checks::SemanticError),
+    Tac(il::tac::TacError),
+}
+
+impl fmt::Display for CompileError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            CompileError::Semantic(err) => err.fmt(f),
+            CompileError::Tac(err) => err.fmt(f),
+        }
+    }
+}
+
+impl error::Error for CompileError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            CompileError::Semantic(err) => Some(err),
+            CompileError::Tac(err) => Some(err),
+        }
+    }
+}
+
+impl From<checks::SemanticError> for CompileError {
+    fn from(err: checks::SemanticError) -> Self {
+        CompileError::Semantic(err)
+    }
+}
+
+impl From<il::tac::TacError> for CompileError {
+    fn from(err: il::tac::TacError) -> Self {
+        CompileError::Tac(err)
+    }
+}
+
+/// compile checks a program and translates it to assembly of the syntax.
+pub fn compile<S: generator::syntax::Syntax>(
+    mut program: ast::Program,
+    opts: &Options,
+) -> Result<Compilation, CompileError> {
+    let warnings = check(&program)?;
+
+    if opts.optimization {
+        ast::fold_constants(&mut program);
+    }
+
+    let mut ir = il::tac::il(&program, &opts.tac)?;
+    if opts.optimization {
+        ir.code = ir
+            .code
+            .into_iter()
+            .map(|f| il::passes::run(f, &opts.disabled_passes))
+            .collect();
+    }
+
+    Ok(Compilation {
+        asm: generator::gen::<S>(ir, &opts.generator),
+        warnings,
+    })
+}
+
+/// check runs the semantic checks over a program.
+fn check(program: &ast::Program) -> Result<Vec<Warning>, checks::SemanticError> {
+    checks::function_checks::func_check(program)?;
+    checks::scope_checks::var_check(program)?;
+    checks::switch_checks::case_check(program)?;
+
+    if !checks::global_vars::name_check(program) {
+        return Err(checks::SemanticError::GlobalNameConflict);
+    }
+
+    if !checks::global_vars::multi_definition(program) {
+        return Err(checks::SemanticError::GlobalRedefinition);
+    }
+
+    if !checks::global_vars::use_before_definition(program) {
+        return Err(checks::SemanticError::UseBeforeDeclaration);
+    }
+
+    let mut warnings = Vec::new();
+    if !checks::shift_checks::shift_width_check(program) {
+        warnings.push(Warning::ShiftWidth);
+    }
+
+    Ok(warnings)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use generator::syntax::GASM;
+
+    fn compile_str(code: &str) -> Result<Compilation, CompileError> {
+        let tokens = lexer::Lexer::new().lex_str(code).unwrap();
+        let program = parser::parse(tokens).unwrap();
+        compile::<GASM>(program, &Options::default())
+    }
+
+    #[test]
+    fn checks() {
+        assert!(matches!(
+            compile_str("int a = 1; int a = 2; int main() { return a; }"),
+            Err(CompileError::Semantic(
+                checks::SemanticError::GlobalRedefinition
+            ))
+        ));
+        assert!(matches!(
+            compile_str("int main() { return b; } int b;"),
+            Err(CompileError::Semantic(
+                checks::SemanticError::UndeclaredVariable(..)
+            ))
+        ));
+
+        let compilation = compile_str("int main() { return 1 << 40; }").unwrap();
+        assert_eq!(compilation.warnings, vec![Warning::ShiftWidth]);
+        assert!(compilation.asm.contains("main:"));
+    }
+}
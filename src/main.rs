@@ -1,17 +1,17 @@
 use std::io::Write;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
 use clap::Clap;
 
 use simple_c_compiler::{
-    checks,
+    ast, checks,
     generator::{
         self,
-        syntax::{Intel, GASM},
+        syntax::{Indent, Intel, GASM},
     },
     il::{self, tac},
-    lexer::Lexer,
-    parser,
+    lexer::{Lexer, Pos},
+    parser, preprocessor,
 };
 
 mod pretty_output;
@@ -23,7 +23,7 @@ mod pretty_output;
     author = "Maxim Zhiburt <zhiburt@gmail.com>",
     about = "A handcrafted C compiler to assembler language
 
-!Supports only int type, the other basic types will cause an error"
+!Types `char`, `long` and `void` are parsed but code generation treats them as int"
 )]
 struct Opt {
     /// Prints tokens which are produced by lexical analyzer to stdout
@@ -35,15 +35,47 @@ struct Opt {
     /// Prints IR(Three Address Code) to stdout
     #[clap(short = "tac", long = "pretty-tac")]
     pretty_tac: bool,
+    /// Prints a Makefile rule listing the input files and the files they include
+    /// instead of compiling
+    #[clap(short = "M")]
+    deps: bool,
     /// Activate optimizations
     #[clap(short = "O")]
     optimization: bool,
+    /// Prints the optimization passes which are run by `-O`
+    #[clap(long = "list-passes")]
+    list_passes: bool,
+    /// Turns off an optimization pass, see `--list-passes`
+    #[clap(long = "disable-pass", value_name = "NAME", multiple_occurrences = true)]
+    disable_pass: Vec<String>,
+    /// Lowers loops with a condition on the top and a jump back at the end
+    #[clap(long = "simple-loops")]
+    simple_loops: bool,
+    /// Initializes local variables declared without an initializer
+    #[clap(long = "ftrivial-auto-var-init", value_name = "zero", possible_values = &["zero"])]
+    trivial_auto_var_init: Option<String>,
+    /// Tears down stack frames by `leave` instruction
+    #[clap(long = "use-leave")]
+    use_leave: bool,
+    /// Doesn't set up a frame pointer in leaf functions and addresses their locals by `%rsp`
+    #[clap(long = "fomit-frame-pointer")]
+    omit_frame_pointer: bool,
+    /// Comments the output file with the IR instructions the code is generated for
+    #[clap(long = "annotate")]
+    annotate: bool,
+    /// Doesn't emit the `.ident` directive which records the compiler version
+    #[clap(long = "no-ident")]
+    no_ident: bool,
+    /// Indentation of instructions in the output file, a tab for intel and 4 spaces for gasm by default
+    #[clap(long = "indent", value_name = "tab|spaces:N", parse(try_from_str))]
+    indent: Option<Indent>,
     /// Assembly syntax of the output file
     #[clap(short, long, value_name = "[intel|gasm]")]
     syntax: Option<String>,
-    /// The input file, written in C programming language
-    #[clap(parse(from_os_str))]
-    input_file: PathBuf,
+    /// The input files, written in C programming language.
+    /// Several files are compiled into a single assembly file
+    #[clap(parse(from_os_str), required_unless_present = "list-passes")]
+    input_files: Vec<PathBuf>,
     /// The output file, in which will be carried out a compilation
     #[clap(short = "o", parse(from_os_str))]
     out_file: Option<PathBuf>,
@@ -51,25 +83,84 @@ struct Opt {
 
 fn main() {
     let opt = Opt::parse();
-    let input_file = opt.input_file;
+    if opt.list_passes {
+        for pass in il::passes::PASSES {
+            println!("{:<12} {}", pass.name, pass.description);
+        }
+        return;
+    }
+
+    if let Some(name) = opt.disable_pass.iter().find(|name| il::passes::find(name).is_none()) {
+        eprintln!("unknown optimization pass '{}'", name);
+        std::process::exit(1);
+    }
+
     let output_file = opt.out_file.map_or(PathBuf::from("asm.s"), |name| name);
 
-    let program = std::fs::File::open(input_file).unwrap();
     let lexer = Lexer::new();
-    let tokens = lexer.lex(program);
+    let mut ast = ast::Program(Vec::new());
+    for input_file in &opt.input_files {
+        let source = match preprocessor::preprocess(input_file) {
+            Ok(source) => source,
+            Err(err) => {
+                eprintln!("{}", err);
+                std::process::exit(1);
+            }
+        };
+
+        if opt.deps {
+            println!("{}", source.deps(&output_file));
+            continue;
+        }
+
+        let tokens = match lexer.lex_str(&source.code) {
+            Ok(tokens) => tokens,
+            Err(mut err) => {
+                let file = relocate(&source, input_file, &mut err.pos);
+                eprintln!("{}: {}", file.display(), err);
+                std::process::exit(1);
+            }
+        };
+
+        if opt.pretty_lex {
+            println!("\n{}", pretty_output::pretty_tokens(&tokens));
+        }
 
-    if opt.pretty_lex {
-        println!("\n{}", pretty_output::pretty_tokens(&tokens));
+        // all files are merged into one translation unit
+        // so duplicate definitions are caught by the checks below
+        match parser::parse(tokens) {
+            Ok(file) => ast.0.extend(file.0),
+            Err(mut err) => {
+                let file = match err.pos_mut() {
+                    Some(pos) => relocate(&source, input_file, pos),
+                    None => input_file.clone(),
+                };
+                eprintln!("{}: {}", file.display(), err);
+                std::process::exit(1);
+            }
+        };
     }
 
-    let ast = parser::parse(tokens).expect("Cannot parse program");
+    if opt.deps {
+        return;
+    }
 
     if opt.pretty_ast {
-        println!("\n{}", pretty_output::pretty_prog(&ast));
+        println!("\n{}", ast);
+    }
+
+    if let Err(err) = checks::function_checks::func_check(&ast) {
+        eprintln!("{}", err);
+        std::process::exit(120);
+    }
+
+    if let Err(err) = checks::scope_checks::var_check(&ast) {
+        eprintln!("{}", err);
+        std::process::exit(120);
     }
 
-    if !checks::function_checks::func_check(&ast) {
-        eprintln!("invalid function declaration or definition");
+    if let Err(err) = checks::switch_checks::case_check(&ast) {
+        eprintln!("{}", err);
         std::process::exit(120);
     }
 
@@ -88,36 +179,75 @@ fn main() {
         std::process::exit(-123);
     }
 
-    let mut tac = tac::il(&ast);
+    if !checks::shift_checks::shift_width_check(&ast) {
+        eprintln!("warning: shift count is greater than or equal to width of type");
+    }
+
+    if opt.optimization {
+        ast::fold_constants(&mut ast);
+    }
+
+    let tac_opts = tac::Options {
+        simple_loops: opt.simple_loops,
+        zero_init_locals: opt.trivial_auto_var_init.is_some(),
+    };
+    let mut tac = match tac::il(&ast, &tac_opts) {
+        Ok(tac) => tac,
+        Err(err) => {
+            eprintln!("{}", err);
+            std::process::exit(120);
+        }
+    };
+    let disabled_passes = opt.disable_pass;
     if opt.optimization {
         tac.code = tac
             .code
             .into_iter()
-            .map(|mut f| {
-                il::constant_fold::fold(&mut f.instructions);
-                f = il::unused_code::remove_unused(f);
-                f
-            })
+            .map(|f| il::passes::run(f, &disabled_passes))
             .collect();
     }
 
     if opt.pretty_tac {
         for f in &tac.code {
             println!();
-            pretty_output::pretty_tac(std::io::stdout(), f);
+            print!("{}", pretty_output::pretty_tac(f));
             println!();
-            let intervals =
-                simple_c_compiler::il::lifeinterval::LiveIntervals::new(&f.instructions);
+            let cfg = il::cfg::build(f);
+            let intervals = il::lifeinterval::LiveIntervals::from_liveness(
+                f,
+                &cfg,
+                &il::liveness::liveness(f, &cfg),
+            );
             writeln!(std::io::stdout(), "intervals {}\n{:?}", f.name, intervals.0).unwrap();
             println!();
         }
     }
 
+    let gen_opts = generator::Options {
+        ident: !opt.no_ident,
+        tail_calls: opt.optimization,
+        use_leave: opt.use_leave,
+        omit_frame_pointer: opt.omit_frame_pointer,
+        indent: opt.indent,
+        annotate: opt.annotate,
+    };
     let asm = match opt.syntax {
-        Some(s) if s == "intel" => generator::gen::<Intel>(tac),
-        _ => generator::gen::<GASM>(tac),
+        Some(s) if s == "intel" => generator::gen::<Intel>(tac, &gen_opts),
+        _ => generator::gen::<GASM>(tac, &gen_opts),
     };
 
     let mut asm_file = std::fs::File::create(output_file).expect("Cannot create output file");
     writeln!(asm_file, "{}", asm).unwrap();
 }
+
+/// relocate moves the position from the preprocessed code to the file the line comes from
+/// and returns the file.
+fn relocate(source: &preprocessor::Source, input_file: &Path, pos: &mut Pos) -> PathBuf {
+    match source.locate(pos.line) {
+        Some((file, line)) => {
+            pos.line = line;
+            file.to_owned()
+        }
+        None => input_file.to_owned(),
+    }
+}
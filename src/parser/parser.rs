@@ -4,21 +4,88 @@
 /// might be better to check it and if something wrong fail?
 /// but not effect original vector, but it's not very crucial now, until we return tokens even in error,
 /// or take &tokens not move them
-use crate::{ast, lexer::Token, lexer::TokenType};
+use crate::{ast, lexer, lexer::Pos, lexer::Token, lexer::TokenType};
 
 use std::error;
 use std::fmt;
+use std::num::{IntErrorKind, ParseIntError};
 
 pub type Result<T> = std::result::Result<T, CompilerError>;
 
 #[derive(Debug)]
 pub enum CompilerError {
-    ParsingError,
+    /// UnexpectedToken is raised when a token doesn't fit in a grammar,
+    /// `expected` lists the tokens which could be there.
+    UnexpectedToken {
+        found: TokenType,
+        expected: Vec<TokenType>,
+        pos: Pos,
+    },
+    /// UnexpectedEof is raised when a program ends in the middle of a construction.
+    UnexpectedEof,
+    /// NotAssignable is raised when `++` or `--` is applied to something but a variable.
+    NotAssignable(Pos),
+    /// NotConstant is raised when a `case` label isn't an integer constant.
+    NotConstant(Pos),
+    /// InvalidLiteral is raised when a literal can't be decoded e.g. `'ab'`.
+    InvalidLiteral(Pos),
+    /// IntegerOverflow is raised when a literal doesn't fit in a 64 bit integer.
+    IntegerOverflow(Pos),
 }
 
 impl fmt::Display for CompilerError {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "syntax_err")
+        match self {
+            CompilerError::UnexpectedToken {
+                found,
+                expected,
+                pos,
+            } => {
+                let expected = expected
+                    .iter()
+                    .map(|t| format!("`{}`", t))
+                    .collect::<Vec<_>>()
+                    .join(" or ");
+                write!(
+                    f,
+                    "expected {} but found `{}` at {}:{}",
+                    expected, found, pos.line, pos.column
+                )
+            }
+            CompilerError::UnexpectedEof => write!(f, "unexpected end of file"),
+            CompilerError::NotAssignable(pos) => write!(
+                f,
+                "operand of increment or decrement isn't assignable at {}:{}",
+                pos.line, pos.column
+            ),
+            CompilerError::NotConstant(pos) => write!(
+                f,
+                "case label isn't an integer constant at {}:{}",
+                pos.line, pos.column
+            ),
+            CompilerError::InvalidLiteral(pos) => {
+                write!(f, "invalid literal at {}:{}", pos.line, pos.column)
+            }
+            CompilerError::IntegerOverflow(pos) => write!(
+                f,
+                "integer literal is too large at {}:{}",
+                pos.line, pos.column
+            ),
+        }
+    }
+}
+
+impl CompilerError {
+    /// pos_mut returns the position the error is reported at if there's one.
+    pub fn pos_mut(&mut self) -> Option<&mut Pos> {
+        match self {
+            CompilerError::UnexpectedToken { pos, .. }
+            | CompilerError::NotAssignable(pos)
+            | CompilerError::NotConstant(pos)
+            | CompilerError::InvalidLiteral(pos)
+            | CompilerError::IntegerOverflow(pos) => Some(pos),
+            CompilerError::UnexpectedEof => None,
+        }
     }
 }
 
@@ -46,10 +113,10 @@ where
             break;
         }
 
-        let tok_type = tokens.remove(0).token_type;
+        let tok = tokens.remove(0);
         let (right, stashed_tokens) = parse(tokens)?;
-        let op = map_token_to_ast(tok_type).unwrap();
-        exp = ast::Exp::BinOp(op, Box::new(exp), Box::new(right));
+        let op = map_token_to_ast(tok.token_type).unwrap();
+        exp = ast::Exp::BinOp(op, Box::new(exp), Box::new(right), tok.pos);
         tokens = stashed_tokens;
     }
 
@@ -105,6 +172,36 @@ fn map_inc_dec_token(t: TokenType, postfix: bool) -> Option<ast::IncOrDec> {
     }
 }
 
+/// parse_int_literal decodes an integer literal regarding its radix prefix.
+///
+/// `0x` is hexadecimal, `0b` is binary and `0o` is octal.
+/// As in C a bare leading `0` means octal as well, so `010` is 8,
+/// though the single `0` is just a zero.
+/// Underscores are digit separators and ignored.
+fn parse_int_literal(literal: &str) -> std::result::Result<u64, ParseIntError> {
+    let literal = literal.replace('_', "");
+    let literal = literal.trim_end_matches(['u', 'U', 'l', 'L']);
+    let (digits, radix) = match literal.get(..2) {
+        Some("0x") | Some("0X") => (&literal[2..], 16),
+        Some("0b") | Some("0B") => (&literal[2..], 2),
+        Some("0o") | Some("0O") => (&literal[2..], 8),
+        _ if literal.len() > 1 && literal.starts_with('0') => (&literal[1..], 8),
+        _ => (literal, 10),
+    };
+
+    u64::from_str_radix(digits, radix)
+}
+
+/// parse_char_literal returns a byte value of a quoted character e.g. `'a'` or `'\n'`.
+fn parse_char_literal(literal: &str) -> Option<i64> {
+    let c = literal.strip_prefix('\'')?.strip_suffix('\'')?;
+    let c = lexer::decode_escapes(c).ok()?;
+    match c.as_bytes() {
+        [c] => Some(*c as i64),
+        _ => None,
+    }
+}
+
 pub fn is_operators(t: &[Token], operators: &[TokenType]) -> bool {
     for (i, op) in operators.iter().enumerate() {
         match t.get(i) {
@@ -133,24 +230,41 @@ fn map_assign_op(t: &Token) -> Option<ast::AssignmentOp> {
     }
 }
 
-pub fn parse_exp(mut tokens: Vec<Token>) -> Result<(ast::Exp, Vec<Token>)> {
-    if tokens[0].is_type(TokenType::Identifier) && tokens[1].is_type(TokenType::Assignment) {
+/// parse_exp parses an expression including the comma operator.
+pub fn parse_exp(tokens: Vec<Token>) -> Result<(ast::Exp, Vec<Token>)> {
+    let (mut exp, mut tokens) = parse_assignment_exp(tokens)?;
+    while is_operators(&tokens, &[TokenType::Comma]) {
+        let comma = tokens.remove(0);
+        let (rhs, toks) = parse_assignment_exp(tokens)?;
+        tokens = toks;
+        exp = ast::Exp::Comma(Box::new(exp), Box::new(rhs), comma.pos);
+    }
+
+    Ok((exp, tokens))
+}
+
+/// parse_assignment_exp parses an expression which can't contain
+/// the comma operator on the top level, e.g. an argument of a function call.
+pub fn parse_assignment_exp(mut tokens: Vec<Token>) -> Result<(ast::Exp, Vec<Token>)> {
+    if is_operators(&tokens, &[TokenType::Identifier, TokenType::Assignment]) {
         let var = tokens.remove(0);
         tokens.remove(0);
-        let (exp, tokens) = parse_exp(tokens)?;
+        let (exp, tokens) = parse_assignment_exp(tokens)?;
 
         Ok((
-            ast::Exp::Assign(var.val.unwrap().to_owned(), Box::new(exp)),
+            ast::Exp::Assign(var.val.unwrap().to_owned(), Box::new(exp), var.pos),
             tokens,
         ))
-    } else if tokens[0].is_type(TokenType::Identifier) && map_assign_op(&tokens[1]).is_some() {
+    } else if is_operators(&tokens, &[TokenType::Identifier])
+        && tokens.get(1).and_then(map_assign_op).is_some()
+    {
         let var = tokens.remove(0);
-        let op = map_assign_op(&tokens[0]).unwrap();
-        tokens.remove(0);
-        let (exp, tokens) = parse_exp(tokens)?;
+        let op_token = tokens.remove(0);
+        let op = map_assign_op(&op_token).unwrap();
+        let (exp, tokens) = parse_assignment_exp(tokens)?;
 
         Ok((
-            ast::Exp::AssignOp(var.val.unwrap().to_owned(), op, Box::new(exp)),
+            ast::Exp::AssignOp(var.val.unwrap().to_owned(), op, Box::new(exp), var.pos),
             tokens,
         ))
     } else {
@@ -162,14 +276,22 @@ pub fn parse_conditional_expr(tokens: Vec<Token>) -> Result<(ast::Exp, Vec<Token
     let (mut exp, mut tokens) = parse_or_expr(tokens)?;
     match tokens.get(0) {
         Some(tok) if tok.token_type == TokenType::QuestionSign => {
-            tokens.remove(0);
+            let question = tokens.remove(0);
 
             let (left_exp, mut toks) = parse_exp(tokens)?;
-            compare_token(toks.remove(0), TokenType::Colon)?;
+            if !is_operators(&toks, &[TokenType::Colon]) {
+                return Err(unexpected(&toks, &[TokenType::Colon]));
+            }
+            toks.remove(0);
             let (right_exp, toks) = parse_conditional_expr(toks)?;
 
             tokens = toks;
-            exp = ast::Exp::CondExp(Box::new(exp), Box::new(left_exp), Box::new(right_exp))
+            exp = ast::Exp::CondExp(
+                Box::new(exp),
+                Box::new(left_exp),
+                Box::new(right_exp),
+                question.pos,
+            )
         }
         _ => (),
     };
@@ -182,7 +304,19 @@ pub fn parse_or_expr(tokens: Vec<Token>) -> Result<(ast::Exp, Vec<Token>)> {
 }
 
 pub fn parse_and_expr(tokens: Vec<Token>) -> Result<(ast::Exp, Vec<Token>)> {
-    parse_expr(parse_equality_expr, &[TokenType::And], tokens)
+    parse_expr(parse_bitwise_or_expr, &[TokenType::And], tokens)
+}
+
+pub fn parse_bitwise_or_expr(tokens: Vec<Token>) -> Result<(ast::Exp, Vec<Token>)> {
+    parse_expr(parse_bitwise_xor_expr, &[TokenType::BitwiseOr], tokens)
+}
+
+pub fn parse_bitwise_xor_expr(tokens: Vec<Token>) -> Result<(ast::Exp, Vec<Token>)> {
+    parse_expr(parse_bitwise_and_expr, &[TokenType::BitwiseXor], tokens)
+}
+
+pub fn parse_bitwise_and_expr(tokens: Vec<Token>) -> Result<(ast::Exp, Vec<Token>)> {
+    parse_expr(parse_equality_expr, &[TokenType::BitwiseAnd], tokens)
 }
 
 pub fn parse_equality_expr(tokens: Vec<Token>) -> Result<(ast::Exp, Vec<Token>)> {
@@ -195,7 +329,7 @@ pub fn parse_equality_expr(tokens: Vec<Token>) -> Result<(ast::Exp, Vec<Token>)>
 
 pub fn parse_relational_expr(tokens: Vec<Token>) -> Result<(ast::Exp, Vec<Token>)> {
     parse_expr(
-        parse_addictive_expr,
+        parse_shift_expr,
         &[
             TokenType::GreaterThan,
             TokenType::GreaterThanOrEqual,
@@ -206,30 +340,18 @@ pub fn parse_relational_expr(tokens: Vec<Token>) -> Result<(ast::Exp, Vec<Token>
     )
 }
 
-pub fn parse_addictive_expr(tokens: Vec<Token>) -> Result<(ast::Exp, Vec<Token>)> {
-    parse_expr(
-        parse_bitwise_expr,
-        &[TokenType::Addition, TokenType::Negation],
-        tokens,
-    )
-}
-
-pub fn parse_bitwise_expr(tokens: Vec<Token>) -> Result<(ast::Exp, Vec<Token>)> {
+pub fn parse_shift_expr(tokens: Vec<Token>) -> Result<(ast::Exp, Vec<Token>)> {
     parse_expr(
-        parse_un_op_term,
+        parse_addictive_expr,
         &[TokenType::BitwiseLeftShift, TokenType::BitwiseRightShift],
         tokens,
     )
 }
 
-pub fn parse_un_op_term(tokens: Vec<Token>) -> Result<(ast::Exp, Vec<Token>)> {
+pub fn parse_addictive_expr(tokens: Vec<Token>) -> Result<(ast::Exp, Vec<Token>)> {
     parse_expr(
         parse_term,
-        &[
-            TokenType::BitwiseAnd,
-            TokenType::BitwiseOr,
-            TokenType::BitwiseXor,
-        ],
+        &[TokenType::Addition, TokenType::Negation],
         tokens,
     )
 }
@@ -247,15 +369,15 @@ pub fn parse_term(tokens: Vec<Token>) -> Result<(ast::Exp, Vec<Token>)> {
 }
 
 pub fn parse_factor(mut tokens: Vec<Token>) -> Result<(ast::Exp, Vec<Token>)> {
-    let picked_token = tokens.get(0).unwrap();
-    match picked_token.token_type {
+    let token_type = match tokens.get(0) {
+        Some(tok) => tok.token_type,
+        None => return Err(CompilerError::UnexpectedEof),
+    };
+    let (exp, mut tokens) = match token_type {
         TokenType::OpenParenthesis => {
-            let mut token = tokens.remove(0);
-            let (expr, mut tokens) = parse_exp(tokens).unwrap();
-            token = tokens.remove(0);
-            if token.token_type != TokenType::CloseParenthesis {
-                return Err(CompilerError::ParsingError);
-            }
+            tokens.remove(0);
+            let (expr, mut tokens) = parse_exp(tokens)?;
+            compare_token(&mut tokens, TokenType::CloseParenthesis)?;
             Ok((expr, tokens))
         }
         TokenType::Identifier => {
@@ -268,7 +390,11 @@ pub fn parse_factor(mut tokens: Vec<Token>) -> Result<(ast::Exp, Vec<Token>)> {
                     let tok_type = tok.token_type;
                     tokens.remove(0);
                     Ok((
-                        ast::Exp::IncOrDec(var_name, map_inc_dec_token(tok_type, true).unwrap()),
+                        ast::Exp::IncOrDec(
+                            var_name,
+                            map_inc_dec_token(tok_type, true).unwrap(),
+                            token.pos,
+                        ),
                         tokens,
                     ))
                 }
@@ -276,61 +402,143 @@ pub fn parse_factor(mut tokens: Vec<Token>) -> Result<(ast::Exp, Vec<Token>)> {
                     tokens.remove(0);
                     // can it be simplified?
                     let mut params = Vec::new();
-                    if !tokens[0].is_type(TokenType::CloseParenthesis) {
-                        let (exp, toks) = parse_exp(tokens)?;
+                    if !is_operators(&tokens, &[TokenType::CloseParenthesis]) {
+                        let (exp, toks) = parse_assignment_exp(tokens)?;
                         tokens = toks;
                         params.push(exp);
-                        while tokens[0].is_type(TokenType::Comma) {
+                        while is_operators(&tokens, &[TokenType::Comma]) {
                             tokens.remove(0);
-                            let (exp, toks) = parse_exp(tokens)?;
+                            let (exp, toks) = parse_assignment_exp(tokens)?;
                             tokens = toks;
                             params.push(exp);
                         }
                     }
-                    compare_token(tokens.remove(0), TokenType::CloseParenthesis).unwrap();
+                    compare_token(&mut tokens, TokenType::CloseParenthesis)?;
 
-                    Ok((ast::Exp::FuncCall(token.val.unwrap(), params), tokens))
+                    Ok((
+                        ast::Exp::FuncCall(token.val.unwrap(), params, token.pos),
+                        tokens,
+                    ))
                 }
-                _ => Ok((ast::Exp::Var(token.val.unwrap().to_owned()), tokens)),
+                _ => Ok((
+                    ast::Exp::Var(token.val.unwrap().to_owned(), token.pos),
+                    tokens,
+                )),
             }
         }
         TokenType::IntegerLiteral => {
             let token = tokens.remove(0);
+            let value = match parse_int_literal(token.val.as_ref().unwrap()) {
+                Ok(value) if value <= i64::MAX as u64 => value as i64,
+                Err(err) if err.kind() != &IntErrorKind::PosOverflow => {
+                    return Err(CompilerError::InvalidLiteral(token.pos))
+                }
+                _ => return Err(CompilerError::IntegerOverflow(token.pos)),
+            };
+            Ok((ast::Exp::Const(ast::Const::Int(value), token.pos), tokens))
+        }
+        TokenType::CharLiteral => {
+            let token = tokens.remove(0);
+            let value = match parse_char_literal(token.val.as_ref().unwrap()) {
+                Some(value) => value,
+                None => return Err(CompilerError::InvalidLiteral(token.pos)),
+            };
+            Ok((ast::Exp::Const(ast::Const::Int(value), token.pos), tokens))
+        }
+        TokenType::BitwiseAnd => {
+            let op = tokens.remove(0);
+            let name = compare_token(&mut tokens, TokenType::Identifier)?;
+            Ok((ast::Exp::AddressOf(name.val.unwrap(), op.pos), tokens))
+        }
+        TokenType::Negation
+            if matches!(tokens.get(1), Some(tok) if tok.is_type(TokenType::IntegerLiteral)
+                && parse_int_literal(tok.val.as_ref().unwrap()) == Ok(i64::MIN.unsigned_abs())) =>
+        {
+            // a literal is unsigned since its sign is a separate unary operator,
+            // so the magnitude of `-9223372036854775808` is kept as the i64::MIN value.
+            let op = tokens.remove(0);
+            let literal = ast::Exp::Const(ast::Const::Int(i64::MIN), tokens.remove(0).pos);
             Ok((
-                ast::Exp::Const(ast::Const::Int(
-                    token.val.as_ref().unwrap().parse().unwrap(),
-                )),
+                ast::Exp::UnOp(ast::UnOp::Negation, Box::new(literal), op.pos),
                 tokens,
             ))
         }
         TokenType::Negation | TokenType::LogicalNegation | TokenType::BitwiseComplement => {
             let token = tokens.remove(0);
-            let (expr, tokens) = parse_expr(parse_factor, &[TokenType::Or], tokens).unwrap();
+            let (expr, tokens) = parse_factor(tokens)?;
             Ok((
-                ast::Exp::UnOp(map_token_to_unop(token.token_type).unwrap(), Box::new(expr)),
+                ast::Exp::UnOp(
+                    map_token_to_unop(token.token_type).unwrap(),
+                    Box::new(expr),
+                    token.pos,
+                ),
                 tokens,
             ))
         }
         _ => parse_inc_dec_expr(tokens),
+    }?;
+
+    // a postfix `++` or `--` of a variable is consumed above
+    // so here it's applied to a parenthesized variable e.g. `(x)++`
+    // or to something else e.g. `5++` or `x++++`
+    let op = tokens
+        .get(0)
+        .and_then(|tok| map_inc_dec_token(tok.token_type, true));
+    let exp = match (exp, op) {
+        (ast::Exp::Var(name, pos), Some(op)) => {
+            tokens.remove(0);
+            ast::Exp::IncOrDec(name, op, pos)
+        }
+        (exp, _) => exp,
+    };
+
+    match tokens.get(0) {
+        Some(tok) if tok.is_type(TokenType::Increment) || tok.is_type(TokenType::Decrement) => {
+            Err(CompilerError::NotAssignable(tok.pos.clone()))
+        }
+        _ => Ok((exp, tokens)),
     }
 }
 
 pub fn parse_inc_dec_expr(mut tokens: Vec<Token>) -> Result<(ast::Exp, Vec<Token>)> {
-    let token = tokens.remove(0);
-    let var_token = tokens.remove(0);
+    let op = match tokens.get(0) {
+        Some(tok) => map_inc_dec_token(tok.token_type, false),
+        None => None,
+    };
+    let op = match op {
+        Some(op) => op,
+        None => {
+            return Err(unexpected(
+                &tokens,
+                &[
+                    TokenType::Identifier,
+                    TokenType::IntegerLiteral,
+                    TokenType::OpenParenthesis,
+                ],
+            ))
+        }
+    };
+    let op_token = tokens.remove(0);
+
+    // the operand may be parenthesized e.g. `++(x)`
+    if is_operators(&tokens, &[TokenType::OpenParenthesis]) {
+        let pos = tokens[0].pos.clone();
+        return match parse_factor(tokens)? {
+            (ast::Exp::Var(var_name, _), tokens) => {
+                Ok((ast::Exp::IncOrDec(var_name, op, op_token.pos), tokens))
+            }
+            _ => Err(CompilerError::NotAssignable(pos)),
+        };
+    }
+
+    let var_token = compare_token(&mut tokens, TokenType::Identifier)?;
     let var_name = var_token.val.unwrap().to_owned();
-    Ok((
-        ast::Exp::IncOrDec(
-            var_name,
-            map_inc_dec_token(token.token_type, false).unwrap(),
-        ),
-        tokens,
-    ))
+    Ok((ast::Exp::IncOrDec(var_name, op, op_token.pos), tokens))
 }
 
 pub fn parse_opt_exp(tokens: Vec<Token>) -> Result<(Option<ast::Exp>, Vec<Token>)> {
-    match tokens[0].token_type {
-        TokenType::Semicolon | TokenType::CloseParenthesis => Ok((None, tokens)),
+    match tokens.get(0).map(|tok| tok.token_type) {
+        Some(TokenType::Semicolon) | Some(TokenType::CloseParenthesis) => Ok((None, tokens)),
         _ => {
             let (exp, tokens) = parse_exp(tokens)?;
             Ok((Some(exp), tokens))
@@ -339,27 +547,29 @@ pub fn parse_opt_exp(tokens: Vec<Token>) -> Result<(Option<ast::Exp>, Vec<Token>
 }
 
 pub fn parse_statement(mut tokens: Vec<Token>) -> Result<(ast::Statement, Vec<Token>)> {
-    let (stat, tokens) = match tokens.get(0).unwrap().token_type {
+    let (token_type, pos) = match tokens.get(0) {
+        Some(tok) => (tok.token_type, tok.pos.clone()),
+        None => return Err(CompilerError::UnexpectedEof),
+    };
+    let (stat, tokens) = match token_type {
         TokenType::Return => {
             tokens.remove(0);
 
-            let (exp, mut tokens) = parse_exp(tokens).unwrap();
-            compare_token(tokens.remove(0), TokenType::Semicolon).unwrap();
+            let (exp, mut tokens) = parse_exp(tokens)?;
+            compare_token(&mut tokens, TokenType::Semicolon)?;
 
-            (ast::Statement::Return { exp: exp }, tokens)
+            (ast::Statement::Return { exp, pos }, tokens)
         }
         TokenType::For => {
             tokens.remove(0);
 
-            compare_token(tokens.remove(0), TokenType::OpenParenthesis).unwrap();
+            compare_token(&mut tokens, TokenType::OpenParenthesis)?;
             if is_seem_decl(&tokens) {
                 let (decl, toks) = parse_decl(tokens)?;
-                let (controll_exp, mut toks) = parse_opt_exp(toks)?;
-                let controll_exp =
-                    controll_exp.map_or(ast::Exp::Const(ast::Const::Int(1)), |ce| ce);
-                compare_token(toks.remove(0), TokenType::Semicolon).unwrap();
+                let (controll_exp, mut toks) = parse_for_condition(toks)?;
+                compare_token(&mut toks, TokenType::Semicolon)?;
                 let (exp, mut toks) = parse_opt_exp(toks)?;
-                compare_token(toks.remove(0), TokenType::CloseParenthesis).unwrap();
+                compare_token(&mut toks, TokenType::CloseParenthesis)?;
                 let (statement, toks) = parse_statement(toks)?;
 
                 (
@@ -368,18 +578,17 @@ pub fn parse_statement(mut tokens: Vec<Token>) -> Result<(ast::Statement, Vec<To
                         exp2: controll_exp,
                         exp3: exp,
                         statement: Box::new(statement),
+                        pos,
                     },
                     toks,
                 )
             } else {
                 let (exp1, mut toks) = parse_opt_exp(tokens)?;
-                compare_token(toks.remove(0), TokenType::Semicolon).unwrap();
-                let (controll_exp, mut toks) = parse_opt_exp(toks)?;
-                let controll_exp =
-                    controll_exp.map_or(ast::Exp::Const(ast::Const::Int(1)), |ce| ce);
-                compare_token(toks.remove(0), TokenType::Semicolon).unwrap();
+                compare_token(&mut toks, TokenType::Semicolon)?;
+                let (controll_exp, mut toks) = parse_for_condition(toks)?;
+                compare_token(&mut toks, TokenType::Semicolon)?;
                 let (exp, mut toks) = parse_opt_exp(toks)?;
-                compare_token(toks.remove(0), TokenType::CloseParenthesis).unwrap();
+                compare_token(&mut toks, TokenType::CloseParenthesis)?;
                 let (statement, toks) = parse_statement(toks)?;
 
                 (
@@ -388,6 +597,7 @@ pub fn parse_statement(mut tokens: Vec<Token>) -> Result<(ast::Statement, Vec<To
                         exp2: controll_exp,
                         exp3: exp,
                         statement: Box::new(statement),
+                        pos,
                     },
                     toks,
                 )
@@ -396,15 +606,16 @@ pub fn parse_statement(mut tokens: Vec<Token>) -> Result<(ast::Statement, Vec<To
         TokenType::While => {
             tokens.remove(0);
 
-            compare_token(tokens.remove(0), TokenType::OpenParenthesis).unwrap();
+            compare_token(&mut tokens, TokenType::OpenParenthesis)?;
             let (exp, mut toks) = parse_exp(tokens)?;
-            compare_token(toks.remove(0), TokenType::CloseParenthesis).unwrap();
+            compare_token(&mut toks, TokenType::CloseParenthesis)?;
             let (statement, toks) = parse_statement(toks)?;
 
             (
                 ast::Statement::While {
                     exp: exp,
                     statement: Box::new(statement),
+                    pos,
                 },
                 toks,
             )
@@ -412,40 +623,56 @@ pub fn parse_statement(mut tokens: Vec<Token>) -> Result<(ast::Statement, Vec<To
         TokenType::Do => {
             tokens.remove(0);
 
-            compare_token(tokens.remove(0), TokenType::OpenBrace).unwrap();
             let (statement, mut toks) = parse_statement(tokens)?;
-            compare_token(toks.remove(0), TokenType::CloseBrace).unwrap();
-            compare_token(toks.remove(0), TokenType::While).unwrap();
-            compare_token(toks.remove(0), TokenType::OpenParenthesis).unwrap();
+            compare_token(&mut toks, TokenType::While)?;
+            compare_token(&mut toks, TokenType::OpenParenthesis)?;
             let (exp, mut toks) = parse_exp(toks)?;
-            compare_token(toks.remove(0), TokenType::CloseParenthesis).unwrap();
-            compare_token(toks.remove(0), TokenType::Semicolon).unwrap();
+            compare_token(&mut toks, TokenType::CloseParenthesis)?;
+            compare_token(&mut toks, TokenType::Semicolon)?;
 
             (
                 ast::Statement::Do {
                     statement: Box::new(statement),
                     exp: exp,
+                    pos,
                 },
                 toks,
             )
         }
+        TokenType::Switch => {
+            tokens.remove(0);
+            compare_token(&mut tokens, TokenType::OpenParenthesis)?;
+            let (exp, mut tokens) = parse_exp(tokens)?;
+            compare_token(&mut tokens, TokenType::CloseParenthesis)?;
+            compare_token(&mut tokens, TokenType::OpenBrace)?;
+
+            let mut cases = Vec::new();
+            while !is_operators(&tokens, &[TokenType::CloseBrace]) {
+                let (case, toks) = parse_switch_case(tokens)?;
+                tokens = toks;
+                cases.push(case);
+            }
+            tokens.remove(0);
+
+            (ast::Statement::Switch { exp, cases, pos }, tokens)
+        }
         TokenType::Break => {
             tokens.remove(0);
-            compare_token(tokens.remove(0), TokenType::Semicolon)?;
+            compare_token(&mut tokens, TokenType::Semicolon)?;
 
-            (ast::Statement::Break, tokens)
+            (ast::Statement::Break { pos }, tokens)
         }
         TokenType::Continue => {
             tokens.remove(0);
-            compare_token(tokens.remove(0), TokenType::Semicolon)?;
+            compare_token(&mut tokens, TokenType::Semicolon)?;
 
-            (ast::Statement::Continue, tokens)
+            (ast::Statement::Continue { pos }, tokens)
         }
         TokenType::If => {
             tokens.remove(0);
-            compare_token(tokens.remove(0), TokenType::OpenParenthesis).unwrap();
+            compare_token(&mut tokens, TokenType::OpenParenthesis)?;
             let (exp, mut tokens) = parse_exp(tokens)?;
-            compare_token(tokens.remove(0), TokenType::CloseParenthesis).unwrap();
+            compare_token(&mut tokens, TokenType::CloseParenthesis)?;
 
             let (if_block, mut tokens) = parse_statement(tokens)?;
 
@@ -465,6 +692,7 @@ pub fn parse_statement(mut tokens: Vec<Token>) -> Result<(ast::Statement, Vec<To
                     cond_expr: exp,
                     if_block: Box::new(if_block),
                     else_block,
+                    pos,
                 },
                 tokens,
             )
@@ -473,7 +701,7 @@ pub fn parse_statement(mut tokens: Vec<Token>) -> Result<(ast::Statement, Vec<To
             tokens.remove(0);
 
             let mut list = Vec::new();
-            while tokens[0].token_type != TokenType::CloseBrace {
+            while !is_operators(&tokens, &[TokenType::CloseBrace]) {
                 let (exp, toks) = parse_block_item(tokens)?;
                 tokens = toks;
                 list.push(exp);
@@ -482,52 +710,189 @@ pub fn parse_statement(mut tokens: Vec<Token>) -> Result<(ast::Statement, Vec<To
 
             let list = if !list.is_empty() { Some(list) } else { None };
 
-            (ast::Statement::Compound { list: list }, tokens)
+            (ast::Statement::Compound { list: list, pos }, tokens)
         }
         _ => {
             let (exp, mut tokens) = parse_opt_exp(tokens)?;
-            compare_token(tokens.remove(0), TokenType::Semicolon).unwrap();
+            compare_token(&mut tokens, TokenType::Semicolon)?;
 
-            (ast::Statement::Exp { exp: exp }, tokens)
+            (ast::Statement::Exp { exp: exp, pos }, tokens)
         }
     };
 
     Ok((stat, tokens))
 }
 
-pub fn parse_decl(mut tokens: Vec<Token>) -> Result<(ast::Declaration, Vec<Token>)> {
-    match tokens.get(0) {
-        Some(tok) if tok.token_type == TokenType::Int => {
+/// parse_for_condition parses a controlling expression of `for`,
+/// which is a constant `1` at the position of `;` when it's omitted.
+fn parse_for_condition(tokens: Vec<Token>) -> Result<(ast::Exp, Vec<Token>)> {
+    let pos = match tokens.get(0) {
+        Some(tok) => tok.pos.clone(),
+        None => return Err(CompilerError::UnexpectedEof),
+    };
+    let (exp, tokens) = parse_opt_exp(tokens)?;
+    let exp = exp.unwrap_or(ast::Exp::Const(ast::Const::Int(1), pos));
+
+    Ok((exp, tokens))
+}
+
+/// parse_switch_case parses a `case` or `default` label
+/// and statements which follow it up to the next label.
+fn parse_switch_case(mut tokens: Vec<Token>) -> Result<(ast::SwitchCase, Vec<Token>)> {
+    let value = match tokens.get(0).map(|tok| tok.token_type) {
+        Some(TokenType::Case) => {
             tokens.remove(0);
-            let var = compare_token(tokens.remove(0), TokenType::Identifier)?;
-            let exp = match tokens.get(0) {
-                Some(tok) if tok.is_type(TokenType::Assignment) => {
-                    tokens.remove(0);
-                    let (exp, toks) = parse_exp(tokens)?;
-                    tokens = toks;
-                    Some(exp)
-                }
-                _ => None,
+            let pos = match tokens.get(0) {
+                Some(tok) => tok.pos.clone(),
+                None => return Err(CompilerError::UnexpectedEof),
             };
-            compare_token(tokens.remove(0), TokenType::Semicolon).unwrap();
+            let (exp, toks) = parse_conditional_expr(tokens)?;
+            tokens = toks;
 
-            Ok((
-                ast::Declaration::Declare {
-                    name: var.val.unwrap().to_owned(),
-                    exp: exp,
+            let value = match exp {
+                ast::Exp::Const(ast::Const::Int(value), _) => Some(value),
+                ast::Exp::UnOp(ast::UnOp::Negation, exp, _) => match *exp {
+                    ast::Exp::Const(ast::Const::Int(value), _) => Some(value.wrapping_neg()),
+                    _ => None,
                 },
-                tokens,
-            ))
+                _ => None,
+            };
+            match value {
+                Some(value) => Some(value),
+                None => return Err(CompilerError::NotConstant(pos)),
+            }
         }
-        _ => Err(CompilerError::ParsingError),
+        Some(TokenType::Default) => {
+            tokens.remove(0);
+            None
+        }
+        _ => return Err(unexpected(&tokens, &[TokenType::Case, TokenType::Default])),
+    };
+    compare_token(&mut tokens, TokenType::Colon)?;
+
+    let mut body = Vec::new();
+    while !is_operators(&tokens, &[TokenType::Case])
+        && !is_operators(&tokens, &[TokenType::Default])
+        && !is_operators(&tokens, &[TokenType::CloseBrace])
+    {
+        let (block, toks) = parse_block_item(tokens)?;
+        tokens = toks;
+        body.push(block);
     }
+
+    Ok((ast::SwitchCase { value, body }, tokens))
 }
 
-pub fn is_seem_decl(tokens: &[Token]) -> bool {
+pub fn parse_decl(mut tokens: Vec<Token>) -> Result<(ast::Declaration, Vec<Token>)> {
+    let ty = parse_type(&mut tokens, VAR_TYPES)?;
+    if is_operators(&tokens, &[TokenType::OpenParenthesis]) {
+        return parse_func_pointer_decl(tokens);
+    }
+
+    let var = compare_token(&mut tokens, TokenType::Identifier)?;
+    let (exp, tokens) = parse_initializer(tokens)?;
+
+    Ok((
+        ast::Declaration::Declare {
+            ty,
+            name: var.val.unwrap().to_owned(),
+            exp: exp,
+            pos: var.pos,
+        },
+        tokens,
+    ))
+}
+
+/// VAR_TYPES are type specifiers which a variable can be declared with.
+const VAR_TYPES: &[TokenType] = &[TokenType::Int, TokenType::Char, TokenType::Long];
+
+/// RETURN_TYPES are type specifiers which a function can return.
+const RETURN_TYPES: &[TokenType] = &[
+    TokenType::Int,
+    TokenType::Char,
+    TokenType::Long,
+    TokenType::Void,
+];
+
+/// parse_type takes off a type specifier if it's one of `types`.
+fn parse_type(tokens: &mut Vec<Token>, types: &[TokenType]) -> Result<ast::Type> {
+    if !is_seem_type(tokens, types) {
+        return Err(unexpected(tokens, types));
+    }
+
+    match tokens.remove(0).token_type {
+        TokenType::Int => Ok(ast::Type::Int),
+        TokenType::Char => Ok(ast::Type::Char),
+        TokenType::Long => Ok(ast::Type::Long),
+        TokenType::Void => Ok(ast::Type::Void),
+        tok => unreachable!("{} is not a type specifier", tok),
+    }
+}
+
+fn is_seem_type(tokens: &[Token], types: &[TokenType]) -> bool {
     match tokens.get(0) {
-        Some(tok) if tok.token_type == TokenType::Int => true,
-        _ => false,
+        Some(tok) => types.contains(&tok.token_type),
+        None => false,
+    }
+}
+
+/// parse_func_pointer_decl parses a declarator of a function pointer,
+/// `(*fp)(int, int)`, which follows a return type.
+fn parse_func_pointer_decl(mut tokens: Vec<Token>) -> Result<(ast::Declaration, Vec<Token>)> {
+    compare_token(&mut tokens, TokenType::OpenParenthesis)?;
+    compare_token(&mut tokens, TokenType::Multiplication)?;
+    let var = compare_token(&mut tokens, TokenType::Identifier)?;
+    compare_token(&mut tokens, TokenType::CloseParenthesis)?;
+
+    compare_token(&mut tokens, TokenType::OpenParenthesis)?;
+    let mut parameters = 0;
+    if !is_operators(&tokens, &[TokenType::CloseParenthesis]) {
+        loop {
+            compare_token(&mut tokens, TokenType::Int)?;
+            // names of parameters are optional here
+            if is_operators(&tokens, &[TokenType::Identifier]) {
+                tokens.remove(0);
+            }
+            parameters += 1;
+
+            if !is_operators(&tokens, &[TokenType::Comma]) {
+                break;
+            }
+            tokens.remove(0);
+        }
     }
+    compare_token(&mut tokens, TokenType::CloseParenthesis)?;
+
+    let (exp, tokens) = parse_initializer(tokens)?;
+
+    Ok((
+        ast::Declaration::FuncPointer {
+            name: var.val.unwrap().to_owned(),
+            parameters,
+            exp,
+            pos: var.pos,
+        },
+        tokens,
+    ))
+}
+
+fn parse_initializer(mut tokens: Vec<Token>) -> Result<(Option<ast::Exp>, Vec<Token>)> {
+    let exp = match tokens.get(0) {
+        Some(tok) if tok.is_type(TokenType::Assignment) => {
+            tokens.remove(0);
+            let (exp, toks) = parse_assignment_exp(tokens)?;
+            tokens = toks;
+            Some(exp)
+        }
+        _ => None,
+    };
+    compare_token(&mut tokens, TokenType::Semicolon)?;
+
+    Ok((exp, tokens))
+}
+
+pub fn is_seem_decl(tokens: &[Token]) -> bool {
+    is_seem_type(tokens, VAR_TYPES)
 }
 
 /// TODO: should we take off the parte with parse_decl?
@@ -547,29 +912,42 @@ pub fn parse_block_item(mut tokens: Vec<Token>) -> Result<(ast::BlockItem, Vec<T
 }
 
 pub fn parse_func(mut tokens: Vec<Token>) -> Result<(ast::FuncDecl, Vec<Token>)> {
-    compare_token(tokens.remove(0), TokenType::Int).unwrap();
-    let func_name = compare_token(tokens.remove(0), TokenType::Identifier).unwrap();
-    compare_token(tokens.remove(0), TokenType::OpenParenthesis).unwrap();
+    let return_type = parse_type(&mut tokens, RETURN_TYPES)?;
+    let func_name = compare_token(&mut tokens, TokenType::Identifier)?;
+    compare_token(&mut tokens, TokenType::OpenParenthesis)?;
 
-    // it can be simplified
     let mut params = Vec::new();
-    while tokens[0].token_type == TokenType::Int {
+    if is_operators(&tokens, &[TokenType::Void, TokenType::CloseParenthesis]) {
+        // `(void)` spells out an empty parameter list
         tokens.remove(0);
-        let param_name = compare_token(tokens.remove(0), TokenType::Identifier).unwrap();
-        params.push(param_name.val.unwrap());
-        if tokens[0].is_type(TokenType::Comma) {
+    } else if is_seem_decl(&tokens) {
+        loop {
+            let ty = parse_type(&mut tokens, VAR_TYPES)?;
+            let param_name = compare_token(&mut tokens, TokenType::Identifier)?;
+            params.push(ast::Parameter {
+                ty,
+                name: param_name.val.unwrap(),
+                pos: param_name.pos,
+            });
+
+            if !is_operators(&tokens, &[TokenType::Comma]) {
+                break;
+            }
+            // a comma must be followed by a parameter
             tokens.remove(0);
-        } else {
-            break;
         }
     }
-    compare_token(tokens.remove(0), TokenType::CloseParenthesis).unwrap();
+    compare_token(&mut tokens, TokenType::CloseParenthesis)?;
 
-    let blocks = match tokens.remove(0).token_type {
+    if tokens.is_empty() {
+        return Err(CompilerError::UnexpectedEof);
+    }
+    let token = tokens.remove(0);
+    let blocks = match token.token_type {
         TokenType::OpenBrace => {
             let mut blocks = Vec::new();
-            while tokens.get(0).unwrap().token_type != TokenType::CloseBrace {
-                let (block, toks) = parse_block_item(tokens).unwrap();
+            while !is_operators(&tokens, &[TokenType::CloseBrace]) {
+                let (block, toks) = parse_block_item(tokens)?;
                 blocks.push(block);
                 tokens = toks;
             }
@@ -578,11 +956,18 @@ pub fn parse_func(mut tokens: Vec<Token>) -> Result<(ast::FuncDecl, Vec<Token>)>
             Some(blocks)
         }
         TokenType::Semicolon => None,
-        _ => return Err(CompilerError::ParsingError),
+        found => {
+            return Err(CompilerError::UnexpectedToken {
+                found,
+                expected: vec![TokenType::OpenBrace, TokenType::Semicolon],
+                pos: token.pos,
+            })
+        }
     };
 
     Ok((
         ast::FuncDecl {
+            return_type,
             name: func_name.val.unwrap().clone(),
             parameters: params,
             blocks: blocks,
@@ -612,10 +997,745 @@ pub fn parse(mut tokens: Vec<Token>) -> Result<ast::Program> {
     Ok(ast::Program(functions))
 }
 
-fn compare_token(tok: Token, tok_type: TokenType) -> Result<Token> {
-    if tok.token_type == tok_type {
-        Ok(tok)
+/// compare_token takes off the first token if it has the expected type.
+fn compare_token(tokens: &mut Vec<Token>, tok_type: TokenType) -> Result<Token> {
+    if is_operators(tokens, &[tok_type]) {
+        Ok(tokens.remove(0))
     } else {
-        Err(CompilerError::ParsingError)
+        Err(unexpected(tokens, &[tok_type]))
+    }
+}
+
+/// unexpected builds an error for the first of tokens
+/// which is supposed to be one of `expected`.
+fn unexpected(tokens: &[Token], expected: &[TokenType]) -> CompilerError {
+    match tokens.get(0) {
+        Some(tok) => CompilerError::UnexpectedToken {
+            found: tok.token_type,
+            expected: expected.to_vec(),
+            pos: tok.pos.clone(),
+        },
+        None => CompilerError::UnexpectedEof,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lexer::Lexer;
+
+    fn parse_const(exp: &str) -> ast::Exp {
+        let tokens = Lexer::new().lex_str(exp).unwrap();
+        let (exp, _) = parse_exp(tokens).unwrap();
+        exp
+    }
+
+    #[test]
+    fn char_literal() {
+        assert!(matches!(parse_const("'A'"), ast::Exp::Const(ast::Const::Int(65), _)));
+        assert!(matches!(parse_const(r"'\n'"), ast::Exp::Const(ast::Const::Int(10), _)));
+        assert!(matches!(parse_const(r"'\t'"), ast::Exp::Const(ast::Const::Int(9), _)));
+        assert!(matches!(parse_const(r"'\0'"), ast::Exp::Const(ast::Const::Int(0), _)));
+        assert!(matches!(parse_const(r"'\''"), ast::Exp::Const(ast::Const::Int(39), _)));
+    }
+
+    #[test]
+    fn function_parameters() {
+        let parse_params = |program| {
+            let tokens = Lexer::new().lex_str(program).unwrap();
+            parse_func(tokens).map(|(func, _)| {
+                func.parameters
+                    .into_iter()
+                    .map(|p| p.name)
+                    .collect::<Vec<_>>()
+            })
+        };
+
+        assert_eq!(
+            parse_params("int add(int a, int b) { return a + b; }").unwrap(),
+            vec!["a".to_owned(), "b".to_owned()]
+        );
+        assert!(parse_params("int main() { return 0; }").unwrap().is_empty());
+        assert!(parse_params("int add(int a,) { return a; }").is_err());
+    }
+
+    #[test]
+    fn function_call() {
+        match parse_const("f()") {
+            ast::Exp::FuncCall(name, params, _) => {
+                assert_eq!(name, "f");
+                assert!(params.is_empty());
+            }
+            exp => panic!("unexpected expression {:?}", exp),
+        }
+
+        match parse_const("add(1, x)") {
+            ast::Exp::FuncCall(name, params, _) => {
+                assert_eq!(name, "add");
+                assert!(matches!(
+                    params.as_slice(),
+                    [ast::Exp::Const(ast::Const::Int(1), _), ast::Exp::Var(x, _)] if x == "x"
+                ));
+            }
+            exp => panic!("unexpected expression {:?}", exp),
+        }
+
+        match parse_const("f(g(x))") {
+            ast::Exp::FuncCall(name, params, _) => {
+                assert_eq!(name, "f");
+                assert!(matches!(
+                    params.as_slice(),
+                    [ast::Exp::FuncCall(g, args, _)]
+                        if g == "g" && matches!(args.as_slice(), [ast::Exp::Var(x, _)] if x == "x")
+                ));
+            }
+            exp => panic!("unexpected expression {:?}", exp),
+        }
+
+        let tokens = Lexer::new().lex_str("f(1").unwrap();
+        assert!(parse_exp(tokens).is_err());
+
+        let tokens = Lexer::new().lex_str("f(1,)").unwrap();
+        assert!(parse_exp(tokens).is_err());
+    }
+
+    #[test]
+    fn conditional_statement() {
+        let parse_stmt = |program| {
+            let tokens = Lexer::new().lex_str(program).unwrap();
+            let (stmt, _) = parse_statement(tokens).unwrap();
+            stmt
+        };
+
+        assert!(matches!(
+            parse_stmt("if (a) return 1;"),
+            ast::Statement::Conditional { else_block: None, .. }
+        ));
+        assert!(matches!(
+            parse_stmt("if (a) return 1; else return 2;"),
+            ast::Statement::Conditional { else_block: Some(..), .. }
+        ));
+
+        // the else belongs to the nearest if
+        match parse_stmt("if (a) if (b) return 1; else return 2;") {
+            ast::Statement::Conditional {
+                if_block,
+                else_block: None,
+                ..
+            } => assert!(matches!(
+                *if_block,
+                ast::Statement::Conditional { else_block: Some(..), .. }
+            )),
+            _ => panic!("unexpected statement"),
+        }
+
+        match parse_stmt("if (a) return 1; else if (b) return 2; else return 3;") {
+            ast::Statement::Conditional {
+                else_block: Some(else_block),
+                ..
+            } => assert!(matches!(
+                *else_block,
+                ast::Statement::Conditional { else_block: Some(..), .. }
+            )),
+            _ => panic!("unexpected statement"),
+        }
+    }
+
+    #[test]
+    fn loop_statements() {
+        let parse_stmt = |program| {
+            let tokens = Lexer::new().lex_str(program).unwrap();
+            parse_statement(tokens).map(|(stmt, tokens)| (stmt, tokens.len()))
+        };
+
+        assert!(matches!(
+            parse_stmt("while (i < 10) i = i + 1;"),
+            Ok((ast::Statement::While { .. }, 0))
+        ));
+        assert!(matches!(
+            parse_stmt("do { i = i + 1; } while (i < 10);"),
+            Ok((ast::Statement::Do { .. }, 0))
+        ));
+        assert!(matches!(
+            parse_stmt("do i = i + 1; while (i < 10);"),
+            Ok((ast::Statement::Do { .. }, 0))
+        ));
+        assert!(parse_stmt("do { i = i + 1; } while (i < 10) return i;").is_err());
+    }
+
+    #[test]
+    fn for_statement() {
+        let parse_stmt = |program| {
+            let tokens = Lexer::new().lex_str(program).unwrap();
+            parse_statement(tokens).map(|(stmt, tokens)| (stmt, tokens.len()))
+        };
+
+        assert!(matches!(
+            parse_stmt("for (int i = 0; i < 10; i = i + 1) sum = sum + i;"),
+            Ok((ast::Statement::ForDecl { exp3: Some(..), .. }, 0))
+        ));
+        assert!(matches!(
+            parse_stmt("for (i = 0; i < 10; i = i + 1) sum = sum + i;"),
+            Ok((ast::Statement::For { exp1: Some(..), exp3: Some(..), .. }, 0))
+        ));
+        assert!(matches!(
+            parse_stmt("for (;;) break;"),
+            Ok((
+                ast::Statement::For {
+                    exp1: None,
+                    exp2: ast::Exp::Const(ast::Const::Int(1), _),
+                    exp3: None,
+                    ..
+                },
+                0
+            ))
+        ));
+        assert!(parse_stmt("for (i = 0; i < 10) break;").is_err());
+    }
+
+    #[test]
+    fn func_pointer_decl() {
+        let parse = |program| {
+            let tokens = Lexer::new().lex_str(program).unwrap();
+            parse_decl(tokens).map(|(decl, _)| decl)
+        };
+
+        assert!(matches!(
+            parse("int (*fp)(int, int b);"),
+            Ok(ast::Declaration::FuncPointer { name, parameters: 2, exp: None, .. }) if name == "fp"
+        ));
+        assert!(matches!(
+            parse("int (*fp)() = &f;"),
+            Ok(ast::Declaration::FuncPointer {
+                parameters: 0,
+                exp: Some(ast::Exp::AddressOf(f, _)),
+                ..
+            }) if f == "f"
+        ));
+        assert!(parse("int (fp)(int);").is_err());
+        assert!(parse("int (*fp)(int,);").is_err());
+    }
+
+    #[test]
+    fn nested_assignment() {
+        match parse_const("a = b + (c = 5)") {
+            ast::Exp::Assign(a, exp, _) => {
+                assert_eq!(a, "a");
+                assert!(matches!(
+                    *exp,
+                    ast::Exp::BinOp(ast::BinOp::Addition, ref b, ref c, _)
+                        if matches!(**b, ast::Exp::Var(ref b, _) if b == "b")
+                            && matches!(**c, ast::Exp::Assign(ref c, _, _) if c == "c")
+                ));
+            }
+            exp => panic!("unexpected expression {:?}", exp),
+        }
+
+        let tokens = Lexer::new().lex_str("a = 1, b = 2;").unwrap();
+        assert!(matches!(
+            parse_statement(tokens),
+            Ok((ast::Statement::Exp { exp: Some(ast::Exp::Comma(..)), .. }, _))
+        ));
+
+        let tokens = Lexer::new()
+            .lex_str("for (i = 0, j = 10; i < j; i = i + 1, j = j - 1) ;")
+            .unwrap();
+        match parse_statement(tokens) {
+            Ok((ast::Statement::For { exp1, exp3, .. }, _)) => {
+                assert!(matches!(exp1, Some(ast::Exp::Comma(..))));
+                assert!(matches!(exp3, Some(ast::Exp::Comma(..))));
+            }
+            _ => panic!("unexpected statement"),
+        }
+    }
+
+    #[test]
+    fn chained_assignment() {
+        match parse_const("a = b = 3") {
+            ast::Exp::Assign(a, exp, _) => {
+                assert_eq!(a, "a");
+                assert!(matches!(
+                    *exp,
+                    ast::Exp::Assign(ref b, ref c, _)
+                        if b == "b" && matches!(**c, ast::Exp::Const(ast::Const::Int(3), _))
+                ));
+            }
+            exp => panic!("unexpected expression {:?}", exp),
+        }
+
+        assert!(matches!(
+            parse_const("a += b = 3"),
+            ast::Exp::AssignOp(_, ast::AssignmentOp::Plus, ref exp, _)
+                if matches!(**exp, ast::Exp::Assign(..))
+        ));
+    }
+
+    #[test]
+    fn logical_operators() {
+        assert!(matches!(
+            parse_const("a || b && c"),
+            ast::Exp::BinOp(ast::BinOp::Or, ref a, ref bc, _)
+                if matches!(**a, ast::Exp::Var(..))
+                    && matches!(**bc, ast::Exp::BinOp(ast::BinOp::And, ..))
+        ));
+
+        assert!(matches!(
+            parse_const("a || b || c"),
+            ast::Exp::BinOp(ast::BinOp::Or, ref ab, ref c, _)
+                if matches!(**ab, ast::Exp::BinOp(ast::BinOp::Or, ..))
+                    && matches!(**c, ast::Exp::Var(..))
+        ));
+
+        // an unary operator is applied only to its operand
+        assert!(matches!(
+            parse_const("!a || b"),
+            ast::Exp::BinOp(ast::BinOp::Or, ref a, _, _)
+                if matches!(**a, ast::Exp::UnOp(ast::UnOp::LogicalNegation, _, _))
+        ));
+    }
+
+    #[test]
+    fn bitwise_precedence() {
+        assert!(matches!(
+            parse_const("1 | 2 ^ 3 & 4"),
+            ast::Exp::BinOp(ast::BinOp::BitwiseOr, ref a, ref b, _)
+                if matches!(**a, ast::Exp::Const(ast::Const::Int(1), _))
+                    && matches!(**b, ast::Exp::BinOp(ast::BinOp::BitwiseXor, ref c, ref d, _)
+                        if matches!(**c, ast::Exp::Const(ast::Const::Int(2), _))
+                            && matches!(**d, ast::Exp::BinOp(ast::BinOp::BitwiseAnd, ..)))
+        ));
+
+        assert!(matches!(
+            parse_const("1 + 2 << 3"),
+            ast::Exp::BinOp(ast::BinOp::BitwiseLeftShift, ref a, ref b, _)
+                if matches!(**a, ast::Exp::BinOp(ast::BinOp::Addition, ..))
+                    && matches!(**b, ast::Exp::Const(ast::Const::Int(3), _))
+        ));
+
+        assert!(matches!(
+            parse_const("a & 1 == 1"),
+            ast::Exp::BinOp(ast::BinOp::BitwiseAnd, _, ref b, _)
+                if matches!(**b, ast::Exp::BinOp(ast::BinOp::Equal, ..))
+        ));
+    }
+
+    #[test]
+    fn parenthesized_assignment() {
+        assert!(matches!(
+            parse_const("(a = 5) + 1"),
+            ast::Exp::BinOp(ast::BinOp::Addition, ref a, ref b, _)
+                if matches!(**a, ast::Exp::Assign(ref name, _, _) if name == "a")
+                    && matches!(**b, ast::Exp::Const(ast::Const::Int(1), _))
+        ));
+    }
+
+    #[test]
+    fn comma_operator() {
+        assert!(matches!(
+            parse_const("(a, 42)"),
+            ast::Exp::Comma(ref a, ref c, _)
+                if matches!(**a, ast::Exp::Var(..))
+                    && matches!(**c, ast::Exp::Const(ast::Const::Int(42), _))
+        ));
+
+        match parse_const("f((a, b), c)") {
+            ast::Exp::FuncCall(_, params, _) => {
+                assert!(matches!(params.as_slice(), [ast::Exp::Comma(..), ast::Exp::Var(..)]))
+            }
+            exp => panic!("unexpected expression {:?}", exp),
+        }
+    }
+
+    #[test]
+    fn conditional_expression() {
+        assert!(matches!(
+            parse_const("a ? b : c"),
+            ast::Exp::CondExp(ref a, ref b, ref c, _)
+                if matches!(**a, ast::Exp::Var(ref a, _) if a == "a")
+                    && matches!(**b, ast::Exp::Var(ref b, _) if b == "b")
+                    && matches!(**c, ast::Exp::Var(ref c, _) if c == "c")
+        ));
+
+        // the else branch is associated to the right
+        assert!(matches!(
+            parse_const("a ? b : c ? d : e"),
+            ast::Exp::CondExp(_, ref b, ref c, _)
+                if matches!(**b, ast::Exp::Var(..)) && matches!(**c, ast::Exp::CondExp(..))
+        ));
+
+        assert!(matches!(
+            parse_const("a ? b ? 1 : 2 : 3"),
+            ast::Exp::CondExp(_, ref b, ref c, _)
+                if matches!(**b, ast::Exp::CondExp(..))
+                    && matches!(**c, ast::Exp::Const(ast::Const::Int(3), _))
+        ));
+
+        let tokens = Lexer::new().lex_str("a ? b").unwrap();
+        assert!(parse_exp(tokens).is_err());
+    }
+
+    #[test]
+    fn top_level_items() {
+        let program = r"
+            int foo(int a);
+            int counter = 0;
+            int foo(int a) { return a; }
+            int main() { return foo(1); }
+        ";
+        let tokens = Lexer::new().lex_str(program).unwrap();
+        let prog = parse(tokens).unwrap();
+
+        assert_eq!(prog.0.len(), 4);
+        assert!(matches!(&prog.0[0], ast::TopLevel::Function(f) if f.name == "foo" && f.blocks.is_none()));
+        assert!(matches!(&prog.0[1], ast::TopLevel::Declaration(..)));
+        assert!(matches!(&prog.0[2], ast::TopLevel::Function(f) if f.name == "foo" && f.blocks.is_some()));
+        assert!(matches!(&prog.0[3], ast::TopLevel::Function(f) if f.name == "main"));
+    }
+
+    #[test]
+    fn negative_literals() {
+        assert!(matches!(
+            parse_const("-2147483648"),
+            ast::Exp::UnOp(ast::UnOp::Negation, ref c, _)
+                if matches!(**c, ast::Exp::Const(ast::Const::Int(2147483648), _))
+        ));
+        assert!(matches!(
+            parse_const("-9223372036854775808L"),
+            ast::Exp::UnOp(ast::UnOp::Negation, ref c, _)
+                if matches!(**c, ast::Exp::Const(ast::Const::Int(std::i64::MIN), _))
+        ));
+    }
+
+    #[test]
+    fn digit_separators() {
+        assert!(matches!(parse_const("1_000"), ast::Exp::Const(ast::Const::Int(1000), _)));
+        assert!(matches!(parse_const("0x1_0"), ast::Exp::Const(ast::Const::Int(16), _)));
+        assert!(matches!(parse_const("0b1_1"), ast::Exp::Const(ast::Const::Int(3), _)));
+    }
+
+    #[test]
+    fn integer_overflow() {
+        let tokens = Lexer::new().lex_str("1 + 99999999999999999999").unwrap();
+        let err = parse_exp(tokens).unwrap_err();
+
+        assert!(matches!(
+            err,
+            CompilerError::IntegerOverflow(Pos {
+                start: 4,
+                end: 24,
+                line: 1,
+                column: 5,
+            })
+        ));
+        assert_eq!(err.to_string(), "integer literal is too large at 1:5");
+
+        for literal in &["9223372036854775808", "0xffffffffffffffff", "-9223372036854775809"] {
+            let tokens = Lexer::new().lex_str(literal).unwrap();
+            assert!(matches!(parse_exp(tokens), Err(CompilerError::IntegerOverflow(..))));
+        }
+    }
+
+    #[test]
+    fn missing_semicolon() {
+        let program = "int main() {\n    int a = 1\n    return a;\n}";
+        let tokens = Lexer::new().lex_str(program).unwrap();
+        let err = parse(tokens).err().unwrap();
+
+        assert!(matches!(
+            err,
+            CompilerError::UnexpectedToken {
+                found: TokenType::Return,
+                ref expected,
+                pos: Pos { line: 3, column: 5, .. },
+            } if expected == &[TokenType::Semicolon]
+        ));
+        assert_eq!(err.to_string(), "expected `;` but found `return` at 3:5");
+
+        let tokens = Lexer::new().lex_str("int main() { return 1 }").unwrap();
+        assert_eq!(
+            parse(tokens).err().unwrap().to_string(),
+            "expected `;` but found `}` at 1:23"
+        );
+    }
+
+    #[test]
+    fn truncated_input() {
+        let parse_program = |program| parse(Lexer::new().lex_str(program).unwrap());
+
+        assert!(parse_program("").unwrap().0.is_empty());
+        assert!(matches!(parse_program("int"), Err(CompilerError::UnexpectedEof)));
+        assert!(matches!(parse_program("int main("), Err(CompilerError::UnexpectedEof)));
+        assert!(matches!(parse_program("int main()"), Err(CompilerError::UnexpectedEof)));
+        assert!(matches!(
+            parse_program("int main() { return 1 +"),
+            Err(CompilerError::UnexpectedEof)
+        ));
+        assert!(matches!(parse_program("int main() {"), Err(CompilerError::UnexpectedEof)));
+        assert!(matches!(parse_exp(Vec::new()), Err(CompilerError::UnexpectedEof)));
+    }
+
+    #[test]
+    fn nested_syntax_error() {
+        let parse_expr = |exp| parse_exp(Lexer::new().lex_str(exp).unwrap());
+
+        assert!(matches!(
+            parse_expr("(1 + )"),
+            Err(CompilerError::UnexpectedToken {
+                found: TokenType::CloseParenthesis,
+                ..
+            })
+        ));
+        assert!(parse_expr("-(1 + )").is_err());
+        assert!(parse_expr("!(a = )").is_err());
+        assert!(parse_expr("f((1 + ))").is_err());
+    }
+
+    #[test]
+    fn prefix_inc_dec() {
+        assert!(matches!(
+            parse_const("++x"),
+            ast::Exp::IncOrDec(ref x, ast::IncOrDec::Inc(ast::OperationSide::Prefix), _) if x == "x"
+        ));
+        assert!(matches!(
+            parse_const("--x"),
+            ast::Exp::IncOrDec(ref x, ast::IncOrDec::Dec(ast::OperationSide::Prefix), _) if x == "x"
+        ));
+
+        // an operand has to be a variable
+        let tokens = Lexer::new().lex_str("++5").unwrap();
+        assert!(matches!(
+            parse_exp(tokens),
+            Err(CompilerError::UnexpectedToken {
+                found: TokenType::IntegerLiteral,
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn postfix_inc_dec() {
+        assert!(matches!(
+            parse_const("x++"),
+            ast::Exp::IncOrDec(ref x, ast::IncOrDec::Inc(ast::OperationSide::Postfix), _) if x == "x"
+        ));
+        assert!(matches!(
+            parse_const("x--"),
+            ast::Exp::IncOrDec(ref x, ast::IncOrDec::Dec(ast::OperationSide::Postfix), _) if x == "x"
+        ));
+        assert!(matches!(
+            parse_const("-x++"),
+            ast::Exp::UnOp(ast::UnOp::Negation, ref x, _) if matches!(**x, ast::Exp::IncOrDec(..))
+        ));
+
+        let parse_expr = |exp| parse_exp(Lexer::new().lex_str(exp).unwrap());
+        assert!(matches!(
+            parse_expr("5++"),
+            Err(CompilerError::NotAssignable(Pos { column: 2, .. }))
+        ));
+        assert!(matches!(parse_expr("x++--"), Err(CompilerError::NotAssignable(..))));
+        assert!(matches!(parse_expr("(x + 1)--"), Err(CompilerError::NotAssignable(..))));
+        assert!(matches!(parse_expr("++(x + 1)"), Err(CompilerError::NotAssignable(..))));
+        assert!(matches!(parse_expr("(x)++++"), Err(CompilerError::NotAssignable(..))));
+
+        // a parenthesized variable is still assignable
+        assert!(matches!(
+            parse_const("(x)++"),
+            ast::Exp::IncOrDec(ref x, ast::IncOrDec::Inc(ast::OperationSide::Postfix), _) if x == "x"
+        ));
+        assert!(matches!(
+            parse_const("((x))--"),
+            ast::Exp::IncOrDec(ref x, ast::IncOrDec::Dec(ast::OperationSide::Postfix), _) if x == "x"
+        ));
+        assert!(matches!(
+            parse_const("++(x)"),
+            ast::Exp::IncOrDec(ref x, ast::IncOrDec::Inc(ast::OperationSide::Prefix), _) if x == "x"
+        ));
+    }
+
+    #[test]
+    fn switch_statement() {
+        let parse_stmt = |program| {
+            let tokens = Lexer::new().lex_str(program).unwrap();
+            parse_statement(tokens)
+        };
+
+        match parse_stmt("switch (a) { case 1: b = 1; break; case -2: default: b = 2; }") {
+            Ok((ast::Statement::Switch { exp, cases, .. }, _)) => {
+                assert!(matches!(exp, ast::Exp::Var(ref a, _) if a == "a"));
+                let labels = cases.iter().map(|case| case.value).collect::<Vec<_>>();
+                assert_eq!(labels, vec![Some(1), Some(-2), None]);
+                assert_eq!(cases[0].body.len(), 2);
+                assert!(cases[1].body.is_empty());
+                assert_eq!(cases[2].body.len(), 1);
+            }
+            _ => panic!("unexpected statement"),
+        }
+
+        assert!(matches!(
+            parse_stmt("switch (a) { case b: break; }"),
+            Err(CompilerError::NotConstant(..))
+        ));
+        assert!(parse_stmt("switch (a) { b = 1; }").is_err());
+    }
+
+    #[test]
+    fn empty_statement() {
+        let tokens = Lexer::new().lex_str("{ ;; }").unwrap();
+        match parse_statement(tokens) {
+            Ok((
+                ast::Statement::Compound {
+                    list: Some(list), ..
+                },
+                tokens,
+            )) => {
+                assert!(tokens.is_empty());
+                assert_eq!(list.len(), 2);
+                for item in list {
+                    assert!(matches!(
+                        item,
+                        ast::BlockItem::Statement(ast::Statement::Exp { exp: None, .. })
+                    ));
+                }
+            }
+            _ => panic!("unexpected statement"),
+        }
+    }
+
+    #[test]
+    fn trailing_tokens() {
+        let tokens = Lexer::new().lex_str("int main(){return 0;} )").unwrap();
+        let err = parse(tokens).err().unwrap();
+
+        assert!(matches!(
+            err,
+            CompilerError::UnexpectedToken {
+                found: TokenType::CloseParenthesis,
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn void_parameters() {
+        let tokens = Lexer::new().lex_str("int main(void) { return 0; }").unwrap();
+        let (func, tokens) = parse_func(tokens).unwrap();
+        assert!(tokens.is_empty());
+        assert!(func.parameters.is_empty());
+        assert!(func.blocks.is_some());
+
+        let tokens = Lexer::new().lex_str("int f(void);").unwrap();
+        assert!(parse_func(tokens).unwrap().0.parameters.is_empty());
+
+        for program in &["int f(int void) {}", "int f(void a) {}", "int f(void, int a) {}"] {
+            let tokens = Lexer::new().lex_str(program).unwrap();
+            assert!(matches!(
+                parse_func(tokens),
+                Err(CompilerError::UnexpectedToken { .. })
+            ));
+        }
+    }
+
+    #[test]
+    fn name_positions() {
+        let tokens = Lexer::new().lex_str("int a = 1;\nreturn a;").unwrap();
+        let decl_pos = tokens[1].pos.clone();
+        let var_pos = tokens[6].pos.clone();
+        assert_eq!((var_pos.line, var_pos.column), (2, 8));
+
+        let (decl, tokens) = parse_decl(tokens).unwrap();
+        assert!(matches!(decl, ast::Declaration::Declare { pos, .. } if pos == decl_pos));
+
+        assert!(matches!(
+            parse_statement(tokens),
+            Ok((ast::Statement::Return { exp: ast::Exp::Var(_, pos), .. }, _)) if pos == var_pos
+        ));
+    }
+
+    #[test]
+    fn statement_positions() {
+        let tokens = Lexer::new().lex_str("while (x) x = -x + 1;").unwrap();
+        let (st, _) = parse_statement(tokens).unwrap();
+        assert_eq!(st.pos().column, 1);
+
+        match st {
+            ast::Statement::While { exp, statement, .. } => {
+                assert_eq!(exp.pos().column, 8);
+                assert_eq!(statement.pos().column, 11);
+                match *statement {
+                    ast::Statement::Exp {
+                        exp: Some(ast::Exp::Assign(_, rhs, pos)),
+                        ..
+                    } => {
+                        assert_eq!(pos.column, 11);
+                        assert_eq!(rhs.pos().column, 18);
+                        assert!(matches!(
+                            *rhs,
+                            ast::Exp::BinOp(_, ref neg, ref one, _)
+                                if neg.pos().column == 15 && one.pos().column == 20
+                        ));
+                    }
+                    st => panic!("unexpected statement {:?}", st),
+                }
+            }
+            st => panic!("unexpected statement {:?}", st),
+        }
+    }
+
+    #[test]
+    fn type_specifiers() {
+        let tokens = Lexer::new().lex_str("char c = 'x';").unwrap();
+        match parse_decl(tokens) {
+            Ok((ast::Declaration::Declare { ty, name, exp, .. }, _)) => {
+                assert_eq!(ty, ast::Type::Char);
+                assert_eq!(name, "c");
+                assert!(matches!(
+                    exp,
+                    Some(ast::Exp::Const(ast::Const::Int(120), _))
+                ));
+            }
+            _ => panic!("unexpected declaration"),
+        }
+
+        let tokens = Lexer::new().lex_str("void f(long a, char b);").unwrap();
+        let (func, _) = parse_func(tokens).unwrap();
+        assert_eq!(func.return_type, ast::Type::Void);
+        let types = func.parameters.iter().map(|p| p.ty).collect::<Vec<_>>();
+        assert_eq!(types, vec![ast::Type::Long, ast::Type::Char]);
+
+        let tokens = Lexer::new().lex_str("long f() { return 0; }").unwrap();
+        assert_eq!(parse_func(tokens).unwrap().0.return_type, ast::Type::Long);
+
+        let tokens = Lexer::new().lex_str("void a;").unwrap();
+        assert!(matches!(
+            parse_decl(tokens),
+            Err(CompilerError::UnexpectedToken { found: TokenType::Void, .. })
+        ));
+    }
+
+    #[test]
+    fn expression_tree() {
+        let pos = |start, len| Pos {
+            start,
+            end: start + len,
+            line: 1,
+            column: start + 1,
+        };
+        let var =
+            |name: &str, start| Box::new(ast::Exp::Var(name.to_owned(), pos(start, name.len())));
+
+        assert_eq!(
+            parse_const("a + b * c"),
+            ast::Exp::BinOp(
+                ast::BinOp::Addition,
+                var("a", 0),
+                Box::new(ast::Exp::BinOp(
+                    ast::BinOp::Multiplication,
+                    var("b", 4),
+                    var("c", 8),
+                    pos(6, 1),
+                )),
+                pos(2, 1),
+            )
+        );
     }
 }
@@ -0,0 +1,3 @@
+mod preprocessor;
+
+pub use preprocessor::*;
@@ -0,0 +1,457 @@
+use std::collections::HashMap;
+use std::error;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// Source is a text of a file where its `#include` directives
+/// are substituted by the contents of the included files
+/// and macros defined by `#define` are expanded.
+#[derive(Debug)]
+pub struct Source {
+    pub code: String,
+    /// files are the file itself and every file it includes transitively,
+    /// each file is listed once in the order it's met.
+    pub files: Vec<PathBuf>,
+    /// lines maps each line of the code to the index of a file in `files`
+    /// and the number of the line in that file.
+    pub lines: Vec<(usize, usize)>,
+}
+
+impl Source {
+    /// deps renders a Makefile rule which makes the target depend on the files.
+    pub fn deps(&self, target: &Path) -> String {
+        let files = self
+            .files
+            .iter()
+            .map(|file| file.display().to_string())
+            .collect::<Vec<_>>();
+
+        format!("{}: {}", target.display(), files.join(" "))
+    }
+
+    /// locate finds the file and the line in it which the line of the code comes from.
+    /// Lines are numbered from 1.
+    pub fn locate(&self, line: usize) -> Option<(&Path, usize)> {
+        let (file, line) = *self.lines.get(line.checked_sub(1)?)?;
+        Some((&self.files[file], line))
+    }
+}
+
+#[derive(Debug)]
+pub enum PreprocessError {
+    /// Io is raised when a file can't be read.
+    Io { path: PathBuf, err: io::Error },
+    /// InvalidInclude is raised when `#include` isn't followed by a quoted file name,
+    /// system headers such as `<stdio.h>` aren't supported.
+    InvalidInclude { path: PathBuf, line: usize },
+    /// RecursiveInclude is raised when a file includes itself, directly or not.
+    RecursiveInclude(PathBuf),
+    /// InvalidDefine is raised when `#define` isn't followed by a macro name,
+    /// function-like macros such as `#define SQ(x)` aren't supported.
+    InvalidDefine { path: PathBuf, line: usize },
+    /// MacroRecursion is raised when an expansion of a macro is nested deeper
+    /// than MAX_EXPANSION_DEPTH.
+    ///
+    /// A macro which refers to itself, directly or not, isn't an error,
+    /// the reference is left unexpanded like the C preprocessor does.
+    MacroRecursion {
+        name: String,
+        path: PathBuf,
+        line: usize,
+    },
+    /// UnknownDirective is raised on a directive which isn't supported.
+    UnknownDirective {
+        directive: String,
+        path: PathBuf,
+        line: usize,
+    },
+}
+
+impl fmt::Display for PreprocessError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            PreprocessError::Io { path, err } => write!(f, "{}: {}", path.display(), err),
+            PreprocessError::InvalidInclude { path, line } => write!(
+                f,
+                "{}:{}: #include expects \"FILENAME\"",
+                path.display(),
+                line
+            ),
+            PreprocessError::RecursiveInclude(path) => {
+                write!(f, "{}: file includes itself", path.display())
+            }
+            PreprocessError::InvalidDefine { path, line } => write!(
+                f,
+                "{}:{}: #define expects a macro name",
+                path.display(),
+                line
+            ),
+            PreprocessError::MacroRecursion { name, path, line } => write!(
+                f,
+                "{}:{}: macro {:?} is nested too deeply",
+                path.display(),
+                line,
+                name
+            ),
+            PreprocessError::UnknownDirective {
+                directive,
+                path,
+                line,
+            } => write!(
+                f,
+                "{}:{}: invalid preprocessing directive #{}",
+                path.display(),
+                line,
+                directive
+            ),
+        }
+    }
+}
+
+impl error::Error for PreprocessError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            PreprocessError::Io { err, .. } => Some(err),
+            _ => None,
+        }
+    }
+}
+
+/// MAX_EXPANSION_DEPTH limits how many macros may be expanded one within another.
+const MAX_EXPANSION_DEPTH: usize = 64;
+
+/// preprocess reads the file and substitutes its `#include "file"` directives
+/// by the contents of the files, which are looked up next to the including file.
+/// Object-like macros, `#define NAME replacement`, are expanded in the lines below their definitions.
+pub fn preprocess(path: &Path) -> Result<Source, PreprocessError> {
+    let code = read(path)?;
+    preprocess_str(&code, path)
+}
+
+/// preprocess_str is the same as preprocess but works with a text which is already in memory,
+/// `path` is used to look up the included files.
+pub fn preprocess_str(code: &str, path: &Path) -> Result<Source, PreprocessError> {
+    let mut p = Preprocessor {
+        files: Vec::new(),
+        lines: Vec::new(),
+        includes: Vec::new(),
+        macros: HashMap::new(),
+    };
+    let code = p.file(code, path)?;
+
+    Ok(Source {
+        code,
+        files: p.files,
+        lines: p.lines,
+    })
+}
+
+struct Preprocessor {
+    files: Vec<PathBuf>,
+    lines: Vec<(usize, usize)>,
+    // includes is a stack of files which are being processed
+    // and it's used to find a recursive include.
+    includes: Vec<PathBuf>,
+    macros: HashMap<String, String>,
+}
+
+impl Preprocessor {
+    fn file(&mut self, code: &str, path: &Path) -> Result<String, PreprocessError> {
+        let id = fs::canonicalize(path).unwrap_or_else(|_| path.to_owned());
+        if self.includes.contains(&id) {
+            return Err(PreprocessError::RecursiveInclude(path.to_owned()));
+        }
+        self.includes.push(id);
+        let file = match self.files.iter().position(|file| file == path) {
+            Some(file) => file,
+            None => {
+                self.files.push(path.to_owned());
+                self.files.len() - 1
+            }
+        };
+
+        let mut out = String::with_capacity(code.len());
+        for (index, line) in code.split_inclusive('\n').enumerate() {
+            let directive = match line.trim_start().strip_prefix('#') {
+                Some(directive) => directive.trim(),
+                None => {
+                    self.lines.push((file, index + 1));
+                    let mut expanding = Vec::new();
+                    out += &self.expand(line, &mut expanding).map_err(|name| {
+                        PreprocessError::MacroRecursion {
+                            name,
+                            path: path.to_owned(),
+                            line: index + 1,
+                        }
+                    })?;
+                    continue;
+                }
+            };
+
+            let name = directive
+                .split(|c: char| !c.is_alphanumeric())
+                .next()
+                .unwrap_or_default();
+            match name {
+                "include" => {
+                    let file = directive["include".len()..].trim();
+                    let file = file
+                        .strip_prefix('"')
+                        .and_then(|file| file.strip_suffix('"'))
+                        .filter(|file| !file.is_empty())
+                        .ok_or_else(|| PreprocessError::InvalidInclude {
+                            path: path.to_owned(),
+                            line: index + 1,
+                        })?;
+
+                    let included = path.parent().unwrap_or(Path::new("")).join(file);
+                    let code = read(&included)?;
+                    // the included lines are already in the line map
+                    // so the directive's line gets no entry
+                    out += &self.file(&code, &included)?;
+                    if !out.is_empty() && !out.ends_with('\n') {
+                        out.push('\n');
+                    }
+                }
+                "define" => {
+                    let definition = directive["define".len()..].trim_start();
+                    let name_len = definition
+                        .find(|c: char| !is_ident_char(c))
+                        .unwrap_or_else(|| definition.len());
+                    let (name, replacement) = definition.split_at(name_len);
+                    let is_name = name.chars().next().map_or(false, |c| !c.is_ascii_digit());
+                    if !is_name || replacement.starts_with('(') {
+                        return Err(PreprocessError::InvalidDefine {
+                            path: path.to_owned(),
+                            line: index + 1,
+                        });
+                    }
+
+                    self.macros
+                        .insert(name.to_owned(), replacement.trim().to_owned());
+                    self.lines.push((file, index + 1));
+                    out.push('\n');
+                }
+                // a null directive
+                "" => {
+                    self.lines.push((file, index + 1));
+                    out.push('\n');
+                }
+                _ => {
+                    return Err(PreprocessError::UnknownDirective {
+                        directive: name.to_owned(),
+                        path: path.to_owned(),
+                        line: index + 1,
+                    })
+                }
+            }
+        }
+
+        self.includes.pop();
+        Ok(out)
+    }
+
+    /// expand replaces the macros' names in the text by their replacements.
+    /// A replacement is expanded as well, so `expanding` holds the names of macros
+    /// which are being expanded. A name of one of them is kept as it is,
+    /// and the name of a macro nested too deeply is returned.
+    fn expand(&self, text: &str, expanding: &mut Vec<String>) -> Result<String, String> {
+        let mut out = String::with_capacity(text.len());
+        let mut chars = text.char_indices().peekable();
+        while let Some((start, c)) = chars.next() {
+            if c == '"' || c == '\'' {
+                // literals are copied as they are
+                out.push(c);
+                while let Some((_, next)) = chars.next() {
+                    out.push(next);
+                    if next == '\\' {
+                        out.extend(chars.next().map(|(_, escaped)| escaped));
+                    } else if next == c {
+                        break;
+                    }
+                }
+                continue;
+            }
+
+            if !is_ident_char(c) {
+                out.push(c);
+                continue;
+            }
+
+            let mut end = start + c.len_utf8();
+            while let Some((i, next)) = chars.peek().copied() {
+                if !is_ident_char(next) {
+                    break;
+                }
+                end = i + next.len_utf8();
+                chars.next();
+            }
+
+            let word = &text[start..end];
+            match self.macros.get(word) {
+                Some(_) if expanding.iter().any(|name| name == word) => out.push_str(word),
+                Some(replacement) => {
+                    if expanding.len() == MAX_EXPANSION_DEPTH {
+                        return Err(word.to_owned());
+                    }
+
+                    expanding.push(word.to_owned());
+                    out += &self.expand(replacement, expanding)?;
+                    expanding.pop();
+                }
+                _ => out.push_str(word),
+            }
+        }
+
+        Ok(out)
+    }
+}
+
+fn is_ident_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || c == '_'
+}
+
+fn read(path: &Path) -> Result<String, PreprocessError> {
+    fs::read_to_string(path).map_err(|err| PreprocessError::Io {
+        path: path.to_owned(),
+        err,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// temp_dir creates an empty directory for the test's files.
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("scc_{}_{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn include() {
+        let dir = temp_dir("include");
+        fs::write(dir.join("a.h"), "int a();\n").unwrap();
+        fs::write(dir.join("b.h"), "#include \"a.h\"\nint b();").unwrap();
+        let main = dir.join("main.c");
+        fs::write(
+            &main,
+            "#include \"b.h\"\n#include \"a.h\"\nint main() { return 0; }\n",
+        )
+        .unwrap();
+
+        let source = preprocess(&main).unwrap();
+        assert_eq!(
+            source.code,
+            "int a();\nint b();\nint a();\nint main() { return 0; }\n"
+        );
+        assert_eq!(
+            source.deps(Path::new("main.s")),
+            format!(
+                "main.s: {} {} {}",
+                main.display(),
+                dir.join("b.h").display(),
+                dir.join("a.h").display()
+            )
+        );
+        assert_eq!(source.locate(2), Some((dir.join("b.h").as_path(), 2)));
+        assert_eq!(source.locate(4), Some((main.as_path(), 3)));
+        assert_eq!(source.locate(5), None);
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn error_after_include() {
+        let dir = temp_dir("error_after_include");
+        fs::write(dir.join("a.h"), "int a();\nint b();\n").unwrap();
+        let main = dir.join("main.c");
+        fs::write(&main, "#include \"a.h\"\n\nint main() { return @; }\n").unwrap();
+
+        let source = preprocess(&main).unwrap();
+        let err = crate::lexer::Lexer::new()
+            .lex_str(&source.code)
+            .unwrap_err();
+        assert_eq!(err.pos.line, 4);
+        assert_eq!(source.locate(err.pos.line), Some((main.as_path(), 3)));
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn recursive_include() {
+        let dir = temp_dir("recursive_include");
+        fs::write(dir.join("a.h"), "#include \"b.h\"\n").unwrap();
+        fs::write(dir.join("b.h"), "#include \"a.h\"\n").unwrap();
+
+        let err = preprocess_str("#include \"a.h\"\n", &dir.join("main.c")).unwrap_err();
+        assert!(matches!(err, PreprocessError::RecursiveInclude(path) if path == dir.join("a.h")));
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn invalid_directives() {
+        let path = Path::new("main.c");
+        assert!(matches!(
+            preprocess_str("int a;\n#include <stdio.h>\n", path),
+            Err(PreprocessError::InvalidInclude { line: 2, .. })
+        ));
+        assert!(matches!(
+            preprocess_str("#pragma once\n", path),
+            Err(PreprocessError::UnknownDirective { directive, line: 1, .. }) if directive == "pragma"
+        ));
+        assert!(matches!(
+            preprocess_str("#include \"missing.h\"\n", path),
+            Err(PreprocessError::Io { .. })
+        ));
+        assert!(matches!(
+            preprocess_str("\n#define SQ(x) x * x\n", path),
+            Err(PreprocessError::InvalidDefine { line: 2, .. })
+        ));
+        assert!(matches!(
+            preprocess_str("#define 1 2\n", path),
+            Err(PreprocessError::InvalidDefine { line: 1, .. })
+        ));
+    }
+
+    #[test]
+    fn define() {
+        let path = Path::new("main.c");
+        let source = preprocess_str(
+            "#define SIZE 10\n#define TWICE SIZE + SIZE\nint main() { char c = 'SIZE'; return TWICE + SIZE_2; }\n",
+            path,
+        )
+        .unwrap();
+        assert_eq!(
+            source.code,
+            "\n\nint main() { char c = 'SIZE'; return 10 + 10 + SIZE_2; }\n"
+        );
+    }
+
+    #[test]
+    fn macro_recursion() {
+        let path = Path::new("main.c");
+        // a macro isn't expanded inside its own replacement
+        let source = preprocess_str("#define A A + 1\nint a = A;\n", path).unwrap();
+        assert_eq!(source.code, "\nint a = A + 1;\n");
+
+        let source =
+            preprocess_str("#define A B\n#define B A\nint main() { return A; }\n", path).unwrap();
+        assert_eq!(source.code, "\n\nint main() { return A; }\n");
+
+        let defines = (0..=MAX_EXPANSION_DEPTH)
+            .map(|i| format!("#define M{} M{}\n", i, i + 1))
+            .collect::<String>();
+        let err = preprocess_str(&format!("{}int a = M0;\n", defines), path).unwrap_err();
+        assert!(matches!(
+            &err,
+            PreprocessError::MacroRecursion { name, .. } if name == &format!("M{}", MAX_EXPANSION_DEPTH)
+        ));
+        assert!(err.to_string().ends_with("is nested too deeply"));
+    }
+}
@@ -1,7 +1,4 @@
-mod decl;
-mod tac;
 mod tokens;
 
-pub use decl::{pretty_prog};
-pub use tac::pretty as pretty_tac;
+pub use simple_c_compiler::il::pretty::pretty as pretty_tac;
 pub use tokens::pretty_tokens;
@@ -1,35 +1,30 @@
+use super::SemanticError;
 use crate::ast;
 use std::collections::{HashMap, HashSet};
 
-pub fn func_check(prog: &ast::Program) -> bool {
-    if !global_check(prog) {
-        return false;
-    }
-
+pub fn func_check(prog: &ast::Program) -> Result<(), SemanticError> {
+    global_check(prog)?;
     calls_precidence_check(prog)
 }
 
-fn global_check(prog: &ast::Program) -> bool {
+fn global_check(prog: &ast::Program) -> Result<(), SemanticError> {
     let mut functions: HashMap<String, &ast::FuncDecl> = HashMap::new();
     for top in &prog.0 {
         match top {
             ast::TopLevel::Function(func) => {
                 if let Some(f) = functions.get(&func.name) {
-                    // redefine function
-                    //
-                    // todo: not complete yet as if there's one signature and several realization we
-                    // possible miss it since we save only one function and then compare every one with this.
-                    //
-                    // possible solution is to store all functions in HashMap<String, Vec<&ast::FuncDecl>>
-                    // and after compare them
-                    //
-                    // In a way that multiply definitions is possible but implementations is not
-                    //
+                    // multiply declarations are possible but implementations are not
                     if f.blocks.is_some() && func.blocks.is_some() {
-                        return false;
+                        return Err(SemanticError::Redefinition(func.name.clone()));
                     }
                     if f.parameters.len() != func.parameters.len() {
-                        return false;
+                        return Err(SemanticError::ConflictingDeclaration(func.name.clone()));
+                    }
+
+                    // keep the definition if there's one
+                    // so the next definitions are recognized as redefinitions
+                    if func.blocks.is_some() {
+                        functions.insert(func.name.clone(), func);
                     }
                 } else {
                     functions.insert(func.name.clone(), func);
@@ -39,21 +34,25 @@ fn global_check(prog: &ast::Program) -> bool {
         }
     }
 
-    return true;
+    Ok(())
 }
 
-fn calls_precidence_check(prog: &ast::Program) -> bool {
+fn calls_precidence_check(prog: &ast::Program) -> Result<(), SemanticError> {
     let mut used_funcs = Vec::new();
     let mut declared_funcs = HashSet::new();
     for top in &prog.0 {
         match top {
             ast::TopLevel::Function(func) => {
                 declared_funcs.insert((&func.name, func.parameters.len()));
+                let pointers = func_pointers(func);
                 match &func.blocks {
                     Some(blocks) => {
                         for block in blocks {
                             let mut check = |exp: &ast::Exp| match exp {
-                                ast::Exp::FuncCall(name, params) => {
+                                // a call through a pointer is checked against its declaration
+                                ast::Exp::FuncCall(name, params, _)
+                                    if pointers.contains(&(name, params.len())) => {}
+                                ast::Exp::FuncCall(name, params, _) => {
                                     used_funcs.push((name.clone(), params.len()))
                                 }
                                 _ => (),
@@ -64,7 +63,7 @@ fn calls_precidence_check(prog: &ast::Program) -> bool {
 
                         for (f_name, param_size) in used_funcs.iter() {
                             if !declared_funcs.contains(&(&f_name, *param_size)) {
-                                return false;
+                                return Err(SemanticError::UndeclaredFunction(f_name.clone()));
                             }
                         }
                     }
@@ -75,7 +74,28 @@ fn calls_precidence_check(prog: &ast::Program) -> bool {
         }
     }
 
-    true
+    Ok(())
+}
+
+fn func_pointers(func: &ast::FuncDecl) -> HashSet<(&String, usize)> {
+    struct Pointers<'a>(HashSet<(&'a String, usize)>);
+
+    impl<'a> ast::Visitor<'a> for Pointers<'a> {
+        fn visit_decl(&mut self, decl: &'a ast::Declaration) {
+            if let ast::Declaration::FuncPointer {
+                name, parameters, ..
+            } = decl
+            {
+                self.0.insert((name, *parameters));
+            }
+
+            ast::visitor::visit_decl(self, decl);
+        }
+    }
+
+    let mut pointers = Pointers(HashSet::new());
+    ast::Visitor::visit_function(&mut pointers, func);
+    pointers.0
 }
 
 fn _block_check<F: FnMut(&ast::Exp)>(block: &ast::BlockItem, mut exp_call: &mut F) {
@@ -83,7 +103,8 @@ fn _block_check<F: FnMut(&ast::Exp)>(block: &ast::BlockItem, mut exp_call: &mut
         ast::BlockItem::Statement(s) => {
             _statement_check(s, exp_call);
         }
-        ast::BlockItem::Declaration(ast::Declaration::Declare { name, exp }) => {
+        ast::BlockItem::Declaration(ast::Declaration::Declare { exp, .. })
+        | ast::BlockItem::Declaration(ast::Declaration::FuncPointer { exp, .. }) => {
             if let Some(exp) = exp {
                 exp_call(exp);
             }
@@ -93,17 +114,17 @@ fn _block_check<F: FnMut(&ast::Exp)>(block: &ast::BlockItem, mut exp_call: &mut
 
 fn _statement_check<F: FnMut(&ast::Exp)>(s: &ast::Statement, mut exp_call: &mut F) {
     match s {
-        ast::Statement::Compound { list } => {
+        ast::Statement::Compound { list, .. } => {
             if let Some(list) = list {
                 for b in list {
                     _block_check(b, exp_call);
                 }
             }
         }
-        ast::Statement::Return { exp } => {
+        ast::Statement::Return { exp, .. } => {
             exp_call(exp);
         }
-        ast::Statement::Exp { exp } => {
+        ast::Statement::Exp { exp, .. } => {
             if let Some(exp) = exp {
                 exp_call(exp);
             }
@@ -113,6 +134,7 @@ fn _statement_check<F: FnMut(&ast::Exp)>(s: &ast::Statement, mut exp_call: &mut
             exp2,
             exp3,
             statement,
+            ..
         } => {
             if let Some(exp) = exp1 {
                 exp_call(exp);
@@ -128,10 +150,14 @@ fn _statement_check<F: FnMut(&ast::Exp)>(s: &ast::Statement, mut exp_call: &mut
             exp2,
             exp3,
             statement,
+            ..
         } => {
-            if let ast::Declaration::Declare { name, exp } = decl {
-                if let Some(exp) = exp {
-                    exp_call(exp);
+            match decl {
+                ast::Declaration::Declare { exp, .. }
+                | ast::Declaration::FuncPointer { exp, .. } => {
+                    if let Some(exp) = exp {
+                        exp_call(exp);
+                    }
                 }
             }
             if let Some(exp) = exp3 {
@@ -140,11 +166,11 @@ fn _statement_check<F: FnMut(&ast::Exp)>(s: &ast::Statement, mut exp_call: &mut
             exp_call(exp2);
             _statement_check(statement, exp_call);
         }
-        ast::Statement::While { exp, statement } => {
+        ast::Statement::While { exp, statement, .. } => {
             exp_call(exp);
             _statement_check(statement, exp_call);
         }
-        ast::Statement::Do { statement, exp } => {
+        ast::Statement::Do { statement, exp, .. } => {
             exp_call(exp);
             _statement_check(statement, exp_call);
         }
@@ -152,6 +178,7 @@ fn _statement_check<F: FnMut(&ast::Exp)>(s: &ast::Statement, mut exp_call: &mut
             cond_expr,
             else_block,
             if_block,
+            ..
         } => {
             exp_call(cond_expr);
             if let Some(s) = else_block {
@@ -159,6 +186,42 @@ fn _statement_check<F: FnMut(&ast::Exp)>(s: &ast::Statement, mut exp_call: &mut
             }
             _statement_check(if_block, exp_call);
         }
+        ast::Statement::Switch { exp, cases, .. } => {
+            exp_call(exp);
+            for case in cases {
+                for b in &case.body {
+                    _block_check(b, exp_call);
+                }
+            }
+        }
         _ => {}
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{lexer::Lexer, parser};
+
+    fn check(program: &str) -> Result<(), SemanticError> {
+        let tokens = Lexer::new().lex_str(program).unwrap();
+        let ast = parser::parse(tokens).unwrap();
+        func_check(&ast)
+    }
+
+    #[test]
+    fn conflicting_declaration() {
+        assert_eq!(
+            check("int f(int a); int f(int a, int b) { return a + b; }"),
+            Err(SemanticError::ConflictingDeclaration("f".to_owned()))
+        );
+        assert_eq!(
+            check("int f(int a); int f(int a) { return a; } int main() { return f(1); }"),
+            Ok(())
+        );
+        assert_eq!(
+            check("int f(); int f() { return 1; } int f() { return 2; }"),
+            Err(SemanticError::Redefinition("f".to_owned()))
+        );
+    }
+}
@@ -5,7 +5,7 @@ pub fn multi_definition(prog: &ast::Program) -> bool {
         .0
         .iter()
         .flat_map(|top| match top {
-            ast::TopLevel::Declaration(ast::Declaration::Declare { name, exp })
+            ast::TopLevel::Declaration(ast::Declaration::Declare { name, exp, .. })
                 if exp.is_some() =>
             {
                 Some(name.clone())
@@ -62,7 +62,7 @@ pub fn use_before_definition(prog: &ast::Program) -> bool {
         }
 
         fn visit_expr(&mut self, exp: &'a ast::Exp) {
-            if let ast::Exp::Var(name) = exp {
+            if let ast::Exp::Var(name, _) = exp {
                 if self.globals.contains(name) && !self.definitions.contains(name) {
                     self.issue = true;
                 }
@@ -88,9 +88,7 @@ pub fn use_before_definition(prog: &ast::Program) -> bool {
         issue: false,
         globals: vars,
     };
-    for item in &prog.0 {
-        visitor.visit_global_item(item);
-    }
+    ast::walk_program(&mut visitor, prog);
 
     !visitor.issue
 }
@@ -1,2 +1,72 @@
 pub mod function_checks;
 pub mod global_vars;
+pub mod scope_checks;
+pub mod shift_checks;
+pub mod switch_checks;
+
+use crate::lexer::Pos;
+use std::error;
+use std::fmt;
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum SemanticError {
+    /// ConflictingDeclaration is raised when function's signatures don't match.
+    ConflictingDeclaration(String),
+    /// Redefinition is raised when a function is defined with a body more than once.
+    Redefinition(String),
+    /// UndeclaredFunction is raised when a call refers to a function
+    /// which isn't declared above with the same number of parameters.
+    UndeclaredFunction(String),
+    /// UndeclaredVariable is raised when a variable is used out of a scope it's declared in.
+    UndeclaredVariable(String, Pos),
+    /// GlobalNameConflict is raised when a global variable is named after a function.
+    GlobalNameConflict,
+    /// GlobalRedefinition is raised when a global variable is initialized more than once.
+    GlobalRedefinition,
+    /// UseBeforeDeclaration is raised when a function uses a global variable declared below it.
+    UseBeforeDeclaration,
+    /// CaseOutOfRange is raised when a case label doesn't fit in int.
+    CaseOutOfRange(i64),
+    /// DuplicateCase is raised when a switch has two case labels with the same value,
+    /// the value is None for `default` labels.
+    DuplicateCase(Option<i64>),
+}
+
+impl fmt::Display for SemanticError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SemanticError::ConflictingDeclaration(name) => {
+                write!(f, "conflicting types for function {:?}", name)
+            }
+            SemanticError::Redefinition(name) => write!(f, "redefinition of function {:?}", name),
+            SemanticError::UndeclaredFunction(name) => {
+                write!(f, "call of undeclared function {:?}", name)
+            }
+            SemanticError::UndeclaredVariable(name, pos) => write!(
+                f,
+                "use of undeclared variable {:?} at {}:{}",
+                name, pos.line, pos.column
+            ),
+            SemanticError::GlobalNameConflict => {
+                write!(f, "global variable can not have the same name as function")
+            }
+            SemanticError::GlobalRedefinition => write!(f, "global variable defined several times"),
+            SemanticError::UseBeforeDeclaration => write!(f, "usage before declaration"),
+            SemanticError::CaseOutOfRange(value) => {
+                write!(f, "case label value {} is out of range of int", value)
+            }
+            SemanticError::DuplicateCase(Some(value)) => {
+                write!(f, "duplicate case value {}", value)
+            }
+            SemanticError::DuplicateCase(None) => {
+                write!(f, "multiple default labels in one switch")
+            }
+        }
+    }
+}
+
+impl error::Error for SemanticError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        None
+    }
+}
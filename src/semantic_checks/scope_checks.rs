@@ -0,0 +1,152 @@
+use super::SemanticError;
+use crate::ast::{self, Visitor};
+use crate::lexer::Pos;
+use std::collections::HashSet;
+
+/// var_check verifies that every variable is used within a scope it's declared in.
+pub fn var_check(prog: &ast::Program) -> Result<(), SemanticError> {
+    let mut scopes = Scopes {
+        scopes: vec![HashSet::new()],
+        issue: None,
+    };
+    ast::walk_program(&mut scopes, prog);
+
+    match scopes.issue {
+        Some((name, pos)) => Err(SemanticError::UndeclaredVariable(name, pos)),
+        None => Ok(()),
+    }
+}
+
+struct Scopes<'a> {
+    scopes: Vec<HashSet<&'a str>>,
+    issue: Option<(String, Pos)>,
+}
+
+impl<'a> Scopes<'a> {
+    fn scoped<F: FnOnce(&mut Self)>(&mut self, f: F) {
+        self.scopes.push(HashSet::new());
+        f(self);
+        self.scopes.pop();
+    }
+
+    fn declare(&mut self, name: &'a str) {
+        self.scopes.last_mut().unwrap().insert(name);
+    }
+
+    fn check(&mut self, name: &str, pos: &Pos) {
+        let declared = self.scopes.iter().any(|scope| scope.contains(name));
+        if !declared && self.issue.is_none() {
+            self.issue = Some((name.to_owned(), pos.clone()));
+        }
+    }
+}
+
+impl<'a> Visitor<'a> for Scopes<'a> {
+    fn visit_global_item(&mut self, item: &'a ast::TopLevel) {
+        match item {
+            ast::TopLevel::Declaration(decl) => self.visit_decl(decl),
+            ast::TopLevel::Function(func) => self.visit_function(func),
+        }
+    }
+
+    fn visit_function(&mut self, func: &'a ast::FuncDecl) {
+        // a function name designates the function so it may be assigned to a function pointer
+        self.declare(&func.name);
+        self.scoped(|s| {
+            for p in &func.parameters {
+                s.declare(&p.name);
+            }
+
+            ast::visitor::visit_function(s, func);
+        });
+    }
+
+    fn visit_statement(&mut self, st: &'a ast::Statement) {
+        match st {
+            ast::Statement::Compound { .. }
+            | ast::Statement::ForDecl { .. }
+            | ast::Statement::Switch { .. } => {
+                self.scoped(|s| ast::visitor::visit_statement(s, st))
+            }
+            _ => ast::visitor::visit_statement(self, st),
+        }
+    }
+
+    fn visit_decl(&mut self, decl: &'a ast::Declaration) {
+        // the initializer is checked before the name is declared
+        ast::visitor::visit_decl(self, decl);
+
+        match decl {
+            ast::Declaration::Declare { name, .. }
+            | ast::Declaration::FuncPointer { name, .. } => self.declare(name),
+        }
+    }
+
+    fn visit_expr(&mut self, exp: &'a ast::Exp) {
+        match exp {
+            ast::Exp::Var(name, pos)
+            | ast::Exp::Assign(name, _, pos)
+            | ast::Exp::AssignOp(name, _, _, pos)
+            | ast::Exp::IncOrDec(name, _, pos) => self.check(name, pos),
+            _ => (),
+        }
+
+        ast::visitor::visit_expr(self, exp);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{lexer::Lexer, parser};
+
+    fn check(program: &str) -> Result<(), String> {
+        let tokens = Lexer::new().lex_str(program).unwrap();
+        let ast = parser::parse(tokens).unwrap();
+        var_check(&ast).map_err(|err| err.to_string())
+    }
+
+    #[test]
+    fn for_scope() {
+        assert_eq!(
+            check(
+                "int main() { int sum = 0; for (int i = 0; i < 10; i++) { int t = i * 2; sum += t; } return sum; }"
+            ),
+            Ok(())
+        );
+        assert_eq!(
+            check("int main() { for (int i = 0; i < 10; i++) ; return i; }"),
+            Err("use of undeclared variable \"i\" at 1:52".to_owned())
+        );
+        assert_eq!(
+            check("int main() { for (int i = 0; i < 10; i++) { int t = i; } return t; }"),
+            Err("use of undeclared variable \"t\" at 1:65".to_owned())
+        );
+    }
+
+    #[test]
+    fn undeclared_variable() {
+        assert_eq!(check("int g = 1; int f(int a) { return a + g; }"), Ok(()));
+        assert_eq!(check("int main() { int a = 1; { return a; } }"), Ok(()));
+        assert_eq!(
+            check("int main() { { int a = 1; } return a; }"),
+            Err("use of undeclared variable \"a\" at 1:36".to_owned())
+        );
+        assert_eq!(
+            check("int main() { a = 1; return 0; }"),
+            Err("use of undeclared variable \"a\" at 1:14".to_owned())
+        );
+    }
+
+    #[test]
+    fn function_designator() {
+        assert_eq!(
+            check("int t(int a); int (*fp)(int) = t; int main() { int (*f)(int) = t; return 0; }"),
+            Ok(())
+        );
+        assert_eq!(
+            check("int main() { int (*f)(int) = t; return 0; } int t(int a) { return a; }"),
+            Err("use of undeclared variable \"t\" at 1:30".to_owned())
+        );
+    }
+}
@@ -0,0 +1,69 @@
+use crate::ast;
+
+// INT_WIDTH is a width of int in bits
+const INT_WIDTH: i64 = 32;
+
+/// shift_width_check verifies that constant shift counts aren't negative
+/// and are less than the width of int.
+///
+/// Such shifts are undefined in C, x86 masks the count to 5 bits.
+pub fn shift_width_check(prog: &ast::Program) -> bool {
+    use ast::Visitor;
+    struct Shifts {
+        issue: bool,
+    }
+
+    impl<'a> Visitor<'a> for Shifts {
+        fn visit_expr(&mut self, exp: &'a ast::Exp) {
+            let count = match exp {
+                ast::Exp::BinOp(ast::BinOp::BitwiseLeftShift, _, count, _)
+                | ast::Exp::BinOp(ast::BinOp::BitwiseRightShift, _, count, _)
+                | ast::Exp::AssignOp(_, ast::AssignmentOp::BitLeftShift, count, _)
+                | ast::Exp::AssignOp(_, ast::AssignmentOp::BitRightShift, count, _) => Some(count),
+                _ => None,
+            };
+
+            // a negative count is parsed as a negation of a literal
+            let count = match count.map(|c| c.as_ref()) {
+                Some(ast::Exp::Const(ast::Const::Int(count), _)) => Some(*count),
+                Some(ast::Exp::UnOp(ast::UnOp::Negation, count, _)) => match count.as_ref() {
+                    ast::Exp::Const(ast::Const::Int(count), _) => Some(count.wrapping_neg()),
+                    _ => None,
+                },
+                _ => None,
+            };
+            if matches!(count, Some(count) if count < 0 || count >= INT_WIDTH) {
+                self.issue = true;
+            }
+
+            ast::visitor::visit_expr(self, exp);
+        }
+    }
+
+    let mut visitor = Shifts { issue: false };
+    ast::walk_program(&mut visitor, prog);
+
+    !visitor.issue
+}
+
+#[cfg(test)]
+mod tests {
+    use super::shift_width_check;
+    use crate::lexer::Lexer;
+    use crate::parser;
+
+    fn check(program: &str) -> bool {
+        let tokens = Lexer::new().lex_str(program).unwrap();
+        let ast = parser::parse(tokens).unwrap();
+        shift_width_check(&ast)
+    }
+
+    #[test]
+    fn shift_width() {
+        assert!(check("int main() { int x = 1; return x << 31; }"));
+        assert!(!check("int main() { int x = 1; return x << 33; }"));
+        assert!(!check("int main() { int x = 1; x >>= 32; return x; }"));
+        assert!(!check("int main() { int x = 1; return x << -1; }"));
+        assert!(!check("int main() { int x = 1; x >>= -1; return x; }"));
+    }
+}
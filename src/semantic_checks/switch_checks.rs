@@ -0,0 +1,74 @@
+use super::SemanticError;
+use crate::ast::{self, Visitor};
+use std::collections::HashSet;
+use std::convert::TryFrom;
+
+/// case_check verifies that case labels of a switch fit in int and aren't repeated.
+pub fn case_check(prog: &ast::Program) -> Result<(), SemanticError> {
+    struct Cases {
+        issue: Option<SemanticError>,
+    }
+
+    impl<'a> Visitor<'a> for Cases {
+        fn visit_statement(&mut self, st: &'a ast::Statement) {
+            if let ast::Statement::Switch { cases, .. } = st {
+                let mut labels = HashSet::new();
+                for case in cases {
+                    let issue = match case.value {
+                        Some(value) if i32::try_from(value).is_err() => {
+                            Some(SemanticError::CaseOutOfRange(value))
+                        }
+                        value if !labels.insert(value) => Some(SemanticError::DuplicateCase(value)),
+                        _ => None,
+                    };
+
+                    if self.issue.is_none() {
+                        self.issue = issue;
+                    }
+                }
+            }
+
+            ast::visitor::visit_statement(self, st);
+        }
+    }
+
+    let mut visitor = Cases { issue: None };
+    ast::walk_program(&mut visitor, prog);
+
+    match visitor.issue {
+        Some(err) => Err(err),
+        None => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{lexer::Lexer, parser};
+
+    fn check(program: &str) -> Result<(), SemanticError> {
+        let tokens = Lexer::new().lex_str(program).unwrap();
+        let ast = parser::parse(tokens).unwrap();
+        case_check(&ast)
+    }
+
+    #[test]
+    fn case_labels() {
+        assert_eq!(
+            check("int main() { switch (1) { case -2147483648: case 2147483647: default: return 1; } return 0; }"),
+            Ok(())
+        );
+        assert_eq!(
+            check("int main() { switch (1) { case 2147483648: return 1; } return 0; }"),
+            Err(SemanticError::CaseOutOfRange(2147483648))
+        );
+        assert_eq!(
+            check("int main() { switch (1) { case 1: return 1; case 2: case 1: return 2; } return 0; }"),
+            Err(SemanticError::DuplicateCase(Some(1)))
+        );
+        assert_eq!(
+            check("int main() { for (;;) switch (1) { default: break; default: return 1; } }"),
+            Err(SemanticError::DuplicateCase(None))
+        );
+    }
+}
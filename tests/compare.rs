@@ -3,39 +3,93 @@ pub mod gcc {
         assert_eq!(compile_gcc_expr(&code), compile_code(&code));
     }
 
+    /// compare_code_with is the same as compare_code
+    /// but the compiler is run with the given arguments.
+    pub fn compare_code_with(code: &str, args: &[&str]) {
+        assert_eq!(compile_gcc_expr(&code), compile_code_with(&code, args));
+    }
+
     pub fn compare_expr(expr: &str) {
         let code = format!("int main(){{ {} }}", expr);
         assert_eq!(compile_gcc_expr(&code), compile_code(&code));
     }
 
+    /// compare_expr_in_crate is the same as compare_expr
+    /// but the code is compiled by the library instead of the binary.
+    pub fn compare_expr_in_crate(expr: &str) {
+        let code = format!("int main(){{ {} }}", expr);
+        assert_eq!(compile_gcc_expr(&code), compile_in_crate(&code));
+    }
+
     pub fn compile_expr(exp: &str) -> usize {
         let code = format!("int main(){{ return {} }}", exp);
         compile_code(&code)
     }
 
     pub fn compile_code(code: &str) -> usize {
+        compile_code_with(code, &[])
+    }
+
+    pub fn compile_code_with(code: &str, args: &[&str]) -> usize {
+        compile_files_with(&[code], args)
+    }
+
+    pub fn compile_files_with(files: &[&str], args: &[&str]) -> usize {
         use std::io::Write;
 
-        let code_file = random_name("code_", ".c");
-        let mut file = std::fs::File::create(&code_file).unwrap();
-        file.write_all(code.as_bytes()).unwrap();
+        let code_files = files
+            .iter()
+            .map(|code| {
+                let code_file = random_name("code_", ".c");
+                let mut file = std::fs::File::create(&code_file).unwrap();
+                file.write_all(code.as_bytes()).unwrap();
+                code_file
+            })
+            .collect::<Vec<_>>();
 
         let asm_file = random_name("asm_", ".s");
-        let bin_file = random_name("bin_", ".out");
 
         let compiler = std::process::Command::new("./target/debug/simple-c-compiler")
-            .arg(&code_file)
+            .args(&code_files)
             .arg("-o")
             .arg(&asm_file)
+            .args(args)
             .output()
             .expect("start compilation process");
         if !compiler.status.success() {
-            println!("{:?}", code_file);
+            println!("{:?}", code_files);
             panic!();
         }
 
+        for code_file in code_files {
+            std::fs::remove_file(code_file).unwrap();
+        }
+
+        run_asm(&asm_file)
+    }
+
+    /// compile_in_crate compiles the code by `simple_c_compiler::compile`,
+    /// runs the program and returns its exit code.
+    pub fn compile_in_crate(code: &str) -> usize {
+        use simple_c_compiler::{compile, generator::syntax::GASM, lexer::Lexer, parser, Options};
+
+        let tokens = Lexer::new().lex_str(code).unwrap();
+        let program = parser::parse(tokens).unwrap();
+        let compilation = compile::<GASM>(program, &Options::default()).unwrap();
+
+        let asm_file = random_name("asm_", ".s");
+        std::fs::write(&asm_file, compilation.asm).unwrap();
+
+        run_asm(&asm_file)
+    }
+
+    /// run_asm assembles the file, runs the program and returns its exit code.
+    /// The assembly file is removed afterwards.
+    fn run_asm(asm_file: &str) -> usize {
+        let bin_file = random_name("bin_", ".out");
+
         let gcc = std::process::Command::new("gcc")
-            .args(&["-m64", "-o", &bin_file, &asm_file])
+            .args(&["-m64", "-o", &bin_file, asm_file])
             .output()
             .expect("Run gcc to compile asm")
             .status;
@@ -50,7 +104,6 @@ pub mod gcc {
             .expect("Run compiled programm")
             .status;
 
-        std::fs::remove_file(code_file).unwrap();
         std::fs::remove_file(asm_file).unwrap();
         std::fs::remove_file(bin_file).unwrap();
 
@@ -42,6 +42,13 @@ fn bit_operations() {
 
     gcc::compare_expr("return 1 ^ 2;");
     gcc::compare_expr("return 2 ^ 2;");
+
+    gcc::compare_expr("return 1 | 2 ^ 3 & 4;");
+    gcc::compare_expr("return 6 & 3 ^ 5 | 8;");
+    gcc::compare_expr("return 1 + 2 << 3;");
+    gcc::compare_expr("return 2 * 3 >> 1;");
+    gcc::compare_expr("return 1 << 2 < 5;");
+    gcc::compare_expr("return 3 & 1 == 1;");
 }
 
 #[test]
@@ -49,6 +56,8 @@ fn unary_operations() {
     gcc::compare_expr("return !1;");
     gcc::compare_expr("return ~1;");
     gcc::compare_expr("return -1;");
+    gcc::compare_expr("return !1 || 1;");
+    gcc::compare_expr("return -1 || 0;");
 }
 
 #[test]
@@ -87,6 +96,7 @@ fn increment_operations() {
     gcc::compare_expr("int a = 1; return a++;");
     gcc::compare_expr("int a = 1; a++; return a;");
     gcc::compare_expr("int a = 1; ++a; return a;");
+    gcc::compare_expr("int a = 1; int b = (a)++; return b + ++(a);");
 }
 
 #[test]
@@ -123,3 +133,68 @@ fn assign_operations() {
     gcc::compare_expr("int a = 2; return a ^= 1;");
     gcc::compare_expr("int a = 2; a ^= 1; return a;");
 }
+
+#[test]
+fn nested_assignment() {
+    gcc::compare_expr("int a = 0; int b = 2; int c = 1; a = b + (c = 5); return a;");
+    gcc::compare_expr("int a = 0; int b = 2; int c = 1; a = b + (c = 5); return c;");
+    gcc::compare_expr("int a = 0; int c = 1; a = c = 5; return a + c;");
+    gcc::compare_expr("int a; int b; int c; a = b = c = 3; return a * 100 + b * 10 + c;");
+    gcc::compare_expr("int a = 1; int b = 2; a += b = 3; return a * 10 + b;");
+    gcc::compare_expr("int a = 3; int c = 1; a += (c = 5) * 2; return a * 10 + c;");
+}
+
+#[test]
+fn integer_literals() {
+    gcc::compare_expr("return 0;");
+    gcc::compare_expr("return 010;");
+    gcc::compare_expr("return 0b1010;");
+    gcc::compare_expr("return 0x1f;");
+
+    assert_eq!(8, gcc::compile_expr("0o10;"));
+}
+
+#[test]
+fn negated_compare_operations() {
+    gcc::compare_expr("int a = 1; int b = 2; return !(a < b);");
+    gcc::compare_expr("int a = 1; int b = 2; return !(a <= b);");
+    gcc::compare_expr("int a = 1; int b = 2; return !(a > b);");
+    gcc::compare_expr("int a = 1; int b = 2; return !(a >= b);");
+    gcc::compare_expr("int a = 1; int b = 2; return !(a == b);");
+    gcc::compare_expr("int a = 1; int b = 2; return !(a != b);");
+}
+
+#[test]
+fn shift_operations() {
+    gcc::compare_expr("int a = 5; return a << 3;");
+    gcc::compare_expr("int a = 40; return a >> 3;");
+
+    gcc::compare_expr("int a = 1; return a << 33;");
+    gcc::compare_expr("int a = 64; return a >> 33;");
+}
+
+#[test]
+fn bitwise_negative_operands() {
+    gcc::compare_expr("return -1 & 255;");
+    gcc::compare_expr("int a = -1; return a & 255;");
+    gcc::compare_expr("int a = -8; int b = 7; return (a | b) + 10;");
+    gcc::compare_expr("int a = -1; int b = 3; return (a ^ b) + 10;");
+    gcc::compare_expr("int a = -1; return (a >> 1) + 2;");
+    gcc::compare_expr("return ~0 + 2;");
+}
+
+#[test]
+fn relational_in_arithmetic() {
+    gcc::compare_expr("int a = 5; int b = 3; int c = 1; int d = 2; return (a > b) * 10 + (c < d);");
+    gcc::compare_expr("int a = 300; int b = 3; return (a != b) * 7 - (a <= b) + (b >= a) * 2;");
+    gcc::compare_expr("int a = 5; return (a > 2) * 10 + (3 < a);");
+    gcc::compare_expr("int a = 5; int b = (a > 2); return b * 10 + (a == 5) * 3;");
+}
+
+#[test]
+fn negative_literals() {
+    gcc::compare_expr("return -2147483648;");
+    gcc::compare_expr("int a = -2147483648; return a == -2147483647 - 1;");
+    gcc::compare_expr("return -9223372036854775808L;");
+    assert_eq!(0, gcc::compile_code_with("int main() { return -2147483648; }", &["-O"]));
+}
@@ -3,31 +3,31 @@ use compare::gcc;
 
 #[test]
 fn if_statement() {
-    gcc::compare_expr(r"
+    gcc::compare_expr_in_crate(r"
         int a = 10;
         if (a > 0) return 10;
         else
             return 20;
     ");
 
-    gcc::compare_expr(r"
+    gcc::compare_expr_in_crate(r"
         int a = 10;
         if (a > 0) return 10;
         return 20;
     ");
 
-    gcc::compare_expr(r"
+    gcc::compare_expr_in_crate(r"
         int a = 10;
         if (a > 0) return 10;
     ");
 
-    gcc::compare_expr(r"
+    gcc::compare_expr_in_crate(r"
         int a = 10;
         if (a > 0) { a = 20; }
         return 20;
     ");
 
-    gcc::compare_expr(r"
+    gcc::compare_expr_in_crate(r"
         int a = -1;
         if (a > 0) {
             a = 20;
@@ -40,7 +40,7 @@ fn if_statement() {
         return a;
     ");
 
-    gcc::compare_expr(r"
+    gcc::compare_expr_in_crate(r"
         if (1) {
             return 201;
         } else if (0) {
@@ -48,6 +48,17 @@ fn if_statement() {
         } else {
         }
     ");
+
+    gcc::compare_expr_in_crate(r"
+        int a = 1;
+        int b = 0;
+        if (a)
+            if (b)
+                return 1;
+            else
+                return 2;
+        return 3;
+    ");
 }
 
 #[test]
@@ -102,6 +113,35 @@ fn while_statement() {
     ");
 }
 
+#[test]
+fn do_statement() {
+    gcc::compare_expr(r"
+        int i = 0;
+        do {
+            i++;
+        } while (i < 10);
+        return i;
+    ");
+
+    gcc::compare_expr(r"
+        int i = 100;
+        do i++; while (i < 10);
+        return i;
+    ");
+
+    gcc::compare_expr(r"
+        int i = 0;
+        int sum = 0;
+        do {
+            i++;
+            if (i % 2 == 0)
+                continue;
+            sum += i;
+        } while (i < 10);
+        return sum;
+    ");
+}
+
 #[test]
 fn for_statement() {
     gcc::compare_expr(r"
@@ -160,6 +200,16 @@ fn for_statement() {
                 break;        
         return i;
     ");
+
+    gcc::compare_expr(r"
+        int sum = 0;
+        int t = 100;
+        for (int i = 0; i < 10; i++) {
+            int t = i * 2;
+            sum += t;
+        }
+        return sum + t;
+    ");
 }
 
 #[test]
@@ -294,3 +344,387 @@ fn libc_call() {
         }
     ");
 }
+
+#[test]
+fn empty_fn() {
+    assert_eq!(
+        0,
+        gcc::compile_code(r"
+            int f() {}
+
+            int main() {
+                return f();
+            }
+        ")
+    );
+}
+
+#[test]
+fn simple_loops() {
+    assert_eq!(
+        87,
+        gcc::compile_code_with(
+            r"
+            int main() {
+                int sum = 0;
+                for(int i = 0; i < 10; i++) {
+                    if(i == 3)
+                        continue;
+                    sum += i;
+                }
+
+                int i = 0;
+                while(i < 10)
+                    sum += i++;
+
+                return sum;
+            }
+        ",
+            &["--simple-loops"],
+        )
+    );
+}
+
+#[test]
+fn conditional_expression_composition() {
+    gcc::compare_code(r"
+        int add(int a, int b) { return a + b; }
+
+        int main() {
+            int c = 1;
+            return add(c ? 1 : 2, c == 0 ? 10 : 20);
+        }
+    ");
+
+    gcc::compare_expr(r"
+        int c = 1;
+        int sum = 0;
+        for (int i = 0; i < (c ? 5 : 50); i++)
+            sum += i;
+        return sum;
+    ");
+
+    gcc::compare_expr(r"
+        int c = 1;
+        int sum = 0;
+        for (int i = 0; i < 5; i++)
+            sum += c > 0 ? i : -i;
+        return sum;
+    ");
+}
+
+#[test]
+fn tail_call() {
+    // the recursion is too deep to be done without tail calls
+    assert_eq!(
+        128,
+        gcc::compile_code_with(
+            r"
+            int sum(int n, int acc) {
+                if (n == 0)
+                    return acc;
+                return sum(n - 1, acc + 1);
+            }
+
+            int main() {
+                return sum(10000000, 0);
+            }
+        ",
+            &["-O"],
+        )
+    );
+}
+
+#[test]
+fn tail_call_return_type() {
+    // the result of the callee has to be extended to the caller's return type
+    gcc::compare_code_with(
+        r"
+        int g(int x) { return x - 256; }
+        long f(int x) { return g(x); }
+
+        int main() {
+            return f(0) == -256;
+        }
+    ",
+        &["-O"],
+    );
+}
+
+#[test]
+fn spilled_temporaries() {
+    // there are more sums live at once than registers to keep them
+    gcc::compare_code(r"
+        int f(int a, int b, int c, int d, int e, int g, int h, int k) {
+            return (a + b) - ((c + d) - ((e + g) - ((h + k) - ((a + c) - ((b + d) - ((e + h) - (g + k)))))));
+        }
+
+        int main() {
+            return f(1, 2, 3, 4, 5, 6, 7, 8);
+        }
+    ");
+}
+
+#[test]
+fn live_across_call() {
+    gcc::compare_code(r"
+        int id(int x) { return x; }
+        int sub(int a, int b) { return a - b; }
+
+        int main() {
+            int a = 1 + id(2);
+            int b = a * 3;
+            return a + sub(b, a) + b;
+        }
+    ");
+}
+
+#[test]
+fn multiple_files() {
+    assert_eq!(
+        42,
+        gcc::compile_files_with(
+            &[
+                r"
+                int helper(int a) {
+                    return a * 2;
+                }
+            ",
+                r"
+                int helper(int a);
+
+                int main() {
+                    return helper(21);
+                }
+            ",
+            ],
+            &[],
+        )
+    );
+}
+
+#[test]
+fn zero_init_locals() {
+    assert_eq!(
+        0,
+        gcc::compile_code_with(
+            r"
+            int dirty() {
+                int a = 42;
+                return a;
+            }
+
+            int f() {
+                int x;
+                return x;
+            }
+
+            int main() {
+                dirty();
+                return f();
+            }
+        ",
+            &["--ftrivial-auto-var-init=zero"],
+        )
+    );
+}
+
+#[test]
+fn omit_frame_pointer() {
+    assert_eq!(
+        20,
+        gcc::compile_code_with(
+            r"
+            int sum(int n) {
+                int acc = 0;
+                for (int i = 1; i <= n; i++)
+                    acc += i;
+                return acc;
+            }
+
+            int main() {
+                int a = 5;
+                return sum(a) + a;
+            }
+        ",
+            &["--fomit-frame-pointer"],
+        )
+    );
+}
+
+#[test]
+fn return_type() {
+    gcc::compare_code(r"
+        char narrow() { return 300; }
+        long wide() { return -1; }
+
+        int main() {
+            return narrow() + wide();
+        }
+    ");
+}
+
+#[test]
+fn function_pointer() {
+    gcc::compare_code(r"
+        int twice(int a) { return a * 2; }
+        int inc(int a) { return a + 1; }
+
+        int main() {
+            int (*fp)(int) = &twice;
+            int a = fp(3);
+            fp = &inc;
+            return a + fp(a);
+        }
+    ");
+}
+
+#[test]
+fn return_comma() {
+    gcc::compare_code(r"
+        int called = 0;
+
+        int f() {
+            called = 1;
+            return 7;
+        }
+
+        int main() {
+            return (f(), 42);
+        }
+    ");
+
+    gcc::compare_code(r"
+        int called = 0;
+
+        int f() {
+            called = 1;
+            return 7;
+        }
+
+        int main() {
+            return (f(), called * 42);
+        }
+    ");
+
+    gcc::compare_expr(r"
+        int a = 1;
+        return (a = a + 1, a * 10);
+    ");
+}
+
+#[test]
+fn comma_in_for() {
+    gcc::compare_expr(r"
+        int i;
+        int j;
+        int steps = 0;
+        for (i = 0, j = 10; i < j; i = i + 1, j = j - 1)
+            steps++;
+        return steps * 10 + i;
+    ");
+
+    gcc::compare_expr(r"
+        int a = 1;
+        int b;
+        a = a + 1, b = a * 3;
+        return b;
+    ");
+}
+
+#[test]
+fn assignment_condition() {
+    gcc::compare_code(r"
+        int n = 5;
+
+        int f() {
+            n = n - 1;
+            return n;
+        }
+
+        int main() {
+            int x;
+            int sum = 0;
+            while ((x = f()) > 0)
+                sum += x;
+
+            for (n = 3; (x = f()) != 0;)
+                sum += x * 10;
+
+            return sum;
+        }
+    ");
+}
+
+#[test]
+fn switch_in_loop() {
+    gcc::compare_expr(r"
+        int sum = 0;
+        for (int i = 0; i < 10; i++) {
+            switch (i % 3) {
+            case 0:
+                sum += 1;
+                break;
+            case 1:
+                sum += 10;
+            case 2:
+                sum += 100;
+                break;
+            }
+            sum += 2;
+        }
+        return sum % 256;
+    ");
+
+    gcc::compare_expr(r"
+        int sum = 0;
+        int i = 0;
+        while (i < 8) {
+            switch (i++) {
+            case -1:
+                return 1;
+            case 3:
+                continue;
+            default:
+                sum += i;
+                break;
+            case 5:
+                sum += 100;
+            }
+            sum += 1;
+        }
+        return sum;
+    ");
+}
+
+#[test]
+fn stack_arguments() {
+    gcc::compare_code(r"
+        int g(int a, int b, int c, int d, int e, int f, int h, int i) {
+            return a + b + c + d + e + f + h * 10 - i;
+        }
+
+        int main() {
+            int x = 8;
+            return g(1, 2, 3, 4, 5, 6, 7, x);
+        }
+    ");
+}
+
+#[test]
+fn assign_parameter() {
+    gcc::compare_code(r"
+        int f(int a) {
+            int s = 0;
+            while (a) {
+                a = a - 1;
+                s = s + 2;
+            }
+            return s;
+        }
+
+        int main() {
+            return f(3);
+        }
+    ");
+}